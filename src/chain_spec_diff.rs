@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use serde_json::{Map, Value};
+use crate::chain_spec_validate::{genesis_config, runtime_code};
+use crate::hashing::{blake2_256, from_hex, to_hex, twox_128};
+
+const MAX_VALUE_LEN: usize = 66;
+
+// Pallets and storage items commonly found in system parachain runtimes
+const KNOWN_STORAGE: &[(&str, &[&str])] = &[
+    ("System", &["Account", "ExtrinsicCount", "BlockWeight", "AllExtrinsicsLen", "BlockHash", "ExtrinsicData", "Number", "ParentHash", "Digest", "Events", "EventCount", "EventTopics", "LastRuntimeUpgrade", "UpgradedToU32RefCount", "UpgradedToTripleRefCount", "ExecutionPhase", "InherentsApplied"]),
+    ("Balances", &["TotalIssuance", "InactiveIssuance", "Account", "Locks", "Reserves", "Holds", "Freezes"]),
+    ("Sudo", &["Key"]),
+    ("Timestamp", &["Now", "DidUpdate"]),
+    ("TransactionPayment", &["NextFeeMultiplier", "StorageVersion"]),
+    ("ParachainInfo", &["ParachainId"]),
+    ("ParachainSystem", &["LastRelayChainBlockNumber", "HostConfiguration", "UpgradeRestrictionSignal"]),
+    ("Aura", &["Authorities", "CurrentSlot"]),
+    ("AuraExt", &["Authorities", "SlotInfo"]),
+    ("Session", &["Validators", "CurrentIndex", "QueuedChanged", "QueuedKeys", "DisabledValidators", "NextKeys", "KeyOwner"]),
+    ("CollatorSelection", &["Invulnerables", "CandidateList", "LastAuthoredBlock", "DesiredCandidates", "CandidacyBond"]),
+    ("Authorship", &["Author"]),
+    ("PolkadotXcm", &["SafeXcmVersion", "SupportedVersion"]),
+    ("XcmpQueue", &[]),
+    ("MessageQueue", &[]),
+    ("Assets", &["Asset", "Account", "Approvals", "Metadata"]),
+    ("ForeignAssets", &["Asset", "Account", "Approvals", "Metadata"]),
+    ("PoolAssets", &["Asset", "Account", "Approvals", "Metadata"]),
+    ("Uniques", &[]),
+    ("Nfts", &[]),
+    ("AssetConversion", &[]),
+    ("Multisig", &[]),
+    ("Proxy", &[]),
+    ("Utility", &[]),
+    ("Vesting", &[]),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Added { section: &'static str, key: String, value: String },
+    Removed { section: &'static str, key: String, value: String },
+    Changed { section: &'static str, key: String, old: String, new: String },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { key, value, .. } => write!(f, "+ {}: {}", key, value),
+            Change::Removed { key, value, .. } => write!(f, "- {}: {}", key, value),
+            Change::Changed { key, old, new, .. } => write!(f, "~ {}: {} -> {}", key, old, new),
+        }
+    }
+}
+
+impl Change {
+    pub fn section(&self) -> &'static str {
+        match self {
+            Change::Added { section, .. } | Change::Removed { section, .. } | Change::Changed { section, .. } => section,
+        }
+    }
+}
+
+pub fn diff_chain_specs(a: &Path, b: &Path) -> Result<Vec<Change>, Box<dyn Error>> {
+    Ok(diff_specs(&load_spec(a)?, &load_spec(b)?))
+}
+
+fn load_spec(path: &Path) -> Result<Value, Box<dyn Error>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read chain spec {:?}: {}", path, e))?;
+    let spec = serde_json::from_str(&content)
+        .map_err(|e| format!("Chain spec {:?} is not valid JSON: {}", path, e))?;
+    Ok(spec)
+}
+
+pub fn diff_specs(a: &Value, b: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    diff_maps("Top-level fields", &top_level_fields(a), &top_level_fields(b), &mut changes);
+
+    let old_hash = code_hash(a);
+    let new_hash = code_hash(b);
+    if old_hash != new_hash {
+        changes.push(Change::Changed {
+            section: "Runtime code",
+            key: "code hash".to_string(),
+            old: old_hash.unwrap_or_else(|| "none".to_string()),
+            new: new_hash.unwrap_or_else(|| "none".to_string()),
+        });
+    }
+
+    let config_a = genesis_config(a);
+    let config_b = genesis_config(b);
+    if config_a.is_some() || config_b.is_some() {
+        diff_maps("Genesis config", &flatten_config(config_a), &flatten_config(config_b), &mut changes);
+        diff_maps("Balances", &balances(config_a), &balances(config_b), &mut changes);
+    }
+
+    let top_a = a.pointer("/genesis/raw/top").and_then(Value::as_object);
+    let top_b = b.pointer("/genesis/raw/top").and_then(Value::as_object);
+    if top_a.is_some() || top_b.is_some() {
+        diff_storage(top_a, top_b, &mut changes);
+    }
+
+    changes
+}
+
+fn top_level_fields(spec: &Value) -> BTreeMap<String, String> {
+    spec.as_object()
+        .map(|fields| {
+            fields
+                .iter()
+                .filter(|(key, _)| key.as_str() != "genesis")
+                .map(|(key, value)| (key.clone(), short(value)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn code_hash(spec: &Value) -> Option<String> {
+    match runtime_code(spec)? {
+        Ok(code) => Some(to_hex(&blake2_256(&code))),
+        Err(e) => Some(format!("<{}>", e)),
+    }
+}
+
+fn flatten_config(config: Option<&Value>) -> BTreeMap<String, String> {
+    let mut flat = BTreeMap::new();
+    if let Some(config) = config {
+        flatten(config, String::new(), &mut flat);
+    }
+    flat
+}
+
+fn flatten(value: &Value, path: String, flat: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(fields) if !fields.is_empty() => {
+            for (key, value) in fields {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                // Balances get their own per-account section
+                if path != "balances.balances" {
+                    flatten(value, path, flat);
+                }
+            }
+        }
+        _ => {
+            flat.insert(path, short(value));
+        }
+    }
+}
+
+fn balances(config: Option<&Value>) -> BTreeMap<String, String> {
+    config
+        .and_then(|config| config.pointer("/balances/balances"))
+        .and_then(Value::as_array)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| Some((entry.get(0)?.as_str()?.to_string(), short(entry.get(1)?))))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn diff_maps(section: &'static str, a: &BTreeMap<String, String>, b: &BTreeMap<String, String>, changes: &mut Vec<Change>) {
+    for (key, old) in a {
+        match b.get(key) {
+            None => changes.push(Change::Removed { section, key: key.clone(), value: old.clone() }),
+            Some(new) if new != old => changes.push(Change::Changed { section, key: key.clone(), old: old.clone(), new: new.clone() }),
+            Some(_) => {}
+        }
+    }
+    for (key, new) in b {
+        if !a.contains_key(key) {
+            changes.push(Change::Added { section, key: key.clone(), value: new.clone() });
+        }
+    }
+}
+
+fn diff_storage(a: Option<&Map<String, Value>>, b: Option<&Map<String, Value>>, changes: &mut Vec<Change>) {
+    let empty = Map::new();
+    let a = a.unwrap_or(&empty);
+    let b = b.unwrap_or(&empty);
+    let section = "Raw storage";
+
+    for (key, old) in a {
+        match b.get(key) {
+            None => changes.push(Change::Removed { section, key: describe_key(key), value: storage_value(key, old) }),
+            Some(new) if new != old => changes.push(Change::Changed {
+                section,
+                key: describe_key(key),
+                old: storage_value(key, old),
+                new: storage_value(key, new),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, new) in b {
+        if !a.contains_key(key) {
+            changes.push(Change::Added { section, key: describe_key(key), value: storage_value(key, new) });
+        }
+    }
+}
+
+// Human readable name for a raw storage key, e.g. `Balances.TotalIssuance` or `:code`
+pub fn describe_key(key: &str) -> String {
+    let bytes = match from_hex(key) {
+        Ok(bytes) => bytes,
+        Err(_) => return key.to_string(),
+    };
+
+    if bytes.first() == Some(&b':') {
+        if let Ok(name) = std::str::from_utf8(&bytes) {
+            return name.to_string();
+        }
+    }
+    if bytes.len() < 16 {
+        return key.to_string();
+    }
+
+    let pallet = KNOWN_STORAGE
+        .iter()
+        .find(|(pallet, _)| twox_128(pallet.as_bytes()) == bytes[..16]);
+    let (pallet, items) = match pallet {
+        Some(known) => known,
+        None => return key.to_string(),
+    };
+    if bytes.len() < 32 {
+        return format!("{} {}", pallet, to_hex(&bytes[16..]));
+    }
+
+    let item = items
+        .iter()
+        .copied()
+        .chain([":__STORAGE_VERSION__:"])
+        .find(|item| twox_128(item.as_bytes()) == bytes[16..32]);
+    let name = match item {
+        Some(":__STORAGE_VERSION__:") => format!("{}.StorageVersion", pallet),
+        Some(item) => format!("{}.{}", pallet, item),
+        None => format!("{}.{}", pallet, to_hex(&bytes[16..32])),
+    };
+    if bytes.len() > 32 {
+        format!("{} {}", name, to_hex(&bytes[32..]))
+    } else {
+        name
+    }
+}
+
+fn storage_value(key: &str, value: &Value) -> String {
+    if key == "0x3a636f6465" {
+        if let Some(Ok(code)) = value.as_str().map(from_hex) {
+            return format!("<{} bytes, hash {}>", code.len(), to_hex(&blake2_256(&code)));
+        }
+    }
+    short(value)
+}
+
+fn short(value: &Value) -> String {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    if text.len() > MAX_VALUE_LEN {
+        let cut = text.char_indices().nth(MAX_VALUE_LEN - 2).map(|(i, _)| i).unwrap_or(text.len());
+        format!("{}… ({} chars)", &text[..cut], text.len())
+    } else {
+        text
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    const BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+
+    fn storage_key(pallet: &str, item: &str) -> String {
+        to_hex(&[twox_128(pallet.as_bytes()), twox_128(item.as_bytes())].concat())
+    }
+
+    #[test]
+    fn test_diff_plain_specs() {
+        let a = json!({
+            "name": "Development",
+            "id": "dev",
+            "genesis": { "runtimeGenesis": { "code": "0x01", "patch": {
+                "balances": { "balances": [[ALICE, 100], [BOB, 100]] },
+                "sudo": { "key": ALICE }
+            }}}
+        });
+        let b = json!({
+            "name": "Development",
+            "id": "dev-2",
+            "protocolId": "dot",
+            "genesis": { "runtimeGenesis": { "code": "0x01", "patch": {
+                "balances": { "balances": [[ALICE, 200]] },
+                "sudo": { "key": BOB }
+            }}}
+        });
+
+        let changes = diff_specs(&a, &b);
+        let lines: Vec<String> = changes.iter().map(|c| format!("{}: {}", c.section(), c)).collect();
+        assert_eq!(lines, vec![
+            "Top-level fields: ~ id: dev -> dev-2".to_string(),
+            "Top-level fields: + protocolId: dot".to_string(),
+            format!("Genesis config: ~ sudo.key: {} -> {}", ALICE, BOB),
+            format!("Balances: - {}: 100", BOB),
+            format!("Balances: ~ {}: 100 -> 200", ALICE),
+        ]);
+    }
+
+    #[test]
+    fn test_diff_code_hash() {
+        let a = json!({ "genesis": { "runtimeGenesis": { "code": "0x01", "patch": {} } } });
+        let b = json!({ "genesis": { "runtimeGenesis": { "code": "0x02", "patch": {} } } });
+
+        let changes = diff_specs(&a, &b);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].section(), "Runtime code");
+    }
+
+    #[test]
+    fn test_diff_raw_specs() {
+        let issuance = storage_key("Balances", "TotalIssuance");
+        let sudo = storage_key("Sudo", "Key");
+        let a = json!({ "genesis": { "raw": { "top": { "0x3a636f6465": "0x01", issuance.clone(): "0x00" } } } });
+        let b = json!({ "genesis": { "raw": { "top": { "0x3a636f6465": "0x0102", issuance: "0x01", sudo: "0x02" } } } });
+
+        let changes = diff_specs(&a, &b);
+        let lines: Vec<String> = changes.iter().filter(|c| c.section() == "Raw storage").map(|c| c.to_string()).collect();
+        assert_eq!(lines, vec![
+            format!("~ :code: <1 bytes, hash {}> -> <2 bytes, hash {}>", to_hex(&blake2_256(&[1])), to_hex(&blake2_256(&[1, 2]))),
+            "~ Balances.TotalIssuance: 0x00 -> 0x01".to_string(),
+            "+ Sudo.Key: 0x02".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_describe_key() {
+        assert_eq!(describe_key("0x3a686561707061676573"), ":heappages");
+        assert_eq!(describe_key(&storage_key("System", "Account")), "System.Account");
+        assert_eq!(describe_key(&format!("{}ff", storage_key("System", "Account"))), "System.Account 0xff");
+        assert_eq!(describe_key(&storage_key("Aura", ":__STORAGE_VERSION__:")), "Aura.StorageVersion");
+        assert_eq!(describe_key("0x0102"), "0x0102");
+    }
+
+    #[test]
+    fn test_diff_chain_specs_missing_file() {
+        let temp_dir = tempdir().unwrap();
+        let a = temp_dir.path().join("a.json");
+        fs::write(&a, "{}").unwrap();
+
+        let result = diff_chain_specs(&a, &temp_dir.path().join("b.json"));
+        assert!(result.unwrap_err().to_string().starts_with("Failed to read chain spec"));
+    }
+}
@@ -0,0 +1,389 @@
+use std::error::Error;
+use std::fs;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use serde_json::Value;
+use crate::hashing::{blake2_256, from_hex, to_hex};
+use crate::ss58;
+
+const CODE_KEY: &str = "0x3a636f6465";
+const CHAIN_TYPES: [&str; 4] = ["Development", "Local", "Live", "Custom"];
+
+pub fn validate_chain_spec(path: &Path, expected_code_hash: Option<[u8; 32]>) -> Result<Vec<String>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read chain spec {:?}: {}", path, e))?;
+    let spec: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Chain spec {:?} is not valid JSON: {}", path, e))?;
+
+    Ok(validate_spec(&spec, expected_code_hash))
+}
+
+pub fn validate_spec(spec: &Value, expected_code_hash: Option<[u8; 32]>) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    if !spec.is_object() {
+        issues.push("Chain spec must be a JSON object".to_string());
+        return issues;
+    }
+
+    for field in ["name", "id"] {
+        match spec.get(field) {
+            Some(Value::String(value)) if !value.is_empty() => {}
+            Some(_) => issues.push(format!("Field `{}` must be a non-empty string", field)),
+            None => issues.push(format!("Missing required field `{}`", field)),
+        }
+    }
+
+    if let Some(chain_type) = spec.get("chainType") {
+        let known = chain_type.as_str().map(|t| CHAIN_TYPES.contains(&t)).unwrap_or(false)
+            || chain_type.get("Custom").is_some();
+        if !known {
+            issues.push(format!("Unknown chainType {}", chain_type));
+        }
+    }
+
+    check_genesis(spec, &mut issues);
+    check_para_id(spec, &mut issues);
+    check_boot_nodes(spec, &mut issues);
+    check_accounts(spec, &mut issues);
+
+    if let Some(expected) = expected_code_hash {
+        match runtime_code(spec) {
+            Some(Ok(code)) => {
+                let actual = blake2_256(&code);
+                if actual != expected {
+                    issues.push(format!(
+                        "Embedded :code hash {} does not match expected {}",
+                        to_hex(&actual),
+                        to_hex(&expected)
+                    ));
+                }
+            }
+            Some(Err(e)) => issues.push(format!("Embedded :code is not valid hex: {}", e)),
+            None => issues.push("Chain spec has no embedded :code".to_string()),
+        }
+    }
+
+    issues
+}
+
+pub fn para_id(spec: &Value) -> Option<&Value> {
+    spec.get("para_id").or_else(|| spec.get("paraId"))
+}
+
+pub fn relay_chain(spec: &Value) -> Option<&Value> {
+    spec.get("relay_chain").or_else(|| spec.get("relayChain"))
+}
+
+// The genesis config as JSON, for plain (non-raw) chain specs
+pub fn genesis_config(spec: &Value) -> Option<&Value> {
+    let genesis = spec.get("genesis")?;
+    if let Some(runtime_genesis) = genesis.get("runtimeGenesis") {
+        return runtime_genesis.get("patch").or_else(|| runtime_genesis.get("config"));
+    }
+    genesis.get("runtime")
+}
+
+pub fn runtime_code(spec: &Value) -> Option<Result<Vec<u8>, String>> {
+    let genesis = spec.get("genesis")?;
+    let code = genesis
+        .pointer("/raw/top")
+        .and_then(|top| top.get(CODE_KEY))
+        .or_else(|| genesis.pointer("/runtimeGenesis/code"))
+        .or_else(|| genesis.pointer("/runtime/system/code"))?;
+
+    Some(match code.as_str() {
+        Some(hex) => from_hex(hex),
+        None => Err("code is not a string".to_string()),
+    })
+}
+
+fn check_genesis(spec: &Value, issues: &mut Vec<String>) {
+    let genesis = match spec.get("genesis") {
+        Some(Value::Object(genesis)) => genesis,
+        Some(_) => {
+            issues.push("Field `genesis` must be an object".to_string());
+            return;
+        }
+        None => {
+            issues.push("Missing required field `genesis`".to_string());
+            return;
+        }
+    };
+
+    let forms: Vec<&str> = ["raw", "runtimeGenesis", "runtime"]
+        .into_iter()
+        .filter(|form| genesis.contains_key(*form))
+        .collect();
+    match forms.len() {
+        0 => issues.push("Genesis must contain one of `raw`, `runtimeGenesis` or `runtime`".to_string()),
+        1 => {}
+        _ => issues.push(format!("Genesis contains more than one form: {}", forms.join(", "))),
+    }
+
+    if let Some(raw) = genesis.get("raw") {
+        if !raw.get("top").map(Value::is_object).unwrap_or(false) {
+            issues.push("Raw genesis is missing the `top` storage map".to_string());
+        }
+    }
+
+    if let Some(runtime_genesis) = genesis.get("runtimeGenesis") {
+        if runtime_genesis.get("code").is_none() {
+            issues.push("runtimeGenesis is missing `code`".to_string());
+        }
+        if runtime_genesis.get("patch").is_none() && runtime_genesis.get("config").is_none() {
+            issues.push("runtimeGenesis must contain `patch` or `config`".to_string());
+        }
+    }
+}
+
+fn check_para_id(spec: &Value, issues: &mut Vec<String>) {
+    match (para_id(spec), relay_chain(spec)) {
+        (None, None) => {}
+        (Some(_), None) => issues.push("`para_id` is set but `relay_chain` is missing".to_string()),
+        (None, Some(_)) => issues.push("`relay_chain` is set but `para_id` is missing".to_string()),
+        (Some(id), Some(relay)) => {
+            if !id.as_u64().map(|id| id <= u32::MAX as u64).unwrap_or(false) {
+                issues.push(format!("`para_id` must be a 32-bit unsigned integer, found {}", id));
+            }
+            if !relay.as_str().map(|r| !r.is_empty()).unwrap_or(false) {
+                issues.push(format!("`relay_chain` must be a non-empty string, found {}", relay));
+            }
+            let genesis_id = genesis_config(spec).and_then(|config| config.pointer("/parachainInfo/parachainId"));
+            if let Some(genesis_id) = genesis_id {
+                if genesis_id != id {
+                    issues.push(format!(
+                        "`para_id` {} does not match parachainInfo.parachainId {} in genesis",
+                        id, genesis_id
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn check_boot_nodes(spec: &Value, issues: &mut Vec<String>) {
+    let boot_nodes = match spec.get("bootNodes") {
+        Some(Value::Array(nodes)) => nodes,
+        Some(_) => {
+            issues.push("Field `bootNodes` must be an array".to_string());
+            return;
+        }
+        None => return,
+    };
+
+    for node in boot_nodes {
+        match node.as_str() {
+            Some(addr) => {
+                if let Err(e) = validate_multiaddr(addr) {
+                    issues.push(format!("Invalid bootnode {}: {}", addr, e));
+                }
+            }
+            None => issues.push(format!("Invalid bootnode {}: not a string", node)),
+        }
+    }
+}
+
+pub fn validate_multiaddr(addr: &str) -> Result<(), String> {
+    let rest = addr.strip_prefix('/').ok_or("must start with `/`")?;
+    let mut parts = rest.split('/');
+    let mut has_peer_id = false;
+
+    while let Some(protocol) = parts.next() {
+        match protocol {
+            "ip4" => {
+                let value = parts.next().ok_or("missing ip4 address")?;
+                value.parse::<Ipv4Addr>().map_err(|_| format!("invalid ip4 address {}", value))?;
+            }
+            "ip6" => {
+                let value = parts.next().ok_or("missing ip6 address")?;
+                value.parse::<Ipv6Addr>().map_err(|_| format!("invalid ip6 address {}", value))?;
+            }
+            "dns" | "dns4" | "dns6" => {
+                let value = parts.next().filter(|v| !v.is_empty()).ok_or("missing dns name")?;
+                if value.contains(char::is_whitespace) {
+                    return Err(format!("invalid dns name {}", value));
+                }
+            }
+            "tcp" | "udp" => {
+                let value = parts.next().ok_or(format!("missing {} port", protocol))?;
+                value.parse::<u16>().map_err(|_| format!("invalid {} port {}", protocol, value))?;
+            }
+            "ws" | "wss" | "quic" | "quic-v1" | "webrtc-direct" => {}
+            "p2p" => {
+                let value = parts.next().ok_or("missing peer id")?;
+                validate_peer_id(value)?;
+                has_peer_id = true;
+            }
+            other => return Err(format!("unsupported protocol `{}`", other)),
+        }
+    }
+
+    if !has_peer_id {
+        return Err("missing /p2p/<peer id>".to_string());
+    }
+    Ok(())
+}
+
+fn validate_peer_id(peer_id: &str) -> Result<(), String> {
+    let bytes = bs58::decode(peer_id)
+        .into_vec()
+        .map_err(|_| format!("invalid peer id {}", peer_id))?;
+    // multihash: <code> <length> <digest>
+    match bytes.as_slice() {
+        [0x00 | 0x12, len, digest @ ..] if *len as usize == digest.len() => Ok(()),
+        _ => Err(format!("invalid peer id {}", peer_id)),
+    }
+}
+
+fn check_accounts(spec: &Value, issues: &mut Vec<String>) {
+    let config = match genesis_config(spec) {
+        Some(config) => config,
+        None => return,
+    };
+
+    let mut accounts: Vec<(String, &Value)> = Vec::new();
+    if let Some(Value::Array(balances)) = config.pointer("/balances/balances") {
+        for (i, entry) in balances.iter().enumerate() {
+            if let Some(account) = entry.get(0) {
+                accounts.push((format!("balances.balances[{}]", i), account));
+            }
+        }
+    }
+    if let Some(key) = config.pointer("/sudo/key").filter(|key| !key.is_null()) {
+        accounts.push(("sudo.key".to_string(), key));
+    }
+    if let Some(Value::Array(invulnerables)) = config.pointer("/collatorSelection/invulnerables") {
+        for (i, account) in invulnerables.iter().enumerate() {
+            accounts.push((format!("collatorSelection.invulnerables[{}]", i), account));
+        }
+    }
+    if let Some(Value::Array(keys)) = config.pointer("/session/keys") {
+        for (i, entry) in keys.iter().enumerate() {
+            for position in 0..2 {
+                if let Some(account) = entry.get(position) {
+                    accounts.push((format!("session.keys[{}][{}]", i, position), account));
+                }
+            }
+        }
+    }
+
+    for (location, account) in accounts {
+        match account.as_str() {
+            Some(address) => {
+                if let Err(e) = ss58::decode(address) {
+                    issues.push(format!("{}: {}", location, e));
+                }
+            }
+            None => issues.push(format!("{}: expected an SS58 address, found {}", location, account)),
+        }
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    const BOOTNODE: &str = "/ip4/127.0.0.1/tcp/30333/p2p/12D3KooWEyoppNCUx8Yx66oV9fJnriXwCcXwDDUA2kj6vnc6iDEp";
+
+    fn plain_spec() -> Value {
+        json!({
+            "name": "Development",
+            "id": "dev",
+            "chainType": "Development",
+            "bootNodes": [BOOTNODE],
+            "para_id": 1000,
+            "relay_chain": "westend",
+            "genesis": {
+                "runtimeGenesis": {
+                    "code": "0x0061736d",
+                    "patch": {
+                        "balances": { "balances": [[ALICE, 1000000000000u64]] },
+                        "parachainInfo": { "parachainId": 1000 },
+                        "sudo": { "key": ALICE }
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_validate_spec_valid() {
+        let issues = validate_spec(&plain_spec(), Some(blake2_256(&[0x00, 0x61, 0x73, 0x6d])));
+        assert!(issues.is_empty(), "Unexpected issues: {:?}", issues);
+    }
+
+    #[test]
+    fn test_validate_spec_missing_fields() {
+        let issues = validate_spec(&json!({ "name": "" }), None);
+        assert!(issues.contains(&"Field `name` must be a non-empty string".to_string()));
+        assert!(issues.contains(&"Missing required field `id`".to_string()));
+        assert!(issues.contains(&"Missing required field `genesis`".to_string()));
+    }
+
+    #[test]
+    fn test_validate_spec_para_id_consistency() {
+        let mut spec = plain_spec();
+        spec["para_id"] = json!(2000);
+        let issues = validate_spec(&spec, None);
+        assert_eq!(issues, vec!["`para_id` 2000 does not match parachainInfo.parachainId 1000 in genesis".to_string()]);
+
+        spec.as_object_mut().unwrap().remove("relay_chain");
+        let issues = validate_spec(&spec, None);
+        assert_eq!(issues, vec!["`para_id` is set but `relay_chain` is missing".to_string()]);
+    }
+
+    #[test]
+    fn test_validate_spec_bad_accounts() {
+        let mut spec = plain_spec();
+        spec["genesis"]["runtimeGenesis"]["patch"]["sudo"]["key"] = json!("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQZ");
+        let issues = validate_spec(&spec, None);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].starts_with("sudo.key: Invalid SS58 address"));
+    }
+
+    #[test]
+    fn test_validate_spec_code_hash_mismatch() {
+        let issues = validate_spec(&plain_spec(), Some([0u8; 32]));
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].starts_with("Embedded :code hash"));
+    }
+
+    #[test]
+    fn test_validate_spec_raw_code() {
+        let spec = json!({
+            "name": "Development",
+            "id": "dev",
+            "genesis": { "raw": { "top": { CODE_KEY: "0x0102" }, "childrenDefault": {} } }
+        });
+        assert!(validate_spec(&spec, Some(blake2_256(&[1, 2]))).is_empty());
+    }
+
+    #[test]
+    fn test_validate_multiaddr() {
+        assert!(validate_multiaddr(BOOTNODE).is_ok());
+        assert!(validate_multiaddr("/dns/bootnode.example.com/tcp/30333/wss/p2p/12D3KooWEyoppNCUx8Yx66oV9fJnriXwCcXwDDUA2kj6vnc6iDEp").is_ok());
+        assert_eq!(validate_multiaddr("ip4/127.0.0.1").unwrap_err(), "must start with `/`");
+        assert_eq!(validate_multiaddr("/ip4/127.0.0.1/tcp/30333").unwrap_err(), "missing /p2p/<peer id>");
+        assert_eq!(validate_multiaddr("/ip4/300.0.0.1/tcp/1/p2p/x").unwrap_err(), "invalid ip4 address 300.0.0.1");
+        assert_eq!(validate_multiaddr("/ip4/127.0.0.1/tcp/99999").unwrap_err(), "invalid tcp port 99999");
+        assert_eq!(validate_multiaddr("/ip4/127.0.0.1/tcp/1/p2p/0OIl").unwrap_err(), "invalid peer id 0OIl");
+    }
+
+    #[test]
+    fn test_validate_chain_spec_invalid_json() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("chain_spec.json");
+        fs::write(&path, "{ not json").unwrap();
+
+        let result = validate_chain_spec(&path, None);
+        assert!(result.unwrap_err().to_string().contains("is not valid JSON"));
+    }
+}
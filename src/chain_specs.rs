@@ -3,11 +3,27 @@ use std::error::Error;
 use process::Command;
 use std::process;
 use std::fs;
+use crate::runtime_catalog::{self, RuntimeSpec};
 
-pub fn gen_chain_spec(wasm_source_path: Option<&Path>, chain_spec_builder_path: Option<&Path>) -> Result<(), Box<dyn Error>> {
-    let wasm_path = wasm_source_path.unwrap_or_else(|| Path::new("./nodes/asset_hub_westend_runtime.compact.compressed.wasm"));
+pub const CHAIN_SPECS_DIR: &str = "./chain-specs";
+pub const DEFAULT_CHAIN_SPEC: &str = "chain_spec";
+
+pub fn chain_spec_path(name: &str) -> PathBuf {
+    Path::new(CHAIN_SPECS_DIR).join(format!("{}.json", name))
+}
+
+pub fn gen_chain_spec(wasm_source_path: Option<&Path>, chain_spec_builder_path: Option<&Path>, destination: Option<&Path>, runtime: Option<&RuntimeSpec>) -> Result<(), Box<dyn Error>> {
+    let runtime = runtime.unwrap_or_else(|| runtime_catalog::default_runtime());
+    let default_wasm_path = runtime.wasm_path();
+    let wasm_path = wasm_source_path.unwrap_or(&default_wasm_path);
     let builder_path = chain_spec_builder_path.unwrap_or_else(|| Path::new("./binaries/chain-spec-builder"));
+    let default_destination = chain_spec_path(DEFAULT_CHAIN_SPEC);
+    let destination = destination.unwrap_or(&default_destination);
+    create_chain_spec(wasm_path, builder_path, destination, runtime.relay_chain, runtime.para_id)
+}
 
+// Runs chain-spec-builder for any relay chain and para id, e.g. for the parachains of a local network
+pub fn create_chain_spec(wasm_path: &Path, builder_path: &Path, destination: &Path, relay_chain: &str, para_id: u32) -> Result<(), Box<dyn Error>> {
     if !wasm_path.exists() {
         eprintln!("WASM file not found: {:?}", wasm_path);
         return Err(format!("WASM file not found: {:?}", wasm_path).into());
@@ -29,59 +45,60 @@ pub fn gen_chain_spec(wasm_source_path: Option<&Path>, chain_spec_builder_path:
         return Err(format!("Failed to add execute permissions to the chain-spec-builder").into());
     }
 
-    let _chain_spec_status = Command::new(builder_path)
+    // Build into a scratch directory next to the destination so the final rename is atomic
+    let destination_dir = destination.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    create_chain_specs_dir(destination_dir)?;
+    let scratch = tempfile::Builder::new().prefix(".chain-spec-").tempdir_in(destination_dir)?;
+    let output = scratch.path().join("chain_spec.json");
+
+    let chain_spec_status = Command::new(builder_path)
+        .arg("-c")
+        .arg(&output)
         .args(&[
             "create",
             "-t", "development",
-            "--relay-chain", "westend",
-            "--para-id", "1000",
+            "--relay-chain", relay_chain,
+            "--para-id", &para_id.to_string(),
             "--runtime", wasm_path.to_str().unwrap(),
             "named-preset", "development"
         ])
-        .status()
-        .expect("Failed to run chain-spec-builder");
+        .status()?;
 
-    let _ = locate_chain_spec();
+    if !chain_spec_status.success() {
+        return Err(format!("chain-spec-builder failed with status {}", chain_spec_status).into());
+    }
+    if !output.exists() {
+        return Err(format!("chain-spec-builder did not write {:?}", output).into());
+    }
+
+    move_chain_spec(&output, destination)?;
+    println!("Chain spec written to {:?}", destination);
     Ok(())
 }
 
-pub fn locate_chain_spec()-> Result<(), String>{
-    // Define the directory to search for the chain_spec.json file
-    let search_directories = vec!["./", "../"];
-    let mut chain_spec_source_path: Option<PathBuf> = None;
-
-    // Locate the chain_spec.json file
-    for dir in &search_directories {
-        let potential_path: PathBuf = Path::new(dir).join("chain_spec.json");
-        if potential_path.exists() {
-            chain_spec_source_path = Some(potential_path);
-            break;
-        }
+pub fn move_chain_spec(chain_spec_source_path: &Path, chain_spec_destination_path: &Path) -> Result<(), String>{
+    if let Some(parent) = chain_spec_destination_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        create_chain_specs_dir(parent)?;
     }
 
-    // Check if the file was found
-    let chain_spec_source_path = match chain_spec_source_path {
-        Some(path) => path,
-        None => {
-            return Err(format!("chain_spec.json not found in the specified directories."));
-        }
-    };
-
-    let _ = move_chain_spec(&chain_spec_source_path);
+    // Move the chain spec into place, replacing any previous spec of the same name
+    if let Err(e) = fs::rename(chain_spec_source_path, chain_spec_destination_path) {
+        return Err(format!("Failed to move chain spec to {:?}: {}", chain_spec_destination_path, e));
+    }
     Ok(())
 }
 
-pub fn move_chain_spec(chain_spec_source_path:&Path) -> Result<(), String>{
-
-    let chain_spec_destination_path = Path::new("./chain-specs/chain_spec.json");
-
-    let _ = create_chain_specs_dir(chain_spec_destination_path.parent().unwrap())?;
-    
-    // Move the chain_spec.json file to the chain-specs directory
-    if let Err(e) = fs::rename(&chain_spec_source_path, &chain_spec_destination_path) {
-        return Err(format!("Failed to move chain_spec.json: {}", e));
+pub fn list_chain_specs(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
     }
-    Ok(())
+
+    let mut specs: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().map(|ext| ext == "json").unwrap_or(false))
+        .collect();
+    specs.sort();
+    Ok(specs)
 }
 
 pub fn create_chain_specs_dir(path: &Path) -> Result<(), String> {
@@ -91,6 +108,63 @@ pub fn create_chain_specs_dir(path: &Path) -> Result<(), String> {
     Ok(())
 }
 
+pub fn convert_to_raw(input: &Path, output: &Path, chain_spec_builder_path: Option<&Path>, omni_node_path: Option<&Path>) -> Result<usize, Box<dyn Error>> {
+    let builder_path = chain_spec_builder_path.unwrap_or_else(|| Path::new("./binaries/chain-spec-builder"));
+    let node_path = omni_node_path.unwrap_or_else(|| Path::new("./binaries/polkadot-omni-node"));
+
+    if !input.exists() {
+        return Err(format!("Chain spec not found: {:?}", input).into());
+    }
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        create_chain_specs_dir(parent)?;
+    }
+
+    // Prefer chain-spec-builder, fall back to omni-node build-spec
+    let mut converted = false;
+    if builder_path.exists() {
+        let status = Command::new(builder_path)
+            .arg("-c")
+            .arg(output)
+            .arg("convert-to-raw")
+            .arg(input)
+            .status()?;
+        converted = status.success();
+        if !converted {
+            eprintln!("chain-spec-builder convert-to-raw failed with status {}", status);
+        }
+    }
+
+    if !converted {
+        if !node_path.exists() {
+            return Err(format!("Neither chain-spec-builder nor omni-node is available to convert {:?}", input).into());
+        }
+        let result = Command::new(node_path)
+            .arg("build-spec")
+            .arg("--chain")
+            .arg(input)
+            .arg("--raw")
+            .output()?;
+        if !result.status.success() {
+            return Err(format!("omni-node build-spec failed with status {}: {}", result.status, String::from_utf8_lossy(&result.stderr).trim()).into());
+        }
+        fs::write(output, &result.stdout)?;
+    }
+
+    verify_raw_chain_spec(output)
+}
+
+pub fn verify_raw_chain_spec(path: &Path) -> Result<usize, Box<dyn Error>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read chain spec {:?}: {}", path, e))?;
+    let spec: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse chain spec {:?}: {}", path, e))?;
+
+    match spec.pointer("/genesis/raw/top").and_then(|top| top.as_object()) {
+        Some(top) => Ok(top.len()),
+        None => Err(format!("Raw chain spec {:?} is missing genesis.raw.top", path).into()),
+    }
+}
+
 
 /// =================================================================================================
 /// Test Module
@@ -119,7 +193,8 @@ mod tests {
         fs::write(&wasm_path, "mock wasm content").expect("Failed to write mock WASM file");
 
         let builder_path = temp_dir.path().join("chain-spec-builder");
-        fs::write(&builder_path, "#!/bin/sh\necho \"Mock chain-spec-builder executed\"\nexit 0").expect("Failed to write mock builder file");
+        // Mock builder invoked as: -c <output> create ...
+        fs::write(&builder_path, "#!/bin/sh\necho \"Mock chain-spec-builder executed\"\necho '{\"name\":\"Development\"}' > \"$2\"\nexit 0").expect("Failed to write mock builder file");
 
         #[cfg(unix)]
         {
@@ -133,8 +208,14 @@ mod tests {
         let new_path = format!("{}:{}", temp_dir.path().to_str().unwrap(), original_path);
         env::set_var("PATH", new_path);
 
-        let result = gen_chain_spec(Some(&wasm_path), Some(&builder_path));
-        assert!(result.is_ok());
+        let destination = temp_dir.path().join("chain-specs/development.json");
+        let result = gen_chain_spec(Some(&wasm_path), Some(&builder_path), Some(&destination), None);
+        assert!(result.is_ok(), "gen_chain_spec failed: {:?}", result.err());
+        assert_eq!(fs::read_to_string(&destination).unwrap().trim(), "{\"name\":\"Development\"}");
+
+        // Only the placed spec is left behind, the scratch directory is cleaned up
+        let entries = fs::read_dir(temp_dir.path().join("chain-specs")).unwrap().count();
+        assert_eq!(entries, 1);
 
         env::set_var("PATH", original_path);
     }
@@ -194,7 +275,7 @@ mod tests {
 
     #[test]
     fn test_gen_chain_spec_wasm_not_found() {
-        let result = gen_chain_spec(Some(Path::new("non_existent_wasm.wasm")), None);
+        let result = gen_chain_spec(Some(Path::new("non_existent_wasm.wasm")), None, None, None);
         assert!(result.is_err());
         assert_eq!(result.unwrap_err().to_string(), "WASM file not found: \"non_existent_wasm.wasm\"");
     }
@@ -222,7 +303,7 @@ mod tests {
 
         
         // Call the function after setting up the mock environment
-        let result = gen_chain_spec(Some(&wasm_source_path), Some(&chain_spec_builder_path));
+        let result = gen_chain_spec(Some(&wasm_source_path), Some(&chain_spec_builder_path), Some(&mock_dir.join("chain-specs/chain_spec.json")), None);
         assert!(result.is_err(), "gen_chain_spec should have failed");
         assert_eq!(result.unwrap_err().to_string(), "Failed to add execute permissions to the chain-spec-builder");
 
@@ -252,7 +333,7 @@ mod tests {
         let wasm_source_path = Path::new("./nodes/asset_hub_westend_runtime_test.compact.compressed.wasm");
         assert!(!wasm_source_path.exists(), "WASM file should not exist for this test");
     
-        let result = gen_chain_spec(Some(&wasm_source_path), Some(&chain_spec_builder_path));
+        let result = gen_chain_spec(Some(&wasm_source_path), Some(&chain_spec_builder_path), Some(&mock_dir.join("chain-specs/chain_spec.json")), None);
         assert!(result.is_err());
         assert_eq!(result.unwrap_err().to_string(), "WASM file not found: \"./nodes/asset_hub_westend_runtime_test.compact.compressed.wasm\"");
     
@@ -339,7 +420,8 @@ mod tests {
         let non_existent_path = PathBuf::from("non_existent_chain_spec.json");
 
         // Run the function with the non-existent file path
-        let result = move_chain_spec(&non_existent_path);
+        let temp_dir = tempdir().unwrap();
+        let result = move_chain_spec(&non_existent_path, &temp_dir.path().join("chain_spec.json"));
 
         // Assert that the result is Err
         assert!(result.is_err());
@@ -347,11 +429,13 @@ mod tests {
     #[test]
     fn test_move_chain_spec_failure_v2() {
         let non_existent_path = PathBuf::from("chain_spec.json");
+        let temp_dir = tempdir().unwrap();
+        let destination = temp_dir.path().join("chain_spec.json");
 
-        let result = move_chain_spec(&non_existent_path);
+        let result = move_chain_spec(&non_existent_path, &destination);
 
         // Assert that the result is Err
-        assert_eq!(result.err().unwrap(), "Failed to move chain_spec.json: No such file or directory (os error 2)");
+        assert_eq!(result.err().unwrap(), format!("Failed to move chain spec to {:?}: No such file or directory (os error 2)", destination));
     }
 
     #[test]
@@ -360,19 +444,20 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let source_file_path = temp_dir.path().join("chain_spec.json");
         let destination_dir_path = temp_dir.path().join("test-specs");
-        let _destination_file_path = destination_dir_path.join("chain_spec.json");
+        let destination_file_path = destination_dir_path.join("chain_spec.json");
 
         // Create a dummy chain_spec.json file
         fs::write(&source_file_path, "mock chain spec content").unwrap();
 
         // Run the function with the source file path
-        let result = move_chain_spec(&source_file_path);
+        let result = move_chain_spec(&source_file_path, &destination_file_path);
 
         // Assert that the result is Ok
         assert!(result.is_ok());
 
         // Assert that the source file no longer exists
         assert!(!source_file_path.exists(), "Source chain_spec.json should not exist after being moved.");
+        assert!(destination_file_path.exists(), "chain_spec.json was not moved to the destination directory.");
 
     }
 
@@ -441,7 +526,99 @@ mod tests {
         let result = fs::rename(&chain_spec_source_path, &chain_spec_destination_path);
         assert!(result.is_err(), "Expected failure to move a nonexistent file.");
     }
-    
+
+    #[test]
+    fn test_gen_chain_spec_uses_runtime_catalog() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let wasm_path = temp_dir.path().join("bridge_hub_rococo_runtime.compact.compressed.wasm");
+        fs::write(&wasm_path, "mock wasm content").unwrap();
+
+        // Record the arguments the builder was called with as the chain spec content
+        let builder_path = temp_dir.path().join("chain-spec-builder");
+        write_mock_binary(&builder_path, "#!/bin/sh\necho \"$@\" > \"$2\"\n");
+
+        let runtime = runtime_catalog::find_runtime("bridge-hub-rococo").unwrap();
+        let destination = temp_dir.path().join("bridge-hub-rococo.json");
+        gen_chain_spec(Some(&wasm_path), Some(&builder_path), Some(&destination), Some(runtime)).unwrap();
+
+        let args = fs::read_to_string(&destination).unwrap();
+        assert!(args.contains("--relay-chain rococo --para-id 1013"), "Unexpected builder args: {}", args);
+    }
+
+    #[test]
+    fn test_list_chain_specs() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("westend.json"), "{}").unwrap();
+        fs::write(temp_dir.path().join("chain_spec.json"), "{}").unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "").unwrap();
+        fs::create_dir(temp_dir.path().join(".chain-spec-scratch")).unwrap();
+
+        let specs = list_chain_specs(temp_dir.path()).unwrap();
+        assert_eq!(specs, vec![temp_dir.path().join("chain_spec.json"), temp_dir.path().join("westend.json")]);
+        assert!(list_chain_specs(&temp_dir.path().join("missing")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_chain_spec_path() {
+        assert_eq!(chain_spec_path("westend"), Path::new("./chain-specs/westend.json"));
+        assert_eq!(chain_spec_path(DEFAULT_CHAIN_SPEC), Path::new("./chain-specs/chain_spec.json"));
+    }
+
+    fn write_mock_binary(path: &Path, script: &str) {
+        fs::write(path, script).expect("Failed to write mock binary");
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).expect("Failed to make mock binary executable");
+    }
+
+    #[test]
+    fn test_convert_to_raw_with_chain_spec_builder() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let input = temp_dir.path().join("chain_spec.json");
+        let output = temp_dir.path().join("raw/chain_spec_raw.json");
+        fs::write(&input, r#"{"genesis":{"runtimeGenesis":{}}}"#).unwrap();
+
+        // Mock builder invoked as: -c <output> convert-to-raw <input>
+        let builder_path = temp_dir.path().join("chain-spec-builder");
+        write_mock_binary(&builder_path, "#!/bin/sh\n[ \"$3\" = \"convert-to-raw\" ] || exit 1\necho '{\"genesis\":{\"raw\":{\"top\":{\"0x3a636f6465\":\"0x00\"},\"childrenDefault\":{}}}}' > \"$2\"\n");
+
+        let result = convert_to_raw(&input, &output, Some(&builder_path), Some(&temp_dir.path().join("missing-node")));
+        assert_eq!(result.unwrap(), 1);
+        assert!(output.exists(), "Raw chain spec was not written");
+    }
+
+    #[test]
+    fn test_convert_to_raw_falls_back_to_omni_node() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let input = temp_dir.path().join("chain_spec.json");
+        let output = temp_dir.path().join("chain_spec_raw.json");
+        fs::write(&input, "{}").unwrap();
+
+        let node_path = temp_dir.path().join("polkadot-omni-node");
+        write_mock_binary(&node_path, "#!/bin/sh\n[ \"$1\" = \"build-spec\" ] || exit 1\necho '{\"genesis\":{\"raw\":{\"top\":{\"0x01\":\"0x02\",\"0x03\":\"0x04\"}}}}'\n");
+
+        let result = convert_to_raw(&input, &output, Some(&temp_dir.path().join("missing-builder")), Some(&node_path));
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_convert_to_raw_input_not_found() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let input = temp_dir.path().join("missing.json");
+        let output = temp_dir.path().join("raw.json");
+
+        let result = convert_to_raw(&input, &output, None, None);
+        assert_eq!(result.unwrap_err().to_string(), format!("Chain spec not found: {:?}", input));
+    }
+
+    #[test]
+    fn test_verify_raw_chain_spec_missing_top() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("plain.json");
+        fs::write(&path, r#"{"genesis":{"runtimeGenesis":{"patch":{}}}}"#).unwrap();
+
+        let result = verify_raw_chain_spec(&path);
+        assert_eq!(result.unwrap_err().to_string(), format!("Raw chain spec {:?} is missing genesis.raw.top", path));
+    }
+
 
 }
 
@@ -0,0 +1,314 @@
+use std::error::Error;
+use std::fs;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+
+pub const RUN_DIR: &str = "./run";
+
+#[derive(Debug, PartialEq)]
+pub struct NodeStatus {
+    pub name: String,
+    pub pid: u32,
+    pub alive: bool,
+    pub state: NodeState,
+}
+
+// What the supervisor knows about its node, kept in run/<name>.state.json
+#[derive(Debug, Default, PartialEq)]
+pub struct NodeState {
+    pub restarting: bool,
+    pub restarts: u32,
+    pub last_exit: Option<String>,
+}
+
+pub fn pid_file(run_dir: &Path, name: &str) -> PathBuf {
+    run_dir.join(format!("{}.pid", name))
+}
+
+pub fn read_pid(run_dir: &Path, name: &str) -> Result<u32, Box<dyn Error>> {
+    let path = pid_file(run_dir, name);
+    let content = fs::read_to_string(&path)
+        .map_err(|_| format!("No node named {} is managed by dot", name))?;
+    let pid = content
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid PID file {:?}", path))?;
+    Ok(pid)
+}
+
+pub fn write_pid(run_dir: &Path, name: &str, pid: u32) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(run_dir)?;
+    fs::write(pid_file(run_dir, name), format!("{}\n", pid))?;
+    Ok(())
+}
+
+pub fn state_file(run_dir: &Path, name: &str) -> PathBuf {
+    run_dir.join(format!("{}.state.json", name))
+}
+
+pub fn write_state(run_dir: &Path, name: &str, state: &NodeState) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(run_dir)?;
+    let content = json!({
+        "restarting": state.restarting,
+        "restarts": state.restarts,
+        "last_exit": state.last_exit,
+    });
+    fs::write(state_file(run_dir, name), format!("{:#}\n", content))?;
+    Ok(())
+}
+
+pub fn read_state(run_dir: &Path, name: &str) -> NodeState {
+    let content: Value = match fs::read_to_string(state_file(run_dir, name)).ok().and_then(|content| serde_json::from_str(&content).ok()) {
+        Some(content) => content,
+        None => return NodeState::default(),
+    };
+    NodeState {
+        restarting: content["restarting"].as_bool().unwrap_or(false),
+        restarts: content["restarts"].as_u64().unwrap_or(0) as u32,
+        last_exit: content["last_exit"].as_str().map(String::from),
+    }
+}
+
+pub fn is_alive(pid: u32) -> bool {
+    let pid = pid as libc::pid_t;
+    // SAFETY: waitpid with WNOHANG and kill with signal 0 only query process state
+    unsafe {
+        // Reap the process first if it is one of our own exited children
+        let mut status = 0;
+        if libc::waitpid(pid, &mut status, libc::WNOHANG) == pid {
+            return false;
+        }
+        let exists = libc::kill(pid, 0) == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+        exists && !is_zombie(pid as u32)
+    }
+}
+
+// An exited supervisor stays a zombie when PID 1 does not reap orphans, as in many containers
+fn is_zombie(pid: u32) -> bool {
+    fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| stat.rsplit_once(')').map(|(_, rest)| rest.trim_start().starts_with('Z')))
+        .unwrap_or(false)
+}
+
+pub fn send_signal(pid: u32, signal: i32) -> Result<(), Box<dyn Error>> {
+    // SAFETY: kill has no memory safety requirements
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } != 0 {
+        return Err(format!("Failed to signal pid {}: {}", pid, std::io::Error::last_os_error()).into());
+    }
+    Ok(())
+}
+
+// Signals the whole process group led by pid, reaching a supervised node as well as its supervisor.
+// A process that does not lead its group, like a `dot serve` started from a script, is signalled alone
+pub fn signal_group(pid: u32, signal: i32) -> Result<(), Box<dyn Error>> {
+    // SAFETY: getpgid, killpg and kill have no memory safety requirements
+    let result = unsafe {
+        if libc::getpgid(pid as libc::pid_t) == pid as libc::pid_t {
+            libc::killpg(pid as libc::pid_t, signal)
+        } else {
+            libc::kill(pid as libc::pid_t, signal)
+        }
+    };
+    if result != 0 {
+        return Err(format!("Failed to signal pid {}: {}", pid, std::io::Error::last_os_error()).into());
+    }
+    Ok(())
+}
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("Invalid node name {:?}: use letters, digits, `-` and `_`", name));
+    }
+    Ok(())
+}
+
+pub fn ensure_not_running(run_dir: &Path, name: &str) -> Result<(), Box<dyn Error>> {
+    validate_name(name)?;
+    if let Ok(pid) = read_pid(run_dir, name) {
+        if is_alive(pid) {
+            return Err(format!("Node {} is already running with pid {}", name, pid).into());
+        }
+    }
+    Ok(())
+}
+
+pub fn spawn_detached(program: &Path, args: &[&str], env: &[(String, String)], run_dir: &Path, name: &str) -> Result<u32, Box<dyn Error>> {
+    ensure_not_running(run_dir, name)?;
+
+    // A separate process group keeps the node alive when the terminal sends SIGINT/SIGHUP
+    let child = Command::new(program)
+        .args(args)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("Failed to start {:?}: {}", program, e))?;
+
+    write_pid(run_dir, name, child.id())?;
+    Ok(child.id())
+}
+
+pub fn list_nodes(run_dir: &Path) -> Result<Vec<NodeStatus>, Box<dyn Error>> {
+    if !run_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut nodes = Vec::new();
+    for entry in fs::read_dir(run_dir)? {
+        let path = entry?.path();
+        if path.extension().map(|ext| ext != "pid").unwrap_or(true) {
+            continue;
+        }
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        if let Ok(pid) = read_pid(run_dir, &name) {
+            nodes.push(NodeStatus { alive: is_alive(pid), state: read_state(run_dir, &name), name, pid });
+        }
+    }
+    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(nodes)
+}
+
+// Stops a node with SIGTERM, escalating to SIGKILL after the timeout, and cleans up its run files
+pub fn stop_node(run_dir: &Path, name: &str, timeout: Duration) -> Result<(), Box<dyn Error>> {
+    let pid = read_pid(run_dir, name)?;
+
+    if is_alive(pid) {
+        signal_group(pid, libc::SIGTERM)?;
+        if !wait_for_exit(pid, timeout) {
+            eprintln!("{} (pid {}) did not stop within {:?}, killing it", name, pid, timeout);
+            signal_group(pid, libc::SIGKILL)?;
+            wait_for_exit(pid, Duration::from_secs(5));
+        }
+    }
+
+    remove_run_files(run_dir, name)
+}
+
+pub fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    let start = Instant::now();
+    while is_alive(pid) {
+        if start.elapsed() >= timeout {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    true
+}
+
+pub fn remove_run_files(run_dir: &Path, name: &str) -> Result<(), Box<dyn Error>> {
+    let prefix = format!("{}.", name);
+    for entry in fs::read_dir(run_dir)? {
+        let path = entry?.path();
+        let matches = path
+            .file_name()
+            .and_then(|file| file.to_str())
+            .map(|file| file.starts_with(&prefix))
+            .unwrap_or(false);
+        if matches {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_spawn_and_stop_detached_node() {
+        let temp_dir = tempdir().unwrap();
+        let run_dir = temp_dir.path().join("run");
+
+        let pid = spawn_detached(Path::new("sleep"), &["30"], &[], &run_dir, "sleeper").unwrap();
+        assert_eq!(read_pid(&run_dir, "sleeper").unwrap(), pid);
+        assert!(is_alive(pid));
+
+        let nodes = list_nodes(&run_dir).unwrap();
+        assert_eq!(nodes, vec![NodeStatus { name: "sleeper".to_string(), pid, alive: true, state: NodeState::default() }]);
+
+        stop_node(&run_dir, "sleeper", Duration::from_secs(5)).unwrap();
+        assert!(!is_alive(pid));
+        assert!(!pid_file(&run_dir, "sleeper").exists());
+    }
+
+    #[test]
+    fn test_spawn_detached_refuses_running_name() {
+        let temp_dir = tempdir().unwrap();
+        let run_dir = temp_dir.path();
+
+        let pid = spawn_detached(Path::new("sleep"), &["30"], &[], run_dir, "node").unwrap();
+        let result = spawn_detached(Path::new("sleep"), &["30"], &[], run_dir, "node");
+        assert_eq!(result.unwrap_err().to_string(), format!("Node node is already running with pid {}", pid));
+
+        stop_node(run_dir, "node", Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn test_stop_node_with_stale_pid_file() {
+        let temp_dir = tempdir().unwrap();
+        let run_dir = temp_dir.path();
+
+        // Spawn and reap a process so its pid is known to be gone
+        let mut child = Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        write_pid(run_dir, "stale", pid).unwrap();
+        fs::write(run_dir.join("stale.chain_spec.json"), "{}").unwrap();
+
+        assert!(!list_nodes(run_dir).unwrap()[0].alive);
+        stop_node(run_dir, "stale", Duration::from_secs(1)).unwrap();
+        assert_eq!(fs::read_dir(run_dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_node_state_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let run_dir = temp_dir.path().join("run");
+        assert_eq!(read_state(&run_dir, "node"), NodeState::default());
+
+        let state = NodeState { restarting: true, restarts: 2, last_exit: Some("exited with code 101".to_string()) };
+        write_state(&run_dir, "node", &state).unwrap();
+        assert_eq!(read_state(&run_dir, "node"), state);
+
+        remove_run_files(&run_dir, "node").unwrap();
+        assert!(!state_file(&run_dir, "node").exists());
+    }
+
+    #[test]
+    fn test_read_pid_unknown_node() {
+        let temp_dir = tempdir().unwrap();
+        let result = read_pid(temp_dir.path(), "missing");
+        assert_eq!(result.unwrap_err().to_string(), "No node named missing is managed by dot");
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("omni-node_1").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../etc").is_err());
+        assert!(validate_name("node.1").is_err());
+    }
+
+    #[test]
+    fn test_list_nodes_without_run_dir() {
+        let temp_dir = tempdir().unwrap();
+        assert!(list_nodes(&temp_dir.path().join("run")).unwrap().is_empty());
+    }
+}
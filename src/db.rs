@@ -0,0 +1,180 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use crate::daemon;
+
+pub const DATA_DIR: &str = "./data";
+pub const SNAPSHOT_DIR: &str = "./snapshots";
+const SNAPSHOT_EXTENSION: &str = "tar.zst";
+
+// Where dot keeps a node's database, passed to the node as --base-path
+pub fn base_path(data_dir: &Path, name: &str) -> PathBuf {
+    data_dir.join(name)
+}
+
+pub fn snapshot_path(snapshot_dir: &Path, name: &str, snapshot: &str) -> PathBuf {
+    snapshot_dir.join(name).join(format!("{}.{}", snapshot, SNAPSHOT_EXTENSION))
+}
+
+pub fn list_snapshots(snapshot_dir: &Path, name: &str) -> Vec<String> {
+    let suffix = format!(".{}", SNAPSHOT_EXTENSION);
+    let mut snapshots: Vec<String> = fs::read_dir(snapshot_dir.join(name))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.file_name().to_str()?.strip_suffix(&suffix).map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+    snapshots.sort();
+    snapshots
+}
+
+// The database must not change underneath a purge, snapshot or restore
+fn ensure_stopped(run_dir: &Path, name: &str) -> Result<(), Box<dyn Error>> {
+    daemon::ensure_not_running(run_dir, name).map_err(|e| format!("{}, stop it first with `dot stop {}`", e, name).into())
+}
+
+// Removes the node's database and returns whether there was one
+pub fn purge(data_dir: &Path, run_dir: &Path, name: &str) -> Result<bool, Box<dyn Error>> {
+    ensure_stopped(run_dir, name)?;
+    let base = base_path(data_dir, name);
+    if !base.exists() {
+        return Ok(false);
+    }
+    fs::remove_dir_all(&base).map_err(|e| format!("Failed to remove {:?}: {}", base, e))?;
+    Ok(true)
+}
+
+// Snapshot names become file names, so they follow the rules of node names
+fn validate_snapshot_name(snapshot: &str) -> Result<(), String> {
+    daemon::validate_name(snapshot).map_err(|_| format!("Invalid snapshot name {:?}: use letters, digits, `-` and `_`", snapshot))
+}
+
+pub fn snapshot(data_dir: &Path, snapshot_dir: &Path, run_dir: &Path, name: &str, snapshot: &str) -> Result<PathBuf, Box<dyn Error>> {
+    validate_snapshot_name(snapshot)?;
+    ensure_stopped(run_dir, name)?;
+    let base = base_path(data_dir, name);
+    if !base.is_dir() {
+        return Err(format!("Node {} has no database at {:?}", name, base).into());
+    }
+    let destination = snapshot_path(snapshot_dir, name, snapshot);
+    if destination.exists() {
+        return Err(format!("Snapshot {} of {} already exists at {:?}", snapshot, name, destination).into());
+    }
+
+    // Written next to the destination and renamed, so a failed snapshot leaves nothing behind
+    let parent = destination.parent().expect("snapshot paths have a parent");
+    fs::create_dir_all(parent)?;
+    let scratch = tempfile::Builder::new().prefix(".snapshot-").tempfile_in(parent)?;
+    let encoder = zstd::Encoder::new(scratch.reopen()?, 3)?.auto_finish();
+    let mut archive = tar::Builder::new(encoder);
+    archive.follow_symlinks(false);
+    archive.append_dir_all(".", &base)?;
+    archive.into_inner()?;
+    scratch.persist(&destination)?;
+    Ok(destination)
+}
+
+pub fn restore(data_dir: &Path, snapshot_dir: &Path, run_dir: &Path, name: &str, snapshot: &str) -> Result<(), Box<dyn Error>> {
+    validate_snapshot_name(snapshot)?;
+    ensure_stopped(run_dir, name)?;
+    let source = snapshot_path(snapshot_dir, name, snapshot);
+    if !source.exists() {
+        let available = list_snapshots(snapshot_dir, name);
+        if available.is_empty() {
+            return Err(format!("Node {} has no snapshots", name).into());
+        }
+        return Err(format!("No snapshot {} of {}, available: {}", snapshot, name, available.join(", ")).into());
+    }
+
+    // Unpacked beside the current database, which is only replaced once that succeeded
+    fs::create_dir_all(data_dir)?;
+    let scratch = tempfile::Builder::new().prefix(".restore-").tempdir_in(data_dir)?;
+    tar::Archive::new(zstd::Decoder::new(File::open(&source)?)?).unpack(scratch.path())?;
+
+    let base = base_path(data_dir, name);
+    if base.exists() {
+        fs::remove_dir_all(&base)?;
+    }
+    fs::rename(scratch.into_path(), &base)?;
+    Ok(())
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Dirs {
+        _root: tempfile::TempDir,
+        data: PathBuf,
+        snapshots: PathBuf,
+        run: PathBuf,
+    }
+
+    fn dirs() -> Dirs {
+        let root = tempfile::tempdir().unwrap();
+        let dirs = Dirs { data: root.path().join("data"), snapshots: root.path().join("snapshots"), run: root.path().join("run"), _root: root };
+        fs::create_dir_all(base_path(&dirs.data, "node").join("chains/dev/db")).unwrap();
+        fs::write(base_path(&dirs.data, "node").join("chains/dev/db/CURRENT"), "block 1").unwrap();
+        dirs
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let dirs = dirs();
+        let db_file = base_path(&dirs.data, "node").join("chains/dev/db/CURRENT");
+
+        let path = snapshot(&dirs.data, &dirs.snapshots, &dirs.run, "node", "genesis").unwrap();
+        assert_eq!(path, dirs.snapshots.join("node/genesis.tar.zst"));
+        assert_eq!(list_snapshots(&dirs.snapshots, "node"), ["genesis"]);
+
+        fs::write(&db_file, "block 2").unwrap();
+        fs::write(base_path(&dirs.data, "node").join("extra"), "").unwrap();
+        restore(&dirs.data, &dirs.snapshots, &dirs.run, "node", "genesis").unwrap();
+        assert_eq!(fs::read_to_string(&db_file).unwrap(), "block 1");
+        assert!(!base_path(&dirs.data, "node").join("extra").exists());
+
+        let result = snapshot(&dirs.data, &dirs.snapshots, &dirs.run, "node", "genesis");
+        assert!(result.unwrap_err().to_string().starts_with("Snapshot genesis of node already exists"));
+    }
+
+    #[test]
+    fn test_restore_unknown_snapshot() {
+        let dirs = dirs();
+        let result = restore(&dirs.data, &dirs.snapshots, &dirs.run, "node", "nope");
+        assert_eq!(result.unwrap_err().to_string(), "Node node has no snapshots");
+
+        snapshot(&dirs.data, &dirs.snapshots, &dirs.run, "node", "a").unwrap();
+        let result = restore(&dirs.data, &dirs.snapshots, &dirs.run, "node", "nope");
+        assert_eq!(result.unwrap_err().to_string(), "No snapshot nope of node, available: a");
+
+        // Archives outside the node's snapshot directory are out of reach
+        let result = restore(&dirs.data, &dirs.snapshots, &dirs.run, "node", "../node/a");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid snapshot name \"../node/a\": use letters, digits, `-` and `_`");
+    }
+
+    #[test]
+    fn test_purge() {
+        let dirs = dirs();
+        assert!(purge(&dirs.data, &dirs.run, "node").unwrap());
+        assert!(!base_path(&dirs.data, "node").exists());
+        assert!(!purge(&dirs.data, &dirs.run, "node").unwrap());
+
+        let result = snapshot(&dirs.data, &dirs.snapshots, &dirs.run, "node", "empty");
+        assert!(result.unwrap_err().to_string().starts_with("Node node has no database at"));
+    }
+
+    #[test]
+    fn test_refuses_running_node() {
+        let dirs = dirs();
+        daemon::write_pid(&dirs.run, "node", std::process::id()).unwrap();
+        let result = purge(&dirs.data, &dirs.run, "node");
+        assert_eq!(result.unwrap_err().to_string(), format!("Node node is already running with pid {}, stop it first with `dot stop node`", std::process::id()));
+        assert!(restore(&dirs.data, &dirs.snapshots, &dirs.run, "node", "a").is_err());
+        assert!(base_path(&dirs.data, "node").exists());
+    }
+}
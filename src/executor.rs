@@ -0,0 +1,144 @@
+use std::error::Error;
+use wasmi::core::Pages;
+use wasmi::{Caller, Engine, ExternType, Func, FuncType, Linker, Memory, Module, Store, Val};
+use crate::runtime;
+
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+// State of the few host functions dot provides. Memory is handed out from `__heap_base` upwards and
+// never freed, which is plenty for a single runtime call
+struct Host {
+    memory: Option<Memory>,
+    next_free: u32,
+}
+
+// Calls a runtime API function such as `Metadata_metadata` with SCALE encoded input and returns its
+// SCALE encoded output. Only calls that need no storage or crypto host functions can succeed
+pub fn call(code: &[u8], function: &str, input: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let code = runtime::decompress(code)?;
+    let engine = Engine::default();
+    let module = Module::new(&engine, &code[..]).map_err(|e| format!("Invalid runtime wasm: {}", e))?;
+    let mut store = Store::new(&engine, Host { memory: None, next_free: 0 });
+    let mut linker = Linker::<Host>::new(&engine);
+
+    for import in module.imports() {
+        let (module_name, name) = (import.module(), import.name());
+        match import.ty() {
+            ExternType::Memory(ty) => {
+                let memory = Memory::new(&mut store, *ty).map_err(|e| format!("Invalid runtime memory: {}", e))?;
+                store.data_mut().memory = Some(memory);
+                linker.define(module_name, name, memory)?;
+            }
+            ExternType::Func(ty) => {
+                let func = host_function(&mut store, name, ty.clone());
+                linker.define(module_name, name, func)?;
+            }
+            _ => return Err(format!("Unsupported runtime import {}::{}", module_name, name).into()),
+        }
+    }
+
+    let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
+    if store.data().memory.is_none() {
+        store.data_mut().memory = instance.get_memory(&store, "memory");
+    }
+    let memory = store.data().memory.ok_or("The runtime has no memory")?;
+    let heap_base = match instance.get_global(&store, "__heap_base").map(|global| global.get(&store)) {
+        Some(Val::I32(heap_base)) => heap_base as u32,
+        _ => return Err("The runtime does not export __heap_base".into()),
+    };
+    store.data_mut().next_free = heap_base;
+
+    let entry = instance.get_typed_func::<(i32, i32), i64>(&store, function)
+        .map_err(|_| format!("The runtime has no function {}", function))?;
+    let input_ptr = allocate(&mut store, input.len() as u32)?;
+    memory.write(&mut store, input_ptr as usize, input).map_err(|e| format!("Failed to pass the input of {}: {}", function, e))?;
+    let result = entry.call(&mut store, (input_ptr as i32, input.len() as i32)).map_err(|e| format!("{} failed: {}", function, e))?;
+
+    // The result packs the output pointer into the lower and its length into the upper 32 bits
+    let (output_ptr, output_len) = (result as u32 as usize, (result as u64 >> 32) as usize);
+    let mut output = vec![0; output_len];
+    memory.read(&store, output_ptr, &mut output).map_err(|e| format!("{} returned an invalid buffer: {}", function, e))?;
+    Ok(output)
+}
+
+fn host_function(store: &mut Store<Host>, name: &str, ty: FuncType) -> Func {
+    match name {
+        "ext_allocator_malloc_version_1" => Func::wrap(&mut *store, |mut caller: Caller<'_, Host>, size: u32| -> Result<u32, wasmi::Error> {
+            allocate(&mut caller, size).map_err(|e| wasmi::Error::new(e.to_string()))
+        }),
+        "ext_allocator_free_version_1" => Func::wrap(&mut *store, |_: Caller<'_, Host>, _: u32| {}),
+        // Logging is switched off, so the runtime skips its log calls
+        "ext_logging_max_level_version_1" => Func::wrap(&mut *store, |_: Caller<'_, Host>| 0u32),
+        _ if ty.results().is_empty() && name.starts_with("ext_logging_") => Func::new(&mut *store, ty, |_, _, _| Ok(())),
+        _ => {
+            let message = format!("the runtime called {}, which dot does not provide", name);
+            Func::new(&mut *store, ty, move |_, _, _| Err(wasmi::Error::new(message.clone())))
+        }
+    }
+}
+
+fn allocate(mut context: impl wasmi::AsContextMut<Data = Host>, size: u32) -> Result<u32, Box<dyn Error>> {
+    let mut context = context.as_context_mut();
+    let memory = context.data().memory.ok_or("The runtime has no memory")?;
+    let ptr = context.data().next_free.next_multiple_of(8);
+    let end = ptr as u64 + size as u64;
+    let available = memory.current_pages(&context).to_bytes().unwrap_or(usize::MAX) as u64;
+    if end > available {
+        let pages = Pages::new((end - available).div_ceil(WASM_PAGE_SIZE) as u32).ok_or("Out of runtime memory")?;
+        memory.grow(&mut context, pages).map_err(|e| format!("Out of runtime memory: {}", e))?;
+    }
+    context.data_mut().next_free = end as u32;
+    Ok(ptr)
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A stand-in runtime importing its memory and a few host functions like the real ones do
+    const RUNTIME: &str = r#"
+        (module
+            (import "env" "memory" (memory 1))
+            (import "env" "ext_allocator_malloc_version_1" (func $malloc (param i32) (result i32)))
+            (import "env" "ext_logging_max_level_version_1" (func $max_level (result i32)))
+            (import "env" "ext_logging_log_version_1" (func $log (param i32 i64 i64)))
+            (import "env" "ext_storage_get_version_1" (func $get (param i64) (result i64)))
+            (global (export "__heap_base") i32 (i32.const 1024))
+
+            (func (export "Echo") (param $ptr i32) (param $len i32) (result i64)
+                (local $out i32)
+                (if (call $max_level) (then (call $log (i32.const 0) (i64.const 0) (i64.const 0))))
+                (local.set $out (call $malloc (local.get $len)))
+                (memory.copy (local.get $out) (local.get $ptr) (local.get $len))
+                (i64.or
+                    (i64.extend_i32_u (local.get $out))
+                    (i64.shl (i64.extend_i32_u (local.get $len)) (i64.const 32))))
+
+            (func (export "Storage") (param i32 i32) (result i64)
+                (call $get (i64.const 0))))
+    "#;
+
+    #[test]
+    fn test_call() {
+        let code = wat::parse_str(RUNTIME).unwrap();
+        assert_eq!(call(&code, "Echo", b"metadata").unwrap(), b"metadata");
+
+        // Larger than the initial page, so the allocator grows the memory
+        let large = vec![7u8; 100_000];
+        assert_eq!(call(&code, "Echo", &large).unwrap(), large);
+    }
+
+    #[test]
+    fn test_call_errors() {
+        let code = wat::parse_str(RUNTIME).unwrap();
+        assert_eq!(call(&code, "Nope", &[]).unwrap_err().to_string(), "The runtime has no function Nope");
+        let error = call(&code, "Storage", &[]).unwrap_err().to_string();
+        assert!(error.starts_with("Storage failed"), "{}", error);
+        assert!(error.contains("the runtime called ext_storage_get_version_1, which dot does not provide"), "{}", error);
+        assert!(call(b"not wasm", "Echo", &[]).unwrap_err().to_string().starts_with("Invalid runtime wasm"));
+    }
+}
@@ -0,0 +1,103 @@
+use blake2::digest::consts::{U16, U32, U64, U8};
+use blake2::{Blake2b, Digest};
+use std::hash::Hasher;
+use twox_hash::XxHash64;
+
+pub fn blake2_64(data: &[u8]) -> [u8; 8] {
+    Blake2b::<U8>::digest(data).into()
+}
+
+pub fn blake2_128(data: &[u8]) -> [u8; 16] {
+    Blake2b::<U16>::digest(data).into()
+}
+
+pub fn blake2_256(data: &[u8]) -> [u8; 32] {
+    Blake2b::<U32>::digest(data).into()
+}
+
+pub fn blake2_512(data: &[u8]) -> [u8; 64] {
+    Blake2b::<U64>::digest(data).into()
+}
+
+pub fn twox_64(data: &[u8]) -> [u8; 8] {
+    twox(data)
+}
+
+pub fn twox_128(data: &[u8]) -> [u8; 16] {
+    twox(data)
+}
+
+pub fn twox_256(data: &[u8]) -> [u8; 32] {
+    twox(data)
+}
+
+// Concatenated xxHash64 digests of the data, seeded 0, 1, ...
+fn twox<const N: usize>(data: &[u8]) -> [u8; N] {
+    let mut result = [0u8; N];
+    for (seed, chunk) in result.chunks_mut(8).enumerate() {
+        let mut hasher = XxHash64::with_seed(seed as u64);
+        hasher.write(data);
+        chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+    result
+}
+
+pub fn to_hex(data: &[u8]) -> String {
+    format!("0x{}", hex::encode(data))
+}
+
+pub fn from_hex(value: &str) -> Result<Vec<u8>, String> {
+    let trimmed = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(trimmed).map_err(|e| format!("Invalid hex value {:?}: {}", truncate(value), e))
+}
+
+fn truncate(value: &str) -> &str {
+    match value.char_indices().nth(18) {
+        Some((index, _)) => &value[..index],
+        None => value,
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blake2_256_known_vector() {
+        assert_eq!(
+            to_hex(&blake2_256(b"")),
+            "0x0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"
+        );
+    }
+
+    #[test]
+    fn test_blake2_128_length() {
+        assert_eq!(blake2_128(b"dot").len(), 16);
+    }
+
+    #[test]
+    fn test_twox_128_known_vector() {
+        // Storage prefix of the System pallet
+        assert_eq!(to_hex(&twox_128(b"System")), "0x26aa394eea5630e07c48ae0c9558cef7");
+        assert_eq!(to_hex(&twox_64(b"System")), "0x26aa394eea5630e0");
+        assert_eq!(&twox_256(b"System")[..16], &twox_128(b"System"));
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let bytes = from_hex("0x3a636f6465").unwrap();
+        assert_eq!(bytes, b":code");
+        assert_eq!(to_hex(&bytes), "0x3a636f6465");
+        assert_eq!(from_hex("3a636f6465").unwrap(), b":code");
+    }
+
+    #[test]
+    fn test_from_hex_invalid() {
+        let result = from_hex("0xzz");
+        assert!(result.unwrap_err().starts_with("Invalid hex value \"0xzz\""));
+    }
+}
@@ -184,8 +184,7 @@ pub fn install_omni_node() -> Result<(), Box<dyn Error>> {
     }
 }
 
-pub fn run_download_script<C: CommandRunner>(runner: &C, destination: &Path) -> Result<(), Box<dyn Error>>{
-    let url = "https://github.com/paritytech/polkadot-sdk/releases/download/polkadot-stable2412/asset_hub_westend_runtime.compact.compressed.wasm";
+pub fn run_download_script<C: CommandRunner>(runner: &C, url: &str, destination: &Path) -> Result<(), Box<dyn Error>>{
     
     // Destination file path
     // let destination = Path::new("./nodes/asset_hub_westend_runtime.compact.compressed.wasm");
@@ -283,6 +282,7 @@ mod e2e_tests {
                         ensure_directory_exists, download_file, check_binary, CommandRunner};
     use crate::process::Stdio;
     use crate::os_check::{check_operating_system, get_os_info, is_wsl};
+    use crate::runtime_catalog::default_runtime;
     use std::path::PathBuf;
     use std::error::Error;
 
@@ -386,7 +386,7 @@ mod e2e_tests {
     
 
         let runner = MockRunner::new();
-        assert!(run_download_script(&runner, &wasm_path).is_ok());
+        assert!(run_download_script(&runner, &default_runtime().url(), &wasm_path).is_ok());
     }
 
 
@@ -399,7 +399,7 @@ mod e2e_tests {
         fs::write(&wasm_path, "mock wasm content").expect("Failed to write mock WASM file");
     
         let runner = MockRunner::new();
-        let result = run_download_script(&runner, &wasm_path);
+        let result = run_download_script(&runner, &default_runtime().url(), &wasm_path);
     
         assert!(result.is_ok(), "run_download_script failed with {:?}", result.unwrap_err());
     }
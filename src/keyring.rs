@@ -0,0 +1,125 @@
+use std::error::Error;
+use bip39::{Language, Mnemonic};
+use parity_scale_codec::Encode;
+use schnorrkel::derive::{ChainCode, Derivation};
+use schnorrkel::{ExpansionMode, MiniSecretKey, SecretKey};
+use crate::hashing::{blake2_256, from_hex};
+
+// The phrase the dev accounts //Alice, //Bob, ... are derived from
+pub const DEV_PHRASE: &str = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+
+const SIGNING_CONTEXT: &[u8] = b"substrate";
+
+// An sr25519 key given by a secret URI like subkey takes it: `<phrase or 0x seed>//hard/soft///password`,
+// the phrase defaulting to the dev phrase, so `//Alice` is Alice's dev account
+pub struct Signer {
+    secret: SecretKey,
+}
+
+impl Signer {
+    pub fn from_suri(suri: &str) -> Result<Signer, Box<dyn Error>> {
+        let (suri, password) = match suri.split_once("///") {
+            Some((suri, password)) => (suri, password),
+            None => (suri, ""),
+        };
+        let (phrase, path) = suri.split_at(suri.find('/').unwrap_or(suri.len()));
+        let phrase = match phrase.trim() {
+            "" => DEV_PHRASE,
+            phrase => phrase,
+        };
+
+        let mini_secret = if phrase.starts_with("0x") {
+            MiniSecretKey::from_bytes(&from_hex(phrase)?).map_err(|_| "A secret seed must be 32 bytes")?
+        } else {
+            let mnemonic = Mnemonic::parse_in_normalized(Language::English, phrase).map_err(|e| format!("Invalid secret phrase: {}", e))?;
+            substrate_bip39::mini_secret_from_entropy(&mnemonic.to_entropy(), password).map_err(|e| format!("Invalid secret phrase: {:?}", e))?
+        };
+        let mut secret = mini_secret.expand(ExpansionMode::Ed25519);
+
+        let mut rest = path;
+        while !rest.is_empty() {
+            let (hard, junction) = match rest.strip_prefix("//") {
+                Some(junction) => (true, junction),
+                None => (false, &rest[1..]),
+            };
+            let end = junction.find('/').unwrap_or(junction.len());
+            if end == 0 {
+                return Err(format!("Invalid derivation path {}", path).into());
+            }
+            let chain_code = ChainCode(chain_code(&junction[..end]));
+            secret = if hard {
+                secret.hard_derive_mini_secret_key(Some(chain_code), b"").0.expand(ExpansionMode::Ed25519)
+            } else {
+                secret.derived_key_simple(chain_code, []).0
+            };
+            rest = &junction[end..];
+        }
+        Ok(Signer { secret })
+    }
+
+    pub fn account(&self) -> [u8; 32] {
+        self.secret.to_public().to_bytes()
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        self.secret.sign_simple(SIGNING_CONTEXT, message, &self.secret.to_public()).to_bytes()
+    }
+}
+
+// Numeric junctions are encoded as u64, others as strings, and hashed when longer than 32 bytes
+fn chain_code(junction: &str) -> [u8; 32] {
+    let encoded = match junction.parse::<u64>() {
+        Ok(index) => index.encode(),
+        Err(_) => junction.encode(),
+    };
+    let mut code = [0u8; 32];
+    if encoded.len() > 32 {
+        code = blake2_256(&encoded);
+    } else {
+        code[..encoded.len()].copy_from_slice(&encoded);
+    }
+    code
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashing::to_hex;
+    use schnorrkel::{PublicKey, Signature};
+
+    #[test]
+    fn test_dev_accounts() {
+        // The well known dev accounts, as subkey derives them
+        let alice = Signer::from_suri("//Alice").unwrap();
+        assert_eq!(to_hex(&alice.account()), "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d");
+        let bob = Signer::from_suri(&format!("{}//Bob", DEV_PHRASE)).unwrap();
+        assert_eq!(to_hex(&bob.account()), "0x8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48");
+        let alice_stash = Signer::from_suri("//Alice//stash").unwrap();
+        assert_eq!(to_hex(&alice_stash.account()), "0xbe5ddb1579b72e84524fc29e78609e3caf42e85aa118ebfe0b0ad404b5bdd25f");
+    }
+
+    #[test]
+    fn test_sign() {
+        let alice = Signer::from_suri("//Alice").unwrap();
+        let signature = Signature::from_bytes(&alice.sign(b"payload")).unwrap();
+        let public = PublicKey::from_bytes(&alice.account()).unwrap();
+        assert!(public.verify_simple(SIGNING_CONTEXT, b"payload", &signature).is_ok());
+        assert!(public.verify_simple(SIGNING_CONTEXT, b"other", &signature).is_err());
+    }
+
+    #[test]
+    fn test_invalid_suri() {
+        // Signer has no Debug, which keeps secrets out of panics
+        let error = |suri: &str| Signer::from_suri(suri).err().unwrap().to_string();
+        assert!(error("not a phrase//Alice").starts_with("Invalid secret phrase"));
+        assert_eq!(error("0x1234"), "A secret seed must be 32 bytes");
+        assert_eq!(error("//Alice//"), "Invalid derivation path //Alice//");
+        // A seed, soft junctions and a password are accepted too
+        assert!(Signer::from_suri(&format!("{}/0", to_hex(&[1; 32]))).is_ok());
+        assert!(Signer::from_suri(&format!("{}///secret", DEV_PHRASE)).is_ok());
+    }
+}
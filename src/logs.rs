@@ -0,0 +1,269 @@
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use chrono::{Local, NaiveDateTime};
+
+pub const LOG_DIR: &str = "./logs";
+pub const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_MAX_FILES: usize = 5;
+
+// Substrate prefixes every log line with a local timestamp such as `2024-10-18 12:34:56.789`
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const TIMESTAMP_LEN: usize = 19;
+
+pub fn log_path(log_dir: &Path, name: &str) -> PathBuf {
+    log_dir.join(format!("{}.log", name))
+}
+
+pub fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", index));
+    PathBuf::from(rotated)
+}
+
+// Appends to <name>.log and shifts it to <name>.log.1 .. <name>.log.N once it grows past max_bytes
+pub struct RotatingWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingWriter {
+    pub fn open(path: &Path, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(RotatingWriter { path: path.to_path_buf(), max_bytes, max_files, file, written })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = rotated_path(&self.path, index);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+            self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+// The current log and its rotations, oldest first
+pub fn log_files(path: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = (1..)
+        .map(|index| rotated_path(path, index))
+        .take_while(|rotated| rotated.exists())
+        .collect();
+    files.reverse();
+    if path.exists() {
+        files.push(path.to_path_buf());
+    }
+    files
+}
+
+pub fn read_lines(path: &Path, lines: Option<usize>, since: Option<NaiveDateTime>) -> Result<Vec<String>, Box<dyn Error>> {
+    let files = log_files(path);
+    if files.is_empty() {
+        return Err(format!("No logs found at {:?}", path).into());
+    }
+
+    let mut selected = Vec::new();
+    // Lines without a timestamp (e.g. panic backtraces) follow the line before them
+    let mut include = since.is_none();
+    for file in files {
+        let content = fs::read(&file)?;
+        for line in String::from_utf8_lossy(&content).lines() {
+            if let (Some(since), Some(timestamp)) = (since, line_timestamp(line)) {
+                include = timestamp >= since;
+            }
+            if include {
+                selected.push(line.to_string());
+            }
+        }
+    }
+
+    if let Some(lines) = lines {
+        let skip = selected.len().saturating_sub(lines);
+        selected.drain(..skip);
+    }
+    Ok(selected)
+}
+
+fn line_timestamp(line: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(line.get(..TIMESTAMP_LEN)?, TIMESTAMP_FORMAT).ok()
+}
+
+// Accepts a relative duration such as 30s, 10m, 2h or 1d, or a local time `YYYY-MM-DD HH:MM:SS`
+pub fn parse_since(value: &str, now: NaiveDateTime) -> Result<NaiveDateTime, String> {
+    for format in [TIMESTAMP_FORMAT, "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(timestamp) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(timestamp);
+        }
+    }
+
+    let invalid = || format!("Invalid --since value {:?}: use e.g. 30s, 10m, 2h, 1d or \"YYYY-MM-DD HH:MM:SS\"", value);
+    let split = value.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let amount: i64 = value[..split].parse().map_err(|_| invalid())?;
+    let duration = match &value[split..] {
+        "s" => chrono::Duration::try_seconds(amount),
+        "m" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" => chrono::Duration::try_days(amount),
+        _ => None,
+    }
+    .ok_or_else(invalid)?;
+    now.checked_sub_signed(duration).ok_or_else(invalid)
+}
+
+pub fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+// Copies whatever was appended since `offset` and returns the new offset, starting over after a rotation
+pub fn follow_once<W: Write>(path: &Path, offset: u64, out: &mut W) -> io::Result<u64> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let len = file.metadata()?.len();
+    let offset = if len < offset { 0 } else { offset };
+    if len > offset {
+        file.seek(SeekFrom::Start(offset))?;
+        io::copy(&mut file.take(len - offset), out)?;
+        out.flush()?;
+    }
+    Ok(len)
+}
+
+pub fn follow<W: Write>(path: &Path, mut offset: u64, out: &mut W) -> io::Result<()> {
+    loop {
+        offset = follow_once(path, offset, out)?;
+        thread::sleep(Duration::from_millis(250));
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn test_rotating_writer_shifts_files() {
+        let temp_dir = tempdir().unwrap();
+        let path = log_path(temp_dir.path(), "node");
+
+        let mut writer = RotatingWriter::open(&path, 10, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 2)).unwrap(), "second\n");
+        assert!(!rotated_path(&path, 3).exists());
+        assert_eq!(log_files(&path), vec![rotated_path(&path, 2), rotated_path(&path, 1), path.clone()]);
+    }
+
+    #[test]
+    fn test_rotating_writer_appends_to_existing_log() {
+        let temp_dir = tempdir().unwrap();
+        let path = log_path(&temp_dir.path().join("logs"), "node");
+
+        RotatingWriter::open(&path, 100, 1).unwrap().write_all(b"one\n").unwrap();
+        RotatingWriter::open(&path, 100, 1).unwrap().write_all(b"two\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+    }
+
+    #[test]
+    fn test_read_lines_since_across_rotations() {
+        let temp_dir = tempdir().unwrap();
+        let path = log_path(temp_dir.path(), "node");
+        fs::write(rotated_path(&path, 1), "2024-10-18 12:00:00.000  INFO old\n2024-10-18 12:05:00.000  INFO boundary\n").unwrap();
+        fs::write(&path, "Thread 'main' panicked\n2024-10-18 12:10:00.000  INFO new\n  backtrace line\n").unwrap();
+
+        let lines = read_lines(&path, None, Some(at("2024-10-18 12:05:00"))).unwrap();
+        assert_eq!(lines, vec![
+            "2024-10-18 12:05:00.000  INFO boundary",
+            "Thread 'main' panicked",
+            "2024-10-18 12:10:00.000  INFO new",
+            "  backtrace line",
+        ]);
+
+        let lines = read_lines(&path, Some(2), None).unwrap();
+        assert_eq!(lines, vec!["2024-10-18 12:10:00.000  INFO new", "  backtrace line"]);
+    }
+
+    #[test]
+    fn test_read_lines_missing_log() {
+        let temp_dir = tempdir().unwrap();
+        let path = log_path(temp_dir.path(), "missing");
+        assert_eq!(read_lines(&path, None, None).unwrap_err().to_string(), format!("No logs found at {:?}", path));
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = at("2024-10-18 12:00:00");
+        assert_eq!(parse_since("90s", now).unwrap(), at("2024-10-18 11:58:30"));
+        assert_eq!(parse_since("10m", now).unwrap(), at("2024-10-18 11:50:00"));
+        assert_eq!(parse_since("1d", now).unwrap(), at("2024-10-17 12:00:00"));
+        assert_eq!(parse_since("2024-10-18T08:30:00", now).unwrap(), at("2024-10-18 08:30:00"));
+        assert!(parse_since("10 minutes", now).is_err());
+        assert!(parse_since("m", now).is_err());
+    }
+
+    #[test]
+    fn test_follow_once_handles_rotation() {
+        let temp_dir = tempdir().unwrap();
+        let path = log_path(temp_dir.path(), "node");
+        let mut out = Vec::new();
+
+        assert_eq!(follow_once(&path, 0, &mut out).unwrap(), 0);
+        fs::write(&path, "first\n").unwrap();
+        let offset = follow_once(&path, 0, &mut out).unwrap();
+        fs::write(&path, "first\nsecond\n").unwrap();
+        let offset = follow_once(&path, offset, &mut out).unwrap();
+
+        // After a rotation the fresh file is shorter than the offset
+        fs::write(&path, "third\n").unwrap();
+        follow_once(&path, offset, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "first\nsecond\nthird\n");
+    }
+}
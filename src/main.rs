@@ -3,12 +3,40 @@ use clap::Command;
 use std::error::Error;
 use crate::install::RealCommandRunner;
 use std::path::Path;
+use std::io::{IsTerminal, Write};
 
 pub mod serve;
 pub mod template;
 pub mod install;
 pub mod chain_specs;
 pub mod os_check;
+pub mod hashing;
+pub mod ss58;
+pub mod chain_spec_validate;
+pub mod chain_spec_diff;
+pub mod runtime;
+pub mod runtime_catalog;
+pub mod daemon;
+pub mod logs;
+pub mod supervisor;
+pub mod rpc;
+pub mod readiness;
+pub mod node_options;
+pub mod ports;
+pub mod topology;
+pub mod network;
+pub mod db;
+pub mod metrics;
+pub mod scale;
+pub mod metadata;
+pub mod watch;
+pub mod executor;
+pub mod storage;
+pub mod keyring;
+pub mod tx;
+pub mod upgrade;
+#[cfg(test)]
+mod test_support;
 
 
 fn main() {
@@ -33,6 +61,21 @@ fn main() {
                         .global(true)
                         .action(clap::ArgAction::Set), // Use Set to capture the value
                 )
+                .arg(
+                    clap::Arg::new("runtime")
+                        .help("The system parachain runtime to download and generate a chain spec for")
+                        .long("runtime")
+                        .default_value(runtime_catalog::DEFAULT_RUNTIME)
+                        .action(clap::ArgAction::Set),
+                )
+                .arg(
+                    clap::Arg::new("grace_period")
+                        .help("Seconds to wait after forwarding Ctrl-C/SIGTERM to the node before sending SIGKILL")
+                        .long("grace-period")
+                        .value_name("SECS")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("30"),
+                )
         )
         .subcommand(
             Command::new("serve")
@@ -40,11 +83,730 @@ fn main() {
                 .arg(
                     clap::Arg::new("chain_spec")
                         .help("The fullpath to the chain spec file")
-                        .long("chain-spec")
                         .required(false)
                         .value_name("CHAIN_SPEC")
                         .index(1),
                 )
+                .arg(
+                    clap::Arg::new("runtime")
+                        .help("Generate a fresh chain spec for this runtime wasm and serve it")
+                        .long("runtime")
+                        .value_name("WASM")
+                        .conflicts_with("chain_spec"),
+                )
+                .arg(
+                    clap::Arg::new("detach")
+                        .help("Run the node in the background and return immediately")
+                        .long("detach")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("name")
+                        .help("Name used to manage the node with `dot stop` and `dot ps`, also passed to omni-node when given")
+                        .long("name")
+                        .value_name("NAME")
+                        .default_value(serve::DEFAULT_NODE_NAME),
+                )
+                .arg(
+                    clap::Arg::new("wait_ready")
+                        .help("Fail, and stop the node, unless it is producing blocks or has peers within --timeout")
+                        .long("wait-ready")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("timeout")
+                        .help("Seconds to wait for the node to become ready")
+                        .long("timeout")
+                        .value_name("SECS")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("120"),
+                )
+                .arg(
+                    clap::Arg::new("grace_period")
+                        .help("Seconds to wait after forwarding Ctrl-C/SIGTERM to the node before sending SIGKILL")
+                        .long("grace-period")
+                        .value_name("SECS")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("30"),
+                )
+                .arg(
+                    clap::Arg::new("restart")
+                        .help("Restart the node when it crashes")
+                        .long("restart")
+                        .value_parser(["no", "on-failure"])
+                        .default_value("no"),
+                )
+                .arg(
+                    clap::Arg::new("max_restarts")
+                        .help("Give up after this many restarts")
+                        .long("max-restarts")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u32))
+                        .default_value("5"),
+                )
+                .arg(
+                    clap::Arg::new("backoff")
+                        .help("Seconds to wait before the first restart, doubled for every further restart (at most 5 minutes)")
+                        .long("backoff")
+                        .value_name("SECS")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("1"),
+                )
+                .arg(
+                    clap::Arg::new("dev")
+                        .help("Run omni-node in development mode")
+                        .long("dev")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("rpc_port")
+                        .help("JSON-RPC port of the node (9944 by default)")
+                        .long("rpc-port")
+                        .value_name("PORT")
+                        .value_parser(clap::value_parser!(u16).range(1..)),
+                )
+                .arg(
+                    clap::Arg::new("port")
+                        .help("p2p port of the node (30333 by default)")
+                        .long("port")
+                        .value_name("PORT")
+                        .value_parser(clap::value_parser!(u16).range(1..)),
+                )
+                .arg(
+                    clap::Arg::new("prometheus_port")
+                        .help("Prometheus metrics port of the node (9615 by default)")
+                        .long("prometheus-port")
+                        .value_name("PORT")
+                        .value_parser(clap::value_parser!(u16).range(1..)),
+                )
+                .arg(
+                    clap::Arg::new("base_path")
+                        .help("Directory for the node's database and keystore")
+                        .long("base-path")
+                        .value_name("PATH"),
+                )
+                .arg(
+                    clap::Arg::new("tmp")
+                        .help("Keep the node's database in a temporary directory removed on exit")
+                        .long("tmp")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("rpc_cors")
+                        .help("Origins allowed to call the JSON-RPC server: `all` or a comma separated list")
+                        .long("rpc-cors")
+                        .value_name("ORIGINS"),
+                )
+                .arg(
+                    clap::Arg::new("log")
+                        .help("Log filter, e.g. `info,runtime=debug`")
+                        .long("log")
+                        .value_name("FILTER"),
+                )
+                .arg(
+                    clap::Arg::new("dev_block_time")
+                        .help("Produce a block every MS milliseconds")
+                        .long("dev-block-time")
+                        .value_name("MS")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(
+                    clap::Arg::new("instant_seal")
+                        .help("Produce a block as soon as a transaction arrives")
+                        .long("instant-seal")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("node_args")
+                        .help("Arguments passed to omni-node as is, after `--`. A second `--` starts the relay chain arguments")
+                        .value_name("NODE_ARGS")
+                        .num_args(1..)
+                        .index(2)
+                        .last(true),
+                )
+        )
+        .subcommand(
+            Command::new("stop")
+                .about("Stop nodes started with `dot serve`")
+                .arg(
+                    clap::Arg::new("name")
+                        .help("The node to stop")
+                        .required_unless_present("all")
+                        .value_name("NAME")
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("all")
+                        .help("Stop every node managed by dot")
+                        .long("all")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("name"),
+                )
+                .arg(
+                    clap::Arg::new("timeout")
+                        .help("Seconds to wait after SIGTERM before sending SIGKILL")
+                        .long("timeout")
+                        .value_name("SECS")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("30"),
+                )
+        )
+        .subcommand(
+            Command::new("ps")
+                .about("List nodes started with `dot serve`, with their restarts and last exit")
+        )
+        .subcommand(
+            Command::new("logs")
+                .about("Show the captured output of a node")
+                .arg(
+                    clap::Arg::new("name")
+                        .help("The node whose logs to show, e.g. omni-node or minimal-template")
+                        .required(true)
+                        .value_name("NAME")
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("follow")
+                        .help("Keep printing new output as the node writes it")
+                        .long("follow")
+                        .short('f')
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("since")
+                        .help("Only show lines since a duration ago (30s, 10m, 2h, 1d) or a local time \"YYYY-MM-DD HH:MM:SS\"")
+                        .long("since")
+                        .value_name("SINCE"),
+                )
+                .arg(
+                    clap::Arg::new("lines")
+                        .help("Number of lines to show from the end of the log")
+                        .long("lines")
+                        .short('n')
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("50"),
+                )
+        )
+        .subcommand(
+            Command::new("supervise")
+                .about("Run a node with its output captured under ./logs (used by `dot serve --detach`)")
+                .hide(true)
+                .arg(
+                    clap::Arg::new("name")
+                        .long("name")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("grace_period")
+                        .help("Seconds to wait after forwarding Ctrl-C/SIGTERM to the node before sending SIGKILL")
+                        .long("grace-period")
+                        .value_name("SECS")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("30"),
+                )
+                .arg(
+                    clap::Arg::new("restart")
+                        .help("Restart the node when it crashes")
+                        .long("restart")
+                        .value_parser(["no", "on-failure"])
+                        .default_value("no"),
+                )
+                .arg(
+                    clap::Arg::new("max_restarts")
+                        .help("Give up after this many restarts")
+                        .long("max-restarts")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u32))
+                        .default_value("5"),
+                )
+                .arg(
+                    clap::Arg::new("backoff")
+                        .help("Seconds to wait before the first restart, doubled for every further restart (at most 5 minutes)")
+                        .long("backoff")
+                        .value_name("SECS")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("1"),
+                )
+                .arg(
+                    clap::Arg::new("command")
+                        .required(true)
+                        .num_args(1..)
+                        .last(true),
+                )
+        )
+        .subcommand(
+            Command::new("chain-spec")
+                .about("Chain spec utilities")
+                .subcommand(
+                    Command::new("generate")
+                        .about("Generate a named chain spec under ./chain-specs")
+                        .arg(
+                            clap::Arg::new("name")
+                                .help("Name of the chain spec, written to ./chain-specs/<NAME>.json")
+                                .long("name")
+                                .value_name("NAME")
+                                .default_value(chain_specs::DEFAULT_CHAIN_SPEC)
+                                .conflicts_with("output"),
+                        )
+                        .arg(
+                            clap::Arg::new("output")
+                                .help("Write the chain spec to this path instead")
+                                .long("output")
+                                .value_name("PATH"),
+                        )
+                        .arg(
+                            clap::Arg::new("wasm")
+                                .help("The runtime wasm to build the chain spec from")
+                                .long("wasm")
+                                .value_name("WASM"),
+                        )
+                        .arg(
+                            clap::Arg::new("runtime")
+                                .help("The catalog runtime providing the relay chain and para id")
+                                .long("runtime")
+                                .default_value(runtime_catalog::DEFAULT_RUNTIME),
+                        )
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List the chain specs under ./chain-specs")
+                )
+                .subcommand(
+                    Command::new("raw")
+                        .about("Convert a plain chain spec to its raw form")
+                        .arg(
+                            clap::Arg::new("input")
+                                .help("The plain chain spec to convert")
+                                .required(true)
+                                .value_name("IN")
+                                .index(1),
+                        )
+                        .arg(
+                            clap::Arg::new("output")
+                                .help("Where to write the raw chain spec")
+                                .required(true)
+                                .value_name("OUT")
+                                .index(2),
+                        )
+                )
+                .subcommand(
+                    Command::new("validate")
+                        .about("Validate a chain spec before handing it to omni-node")
+                        .arg(
+                            clap::Arg::new("path")
+                                .help("The chain spec to validate")
+                                .required(false)
+                                .value_name("CHAIN_SPEC")
+                                .default_value("./chain-specs/chain_spec.json")
+                                .index(1),
+                        )
+                        .arg(
+                            clap::Arg::new("wasm")
+                                .help("Check that the embedded :code matches this runtime wasm")
+                                .long("wasm")
+                                .value_name("WASM")
+                                .conflicts_with("code_hash"),
+                        )
+                        .arg(
+                            clap::Arg::new("code_hash")
+                                .help("Check that the embedded :code has this blake2-256 hash")
+                                .long("code-hash")
+                                .value_name("HASH"),
+                        )
+                )
+                .subcommand(
+                    Command::new("diff")
+                        .about("Show a semantic diff between two chain specs")
+                        .arg(
+                            clap::Arg::new("old")
+                                .help("The original chain spec")
+                                .required(true)
+                                .value_name("A")
+                                .index(1),
+                        )
+                        .arg(
+                            clap::Arg::new("new")
+                                .help("The chain spec to compare against")
+                                .required(true)
+                                .value_name("B")
+                                .index(2),
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("runtime")
+                .about("Runtime wasm utilities")
+                .subcommand(
+                    Command::new("info")
+                        .about("Show the version, APIs and code hash of a runtime wasm")
+                        .arg(
+                            clap::Arg::new("wasm")
+                                .help("The runtime wasm, compressed or not")
+                                .required(true)
+                                .value_name("WASM")
+                                .index(1),
+                        )
+                        .arg(
+                            clap::Arg::new("format")
+                                .help("Output format")
+                                .long("format")
+                                .value_parser(["table", "json"])
+                                .default_value("table"),
+                        )
+                )
+                .subcommand(
+                    Command::new("upgrade")
+                        .about("Upgrade the runtime of a running node through sudo and wait until the chain runs it")
+                        .arg(
+                            clap::Arg::new("wasm")
+                                .help("The new runtime wasm, compressed or not")
+                                .long("wasm")
+                                .value_name("WASM")
+                                .required(true),
+                        )
+                        .arg(
+                            clap::Arg::new("signer")
+                                .help("Secret URI of the chain's sudo key")
+                                .long("signer")
+                                .value_name("SURI")
+                                .default_value("//Alice"),
+                        )
+                        .arg(
+                            clap::Arg::new("authorize")
+                                .help("Authorize the code hash with sudo, then apply the upgrade, instead of a sudo set_code")
+                                .long("authorize")
+                                .action(clap::ArgAction::SetTrue),
+                        )
+                        .arg(
+                            clap::Arg::new("timeout")
+                                .help("Seconds to wait for the chain to enact the upgrade once it is in a block")
+                                .long("timeout")
+                                .value_name("SECS")
+                                .value_parser(clap::value_parser!(u64))
+                                .default_value("300"),
+                        )
+                        .arg(
+                            clap::Arg::new("node")
+                                .help("The served node to upgrade, by default the node of the last dot serve")
+                                .long("node")
+                                .value_name("NAME")
+                                .conflicts_with("url"),
+                        )
+                        .arg(
+                            clap::Arg::new("url")
+                                .help("The RPC endpoint of the node, e.g. ws://127.0.0.1:9944")
+                                .long("url")
+                                .value_name("URL"),
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("network")
+                .about("Run a local relay chain and parachain network")
+                .subcommand(
+                    Command::new("up")
+                        .about("Start the validators and collators described by a topology file")
+                        .arg(
+                            clap::Arg::new("topology")
+                                .help("The topology TOML file")
+                                .required(true)
+                                .value_name("TOPOLOGY")
+                                .index(1),
+                        )
+                )
+                .subcommand(
+                    Command::new("down")
+                        .about("Stop every node of a network")
+                        .arg(
+                            clap::Arg::new("name")
+                                .help("The network name, the topology's `name` or else its file name")
+                                .required(true)
+                                .value_name("NAME")
+                                .index(1),
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("db")
+                .about("Manage the databases of nodes under ./data")
+                .subcommand(
+                    Command::new("purge")
+                        .about("Delete a stopped node's database to restart its chain from genesis")
+                        .arg(
+                            clap::Arg::new("node")
+                                .help("The node name")
+                                .required(true)
+                                .value_name("NODE")
+                                .index(1),
+                        )
+                )
+                .subcommand(
+                    Command::new("snapshot")
+                        .about("Save a stopped node's database under ./snapshots")
+                        .arg(
+                            clap::Arg::new("node")
+                                .help("The node name")
+                                .required(true)
+                                .value_name("NODE")
+                                .index(1),
+                        )
+                        .arg(
+                            clap::Arg::new("snapshot")
+                                .help("Name of the snapshot")
+                                .required(true)
+                                .value_name("SNAPSHOT")
+                                .index(2),
+                        )
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about("Replace a stopped node's database with a snapshot")
+                        .arg(
+                            clap::Arg::new("node")
+                                .help("The node name")
+                                .required(true)
+                                .value_name("NODE")
+                                .index(1),
+                        )
+                        .arg(
+                            clap::Arg::new("snapshot")
+                                .help("Name of the snapshot")
+                                .required(true)
+                                .value_name("SNAPSHOT")
+                                .index(2),
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("metrics")
+                .about("Show key Prometheus metrics of a running node")
+                .arg(
+                    clap::Arg::new("node")
+                        .help("The node name")
+                        .required(true)
+                        .value_name("NODE")
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("watch")
+                        .help("Keep scraping the metrics and print a row per refresh")
+                        .long("watch")
+                        .short('w')
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("interval")
+                        .help("Seconds between refreshes with --watch")
+                        .long("interval")
+                        .value_name("SECS")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .default_value("2")
+                        .requires("watch"),
+                )
+        )
+        .subcommand(
+            Command::new("rpc")
+                .about("Call a JSON-RPC method of a node, or stream a subscription")
+                .arg(
+                    clap::Arg::new("method")
+                        .help("The method to call, e.g. system_health or chain_subscribeNewHeads")
+                        .required_unless_present("list")
+                        .value_name("METHOD")
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("params")
+                        .help("Method parameters, each taken as JSON when it parses as JSON and as a string otherwise")
+                        .value_name("PARAMS")
+                        .num_args(0..)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("node")
+                        .help("The served node to talk to, by default the node of the last dot serve")
+                        .long("node")
+                        .value_name("NAME")
+                        .conflicts_with("url"),
+                )
+                .arg(
+                    clap::Arg::new("url")
+                        .help("The RPC endpoint to talk to, e.g. http://127.0.0.1:9944 or ws://127.0.0.1:9944")
+                        .long("url")
+                        .value_name("URL"),
+                )
+                .arg(
+                    clap::Arg::new("list")
+                        .help("List the methods the node supports (rpc_methods)")
+                        .long("list")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("method"),
+                )
+                .arg(
+                    clap::Arg::new("count")
+                        .help("Stop a subscription after this many notifications")
+                        .long("count")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..)),
+                )
+        )
+        .subcommand(
+            Command::new("watch")
+                .about("Print every new and finalized block of a running node with its extrinsics and events")
+                .arg(
+                    clap::Arg::new("node")
+                        .help("The served node to watch, by default the node of the last dot serve")
+                        .long("node")
+                        .value_name("NAME")
+                        .conflicts_with("url"),
+                )
+                .arg(
+                    clap::Arg::new("url")
+                        .help("The RPC endpoint to watch, e.g. ws://127.0.0.1:9944")
+                        .long("url")
+                        .value_name("URL"),
+                )
+                .arg(
+                    clap::Arg::new("count")
+                        .help("Stop after this many new blocks")
+                        .long("count")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..)),
+                )
+        )
+        .subcommand(
+            Command::new("metadata")
+                .about("Fetch the runtime metadata of a running node or a runtime wasm and export it")
+                .arg(
+                    clap::Arg::new("wasm")
+                        .help("Read the metadata from this runtime wasm instead of a node")
+                        .long("wasm")
+                        .value_name("WASM")
+                        .conflicts_with_all(["node", "url"]),
+                )
+                .arg(
+                    clap::Arg::new("node")
+                        .help("The served node to ask, by default the node of the last dot serve")
+                        .long("node")
+                        .value_name("NAME")
+                        .conflicts_with("url"),
+                )
+                .arg(
+                    clap::Arg::new("url")
+                        .help("The RPC endpoint to ask, e.g. http://127.0.0.1:9944")
+                        .long("url")
+                        .value_name("URL"),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .help("Output format: a pallet listing, the decoded metadata as JSON, or the raw SCALE bytes (as hex on a terminal)")
+                        .long("format")
+                        .value_parser(["text", "json", "scale"])
+                        .default_value("text"),
+                )
+                .arg(
+                    clap::Arg::new("pallet")
+                        .help("Only list this pallet")
+                        .long("pallet")
+                        .value_name("NAME"),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .help("Write the metadata to this file instead of stdout")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE"),
+                )
+        )
+        .subcommand(
+            Command::new("storage")
+                .about("Read a storage item of a running node by pallet and item name, decoded with the runtime metadata")
+                .arg(
+                    clap::Arg::new("pallet")
+                        .help("The pallet, e.g. System")
+                        .required(true)
+                        .value_name("PALLET")
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("item")
+                        .help("The storage item, e.g. Account")
+                        .required(true)
+                        .value_name("ITEM")
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("keys")
+                        .help("Keys of a storage map, each taken as JSON when it parses as JSON and as a string otherwise")
+                        .value_name("KEYS")
+                        .num_args(0..)
+                        .index(3),
+                )
+                .arg(
+                    clap::Arg::new("node")
+                        .help("The served node to ask, by default the node of the last dot serve")
+                        .long("node")
+                        .value_name("NAME")
+                        .conflicts_with("url"),
+                )
+                .arg(
+                    clap::Arg::new("url")
+                        .help("The RPC endpoint to ask, e.g. http://127.0.0.1:9944")
+                        .long("url")
+                        .value_name("URL"),
+                )
+        )
+        .subcommand(
+            Command::new("tx")
+                .about("Sign a call with a dev account, submit it to a running node and wait for it to be included")
+                .arg(
+                    clap::Arg::new("pallet")
+                        .help("The pallet, e.g. Balances")
+                        .required(true)
+                        .value_name("PALLET")
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("call")
+                        .help("The call, e.g. transfer_keep_alive or transferKeepAlive")
+                        .required(true)
+                        .value_name("CALL")
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("args")
+                        .help("Call arguments in order, each taken as JSON when it parses as JSON and as a string otherwise")
+                        .value_name("ARGS")
+                        .num_args(0..)
+                        .index(3),
+                )
+                .arg(
+                    clap::Arg::new("signer")
+                        .help("Secret URI of the sr25519 signing key, e.g. //Bob for a dev account")
+                        .long("signer")
+                        .value_name("SURI")
+                        .default_value("//Alice"),
+                )
+                .arg(
+                    clap::Arg::new("wait")
+                        .help("Return once the transaction is in a block, or only once that block is finalized")
+                        .long("wait")
+                        .value_parser(["in-block", "finalized"])
+                        .default_value("finalized"),
+                )
+                .arg(
+                    clap::Arg::new("node")
+                        .help("The served node to submit to, by default the node of the last dot serve")
+                        .long("node")
+                        .value_name("NAME")
+                        .conflicts_with("url"),
+                )
+                .arg(
+                    clap::Arg::new("url")
+                        .help("The RPC endpoint to submit to, e.g. ws://127.0.0.1:9944")
+                        .long("url")
+                        .value_name("URL"),
+                )
         )
     .get_matches();
 
@@ -52,6 +814,20 @@ fn main() {
     match matches.subcommand() {
         Some(("install", sub_matches)) => handle_install(sub_matches),
         Some(("serve", sub_matches)) => handle_serve(sub_matches),
+        Some(("stop", sub_matches)) => handle_stop(sub_matches),
+        Some(("ps", _)) => handle_ps(),
+        Some(("logs", sub_matches)) => handle_logs(sub_matches),
+        Some(("supervise", sub_matches)) => handle_supervise(sub_matches),
+        Some(("chain-spec", sub_matches)) => handle_chain_spec(sub_matches),
+        Some(("runtime", sub_matches)) => handle_runtime(sub_matches),
+        Some(("network", sub_matches)) => handle_network(sub_matches),
+        Some(("db", sub_matches)) => handle_db(sub_matches),
+        Some(("metrics", sub_matches)) => handle_metrics(sub_matches),
+        Some(("rpc", sub_matches)) => handle_rpc(sub_matches),
+        Some(("watch", sub_matches)) => handle_watch(sub_matches),
+        Some(("metadata", sub_matches)) => handle_metadata(sub_matches),
+        Some(("storage", sub_matches)) => handle_storage(sub_matches),
+        Some(("tx", sub_matches)) => handle_tx(sub_matches),
         _ => {
             eprintln!("No valid subcommand provided. Use --help for more information.");
             process::exit(1);
@@ -71,24 +847,31 @@ fn handle_install(matches: &clap::ArgMatches) {
         handle_chain_spec_options(&chain, matches);
         sub_commands.push(("--chain-spec".to_string(), chain.clone()));
     } else {
-        println!("Installing default configuration.");
-        install("default");
+        let runtime_name = matches.get_one::<String>("runtime").expect("runtime has a default");
+        let runtime = match runtime_catalog::find_runtime(runtime_name) {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+        println!("Installing {} configuration.", runtime.name);
+        install(runtime);
     }
 }
 
-pub fn install(_template: &str){
+pub fn install(runtime: &runtime_catalog::RuntimeSpec){
     let mut results: Vec<(Result<(), Box<dyn Error>>, &str)> = Vec::new();
     
-    let wasm_source_path =  Path::new("./nodes/asset_hub_westend_runtime.compact.compressed.wasm");
+    let wasm_source_path = runtime.wasm_path();
     let chain_spec_builder_path = Path::new("./binaries/chain-spec-builder");
-    let destination = Path::new("./nodes/asset_hub_westend_runtime.compact.compressed.wasm");
 
     let real_runner = RealCommandRunner;
     results.push((install::install_polkadot(&real_runner), "$ Polkadot installation"));
     results.push((install::install_chain_spec_builder(), "$ Chain spec builder installation"));
     results.push((install::install_omni_node(), "$ Omni-node installation"));
-    results.push((install::run_download_script(&real_runner, &destination ), "$ Wasm file download script"));
-    results.push((chain_specs::gen_chain_spec(Some(&wasm_source_path), Some(&chain_spec_builder_path)), "$ Chain spec script"));
+    results.push((install::run_download_script(&real_runner, &runtime.url(), &wasm_source_path), "$ Wasm file download script"));
+    results.push((chain_specs::gen_chain_spec(Some(&wasm_source_path), Some(&chain_spec_builder_path), None, Some(runtime)), "$ Chain spec script"));
 
     println!(" ");
     println!("===========================================================================");
@@ -105,16 +888,16 @@ pub fn install(_template: &str){
 }
 
 fn handle_template_options(template_name: &str, matches: &clap::ArgMatches) {
-    let args: Vec<&str> = matches.get_many::<String>("args")
+    let args: Vec<&str> = matches.try_get_many::<String>("args").ok().flatten()
         .map(|values| values.map(|s| s.as_str()).collect())
         .unwrap_or_else(|| Vec::new());
 
     println!("Called template installation");
-    let _ = template::run_template(&args, template_name);
+    let _ = template::run_template(&args, template_name, grace_period(matches));
 }
 
 fn handle_chain_spec_options(chain_spec: &str, matches: &clap::ArgMatches) {
-    let _args: Vec<&str> = matches.get_many::<String>("args")
+    let _args: Vec<&str> = matches.try_get_many::<String>("args").ok().flatten()
         .map(|values| values.map(|s| s.as_str()).collect())
         .unwrap_or_else(|| Vec::new());
 
@@ -132,14 +915,681 @@ fn handle_chain_spec_options(chain_spec: &str, matches: &clap::ArgMatches) {
 }
 
 fn handle_serve(matches: &clap::ArgMatches) {
-    let mut args: Vec<&str> = matches.get_one::<String>("ARGS").map(|s| s.split_whitespace())
-            .unwrap_or_else(|| "".split_whitespace())
-            .collect(); 
-    if args.is_empty() {
-        args = vec!["--chain", "./chain-specs/chain_spec.json"];
+    let detach = matches.get_flag("detach");
+    let options = |args: &[&str]| -> serve::ServeOptions {
+        serve::ServeOptions {
+            name: matches.get_one::<String>("name").expect("name has a default").clone(),
+            ready: readiness::ReadyCheck {
+                timeout: std::time::Duration::from_secs(*matches.get_one::<u64>("timeout").expect("timeout has a default")),
+                required: matches.get_flag("wait_ready"),
+            },
+            grace: grace_period(matches),
+            restart: restart_policy(matches),
+            node: node_options(matches, args),
+        }
+    };
+
+    let node_args: Vec<&str> = matches.get_many::<String>("node_args")
+        .map(|values| values.map(String::as_str).collect())
+        .unwrap_or_default();
+
+    if let Some(wasm) = matches.get_one::<String>("runtime") {
+        let options = options(&node_args);
+        let result = if detach {
+            serve::serve_runtime_detached(Path::new(wasm), &node_args, &options).map(|_| ())
+        } else {
+            serve::serve_runtime(Path::new(wasm), &node_args, &options)
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to serve runtime {}: {}", wasm, e);
+            process::exit(1);
+        }
+        process::exit(0);
+    }
+
+    let chain_spec = matches.get_one::<String>("chain_spec");
+    if chain_spec.is_some() && serve::has_flag(&node_args, "--chain") {
+        eprintln!("The chain spec is given both as CHAIN_SPEC and as --chain for omni-node, pass only one");
+        process::exit(1);
+    }
+    let chain_spec = chain_spec.map(String::as_str).unwrap_or(serve::DEFAULT_CHAIN_SPEC);
+    let merged = serve::merge_node_args(&[("--chain", chain_spec)], &node_args);
+    let args: Vec<&str> = merged.iter().map(String::as_str).collect();
+    let options = options(&args);
+
+    if detach {
+        if let Err(e) = serve::run_detached(&args, &options) {
+            eprintln!("Failed to start omni-node in the background: {}", e);
+            process::exit(1);
+        }
+        process::exit(0);
     }
-    println!("args: {:?}", args);
 
-    serve::run(&args);
+    if let Err(e) = serve::run(&args, &options) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
     process::exit(0);
-}
\ No newline at end of file
+}
+
+fn handle_stop(matches: &clap::ArgMatches) {
+    let run_dir = Path::new(daemon::RUN_DIR);
+    let timeout = std::time::Duration::from_secs(*matches.get_one::<u64>("timeout").expect("timeout has a default"));
+
+    let names: Vec<String> = if matches.get_flag("all") {
+        match daemon::list_nodes(run_dir) {
+            Ok(nodes) => nodes.into_iter().map(|node| node.name).collect(),
+            Err(e) => {
+                eprintln!("Failed to list nodes: {}", e);
+                process::exit(1);
+            }
+        }
+    } else {
+        vec![matches.get_one::<String>("name").expect("name is required").clone()]
+    };
+
+    let mut failed = false;
+    for name in names {
+        match daemon::stop_node(run_dir, &name, timeout) {
+            Ok(()) => println!("$ Stopped {} ✓", name),
+            Err(e) => {
+                eprintln!("$ Failed to stop {} ✗: {}", name, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn handle_ps() {
+    let nodes = match daemon::list_nodes(Path::new(daemon::RUN_DIR)) {
+        Ok(nodes) => nodes,
+        Err(e) => {
+            eprintln!("Failed to list nodes: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if nodes.is_empty() {
+        println!("No nodes are managed by dot");
+        return;
+    }
+
+    println!("{:<20} {:<10} {:<12} {:<10} LAST EXIT", "NAME", "PID", "STATUS", "RESTARTS");
+    for node in nodes {
+        let status = match (node.alive, node.state.restarting) {
+            (false, _) => "exited",
+            (true, true) => "restarting",
+            (true, false) => "running",
+        };
+        let last_exit = node.state.last_exit.as_deref().unwrap_or("-");
+        println!("{:<20} {:<10} {:<12} {:<10} {}", node.name, node.pid, status, node.state.restarts, last_exit);
+    }
+}
+
+fn handle_logs(matches: &clap::ArgMatches) {
+    let name = matches.get_one::<String>("name").expect("name is required");
+    let path = logs::log_path(Path::new(logs::LOG_DIR), name);
+    let lines = *matches.get_one::<usize>("lines").expect("lines has a default");
+
+    let since = match matches.get_one::<String>("since").map(|since| logs::parse_since(since, logs::now())) {
+        Some(Ok(since)) => Some(since),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        None => None,
+    };
+    // With --since every matching line is shown unless --lines is given explicitly
+    let lines = match (since, matches.value_source("lines")) {
+        (Some(_), Some(clap::parser::ValueSource::DefaultValue)) => None,
+        _ => Some(lines),
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    match logs::read_lines(&path, lines, since) {
+        Ok(lines) => {
+            for line in lines {
+                // A closed pipe (e.g. `dot logs node | head`) just ends the output
+                if writeln!(stdout, "{}", line).is_err() {
+                    return;
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+
+    if matches.get_flag("follow") {
+        let offset = std::fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+        match logs::follow(&path, offset, &mut stdout) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => {
+                eprintln!("Failed to follow {:?}: {}", path, e);
+                process::exit(1);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn handle_supervise(matches: &clap::ArgMatches) {
+    let name = matches.get_one::<String>("name").expect("name is required");
+    let command: Vec<&str> = matches.get_many::<String>("command").expect("command is required").map(String::as_str).collect();
+    process::exit(supervisor::supervise(name, command[0], &command[1..], grace_period(matches), &restart_policy(matches)));
+}
+
+fn restart_policy(matches: &clap::ArgMatches) -> supervisor::RestartPolicy {
+    supervisor::RestartPolicy {
+        on_failure: matches.get_one::<String>("restart").map(String::as_str) == Some("on-failure"),
+        max_restarts: *matches.get_one::<u32>("max_restarts").expect("max restarts has a default"),
+        backoff: std::time::Duration::from_secs(*matches.get_one::<u64>("backoff").expect("backoff has a default")),
+    }
+}
+
+fn node_options(matches: &clap::ArgMatches, node_args: &[&str]) -> node_options::NodeOptions {
+    // An explicit --name also names the node itself, unless omni-node gets its own --name after `--`
+    let explicit_name = matches.value_source("name") == Some(clap::parser::ValueSource::CommandLine);
+    node_options::NodeOptions {
+        dev: matches.get_flag("dev"),
+        name: matches.get_one::<String>("name").filter(|_| explicit_name && !serve::has_flag(node_args, "--name")).cloned(),
+        base_path: matches.get_one::<String>("base_path").map(std::path::PathBuf::from),
+        tmp: matches.get_flag("tmp"),
+        port: matches.get_one::<u16>("port").copied(),
+        rpc_port: matches.get_one::<u16>("rpc_port").copied(),
+        prometheus_port: matches.get_one::<u16>("prometheus_port").copied(),
+        rpc_cors: matches.get_one::<String>("rpc_cors").cloned(),
+        log: matches.get_one::<String>("log").cloned(),
+        dev_block_time: matches.get_one::<u64>("dev_block_time").copied(),
+        instant_seal: matches.get_flag("instant_seal"),
+    }
+}
+
+fn grace_period(matches: &clap::ArgMatches) -> std::time::Duration {
+    std::time::Duration::from_secs(*matches.get_one::<u64>("grace_period").expect("grace period has a default"))
+}
+
+fn handle_chain_spec(matches: &clap::ArgMatches) {
+    match matches.subcommand() {
+        Some(("generate", sub_matches)) => handle_chain_spec_generate(sub_matches),
+        Some(("list", _)) => handle_chain_spec_list(),
+        Some(("raw", sub_matches)) => handle_chain_spec_raw(sub_matches),
+        Some(("validate", sub_matches)) => handle_chain_spec_validate(sub_matches),
+        Some(("diff", sub_matches)) => handle_chain_spec_diff(sub_matches),
+        _ => {
+            eprintln!("No valid chain-spec subcommand provided. Use --help for more information.");
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_chain_spec_generate(matches: &clap::ArgMatches) {
+    let destination = match matches.get_one::<String>("output") {
+        Some(output) => std::path::PathBuf::from(output),
+        None => chain_specs::chain_spec_path(matches.get_one::<String>("name").expect("name has a default")),
+    };
+    let wasm = matches.get_one::<String>("wasm").map(Path::new);
+    let runtime = match runtime_catalog::find_runtime(matches.get_one::<String>("runtime").expect("runtime has a default")) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    if let Err(e) = chain_specs::gen_chain_spec(wasm, None, Some(&destination), Some(runtime)) {
+        eprintln!("$ Chain spec generation failed ✗: {}", e);
+        process::exit(1);
+    }
+}
+
+fn handle_chain_spec_list() {
+    match chain_specs::list_chain_specs(Path::new(chain_specs::CHAIN_SPECS_DIR)) {
+        Ok(specs) if specs.is_empty() => println!("No chain specs found in {}", chain_specs::CHAIN_SPECS_DIR),
+        Ok(specs) => {
+            for spec in specs {
+                println!("{}", spec.display());
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to list chain specs: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_chain_spec_raw(matches: &clap::ArgMatches) {
+    let input = Path::new(matches.get_one::<String>("input").expect("input is required"));
+    let output = Path::new(matches.get_one::<String>("output").expect("output is required"));
+
+    match chain_specs::convert_to_raw(input, output, None, None) {
+        Ok(keys) => println!("$ Raw chain spec written to {:?} ({} storage keys) ✓", output, keys),
+        Err(e) => {
+            eprintln!("$ Raw chain spec conversion failed ✗: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_chain_spec_validate(matches: &clap::ArgMatches) {
+    let path = Path::new(matches.get_one::<String>("path").expect("path has a default"));
+
+    let expected_code_hash = match expected_code_hash(matches) {
+        Ok(hash) => hash,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    match chain_spec_validate::validate_chain_spec(path, expected_code_hash) {
+        Ok(issues) if issues.is_empty() => println!("$ Chain spec {:?} is valid ✓", path),
+        Ok(issues) => {
+            eprintln!("$ Chain spec {:?} has {} issue(s) ✗", path, issues.len());
+            for issue in issues {
+                eprintln!("  - {}", issue);
+            }
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("$ Chain spec validation failed ✗: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_chain_spec_diff(matches: &clap::ArgMatches) {
+    let old = Path::new(matches.get_one::<String>("old").expect("A is required"));
+    let new = Path::new(matches.get_one::<String>("new").expect("B is required"));
+
+    let changes = match chain_spec_diff::diff_chain_specs(old, new) {
+        Ok(changes) => changes,
+        Err(e) => {
+            eprintln!("$ Chain spec diff failed ✗: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if changes.is_empty() {
+        println!("No differences between {:?} and {:?}", old, new);
+        return;
+    }
+
+    let mut section = "";
+    for change in &changes {
+        if change.section() != section {
+            section = change.section();
+            println!("\n{}", section);
+        }
+        println!("  {}", change);
+    }
+}
+
+fn expected_code_hash(matches: &clap::ArgMatches) -> Result<Option<[u8; 32]>, Box<dyn Error>> {
+    if let Some(wasm) = matches.get_one::<String>("wasm") {
+        let code = std::fs::read(wasm).map_err(|e| format!("Failed to read wasm {}: {}", wasm, e))?;
+        return Ok(Some(hashing::blake2_256(&code)));
+    }
+    if let Some(hash) = matches.get_one::<String>("code_hash") {
+        let bytes = hashing::from_hex(hash)?;
+        let hash: [u8; 32] = bytes.try_into().map_err(|_| "Code hash must be 32 bytes")?;
+        return Ok(Some(hash));
+    }
+    Ok(None)
+}
+
+fn handle_network(matches: &clap::ArgMatches) {
+    match matches.subcommand() {
+        Some(("up", sub_matches)) => handle_network_up(sub_matches),
+        Some(("down", sub_matches)) => handle_network_down(sub_matches),
+        _ => {
+            eprintln!("No valid network subcommand provided. Use --help for more information.");
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_network_up(matches: &clap::ArgMatches) {
+    let path = Path::new(matches.get_one::<String>("topology").expect("TOPOLOGY is required"));
+    let result = topology::load(path).and_then(|topology| {
+        println!("Starting network {}...", topology.name);
+        Ok((network::up(&topology)?, topology.name))
+    });
+    let (nodes, name) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Failed to start the network: {}", e);
+            process::exit(1);
+        }
+    };
+
+    println!("{:<28} {:<20} {:<24} BOOTNODE", "NAME", "ROLE", "RPC");
+    for node in &nodes {
+        println!("{:<28} {:<20} {:<24} {}", node.name, node.role, node.ports.rpc_endpoint(), node.bootnode);
+    }
+    println!("Follow a node with `dot logs <NAME> --follow`, stop the network with `dot network down {}`.", name);
+}
+
+fn handle_network_down(matches: &clap::ArgMatches) {
+    let name = matches.get_one::<String>("name").expect("NAME is required");
+    match network::down(name) {
+        Ok(nodes) => println!("Network {} is down, stopped {} node(s).", name, nodes.len()),
+        Err(e) => {
+            eprintln!("Failed to stop network {}: {}", name, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_db(matches: &clap::ArgMatches) {
+    match matches.subcommand() {
+        Some(("purge", sub_matches)) => handle_db_purge(sub_matches),
+        Some(("snapshot", sub_matches)) => handle_db_snapshot(sub_matches),
+        Some(("restore", sub_matches)) => handle_db_restore(sub_matches),
+        _ => {
+            eprintln!("No valid db subcommand provided. Use --help for more information.");
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_db_purge(matches: &clap::ArgMatches) {
+    let node = matches.get_one::<String>("node").expect("NODE is required");
+    let data_dir = Path::new(db::DATA_DIR);
+    match db::purge(data_dir, Path::new(daemon::RUN_DIR), node) {
+        Ok(true) => println!("Removed the database of {} at {:?}", node, db::base_path(data_dir, node)),
+        Ok(false) => println!("{} has no database at {:?}", node, db::base_path(data_dir, node)),
+        Err(e) => {
+            eprintln!("Failed to purge {}: {}", node, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_db_snapshot(matches: &clap::ArgMatches) {
+    let node = matches.get_one::<String>("node").expect("NODE is required");
+    let snapshot = matches.get_one::<String>("snapshot").expect("SNAPSHOT is required");
+    match db::snapshot(Path::new(db::DATA_DIR), Path::new(db::SNAPSHOT_DIR), Path::new(daemon::RUN_DIR), node, snapshot) {
+        Ok(path) => println!("Saved the database of {} to {:?}", node, path),
+        Err(e) => {
+            eprintln!("Failed to snapshot {}: {}", node, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_db_restore(matches: &clap::ArgMatches) {
+    let node = matches.get_one::<String>("node").expect("NODE is required");
+    let snapshot = matches.get_one::<String>("snapshot").expect("SNAPSHOT is required");
+    match db::restore(Path::new(db::DATA_DIR), Path::new(db::SNAPSHOT_DIR), Path::new(daemon::RUN_DIR), node, snapshot) {
+        Ok(()) => println!("Restored the database of {} from snapshot {}", node, snapshot),
+        Err(e) => {
+            eprintln!("Failed to restore {}: {}", node, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_metrics(matches: &clap::ArgMatches) {
+    let node = matches.get_one::<String>("node").expect("NODE is required");
+    let endpoint = match metrics::node_endpoint(Path::new(daemon::RUN_DIR), node) {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    if !matches.get_flag("watch") {
+        match metrics::scrape(&endpoint) {
+            Ok(samples) => metrics::Summary::from_samples(&samples).print(),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // A failed scrape (e.g. while the node restarts) is reported and the next refresh tries again
+    let interval = std::time::Duration::from_secs(*matches.get_one::<u64>("interval").expect("interval has a default"));
+    let mut previous: Option<metrics::Summary> = None;
+    metrics::print_watch_header();
+    loop {
+        let time = chrono::Local::now().format("%H:%M:%S").to_string();
+        match metrics::scrape(&endpoint) {
+            Ok(samples) => {
+                let summary = metrics::Summary::from_samples(&samples);
+                metrics::print_watch_row(&time, &summary, previous.as_ref());
+                previous = Some(summary);
+            }
+            Err(e) => eprintln!("{:<10} {}", time, e),
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+fn handle_rpc(matches: &clap::ArgMatches) {
+    let endpoint = match rpc_endpoint(matches) {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    if matches.get_flag("list") {
+        match rpc::methods(&endpoint) {
+            Ok(methods) => methods.iter().for_each(|method| println!("{}", method)),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let method = matches.get_one::<String>("method").expect("METHOD is required without --list");
+    let params: Vec<&String> = matches.get_many::<String>("params").unwrap_or_default().collect();
+    let params = rpc::parse_params(&params);
+    let result = match rpc::unsubscribe_method(method) {
+        Some(_) => rpc_subscribe(&endpoint, method, params, matches.get_one::<u64>("count").copied()),
+        None => rpc::request(&endpoint, method, params).map(|result| print_rpc_value(&mut std::io::stdout(), &result).unwrap_or(())),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        // Unknown methods are usually typos, so point at the ones of the same module
+        if e.to_string().contains("RPC error -32601") {
+            let module = method.split('_').next().unwrap_or(method);
+            let similar: Vec<String> = rpc::methods(&endpoint).unwrap_or_default().into_iter().filter(|known| known.split('_').next() == Some(module)).collect();
+            if !similar.is_empty() {
+                eprintln!("Known {} methods: {}", module, similar.join(", "));
+            }
+        }
+        process::exit(1);
+    }
+}
+
+fn handle_watch(matches: &clap::ArgMatches) {
+    let result = rpc_endpoint(matches).and_then(|endpoint| watch::watch(&endpoint, matches.get_one::<u64>("count").copied(), &mut std::io::stdout().lock()));
+    match result {
+        // A closed pipe (e.g. `dot watch | head`) just ends the output
+        Err(e) if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) => {}
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        Ok(()) => {}
+    }
+}
+
+fn handle_metadata(matches: &clap::ArgMatches) {
+    if let Err(e) = export_metadata(matches) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn export_metadata(matches: &clap::ArgMatches) -> Result<(), Box<dyn Error>> {
+    let format = matches.get_one::<String>("format").map(String::as_str).unwrap_or("text");
+    let pallet = matches.get_one::<String>("pallet");
+    if pallet.is_some() && format != "text" {
+        return Err("--pallet only applies to --format text".into());
+    }
+
+    let (bytes, metadata) = match matches.get_one::<String>("wasm") {
+        Some(wasm) => {
+            let code = std::fs::read(wasm).map_err(|e| format!("Failed to read {}: {}", wasm, e))?;
+            metadata::from_wasm(&code)?
+        }
+        None => metadata::fetch(&rpc_endpoint(matches)?, None)?,
+    };
+
+    // Raw bytes go to a file or a pipe, on a terminal they are shown as hex
+    let raw = format == "scale" && (matches.contains_id("output") || !std::io::stdout().is_terminal());
+    let output = match format {
+        "json" => serde_json::to_string_pretty(&metadata::to_json(&bytes)?)?.into_bytes(),
+        "scale" if raw => bytes,
+        "scale" => hashing::to_hex(&bytes).into_bytes(),
+        _ => metadata::describe(&metadata, pallet.map(String::as_str))?.into_bytes(),
+    };
+    match matches.get_one::<String>("output") {
+        Some(path) => {
+            std::fs::write(path, &output).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            println!("Wrote metadata V{} to {}", metadata.version, path);
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&output)?;
+            if !raw && !output.ends_with(b"\n") {
+                writeln!(stdout)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_storage(matches: &clap::ArgMatches) {
+    let pallet = matches.get_one::<String>("pallet").expect("PALLET is required");
+    let item = matches.get_one::<String>("item").expect("ITEM is required");
+    let keys: Vec<&String> = matches.get_many::<String>("keys").map(|keys| keys.collect()).unwrap_or_default();
+    let keys = rpc::parse_params(&keys);
+    let keys = keys.as_array().expect("parameters are a list");
+
+    match rpc_endpoint(matches).and_then(|endpoint| storage::query(&endpoint, pallet, item, keys)) {
+        Ok(value) => print_rpc_value(&mut std::io::stdout(), &value).unwrap_or(()),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_tx(matches: &clap::ArgMatches) {
+    let pallet = matches.get_one::<String>("pallet").expect("PALLET is required");
+    let call = matches.get_one::<String>("call").expect("CALL is required");
+    let args: Vec<&String> = matches.get_many::<String>("args").map(|args| args.collect()).unwrap_or_default();
+    let args = rpc::parse_params(&args);
+    let args = args.as_array().expect("parameters are a list");
+    let wait = match matches.get_one::<String>("wait").map(String::as_str) {
+        Some("in-block") => tx::Wait::InBlock,
+        _ => tx::Wait::Finalized,
+    };
+
+    let result = keyring::Signer::from_suri(matches.get_one::<String>("signer").expect("signer has a default"))
+        .map_err(|e| format!("Invalid --signer: {}", e).into())
+        .and_then(|signer| {
+            let endpoint = rpc_endpoint(matches)?;
+            tx::send(&endpoint, pallet, call, args, &signer, wait, &mut std::io::stdout()).map(|_| ())
+        });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+// An explicit --url or --node, otherwise the node of the last `dot serve`, otherwise the default port
+fn rpc_endpoint(matches: &clap::ArgMatches) -> Result<String, Box<dyn Error>> {
+    let run_dir = Path::new(daemon::RUN_DIR);
+    if let Some(url) = matches.get_one::<String>("url") {
+        if !url.starts_with("http://") && !rpc::is_ws(url) {
+            return Err(format!("Unsupported endpoint {}, use an http:// or ws:// URL", url).into());
+        }
+        return Ok(url.clone());
+    }
+    if let Some(node) = matches.get_one::<String>("node") {
+        return Ok(ports::read_ports(run_dir, node)?.rpc_endpoint());
+    }
+    Ok(match ports::last_served(run_dir) {
+        Some((_, ports)) => ports.rpc_endpoint(),
+        None => rpc::local_endpoint(rpc::DEFAULT_RPC_PORT),
+    })
+}
+
+fn rpc_subscribe(endpoint: &str, method: &str, params: serde_json::Value, count: Option<u64>) -> Result<(), Box<dyn Error>> {
+    let mut client = rpc::WsClient::connect(&rpc::ws_endpoint(endpoint))?;
+    let mut stdout = std::io::stdout().lock();
+    let mut received = 0;
+    client.subscribe(method, params, |item| {
+        received += 1;
+        // A closed pipe (e.g. `dot rpc chain_subscribeNewHeads | head`) ends the subscription
+        print_rpc_value(&mut stdout, &item).is_ok() && count.is_none_or(|count| received < count)
+    })
+}
+
+// Strings are printed as is so that hashes and hex values can be piped on, everything else as JSON
+fn print_rpc_value<W: Write>(out: &mut W, value: &serde_json::Value) -> std::io::Result<()> {
+    match value {
+        serde_json::Value::String(value) => writeln!(out, "{}", value),
+        value => writeln!(out, "{}", serde_json::to_string_pretty(value).unwrap_or_default()),
+    }
+}
+
+fn handle_runtime(matches: &clap::ArgMatches) {
+    match matches.subcommand() {
+        Some(("info", sub_matches)) => handle_runtime_info(sub_matches),
+        Some(("upgrade", sub_matches)) => handle_runtime_upgrade(sub_matches),
+        _ => {
+            eprintln!("No valid runtime subcommand provided. Use --help for more information.");
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_runtime_upgrade(matches: &clap::ArgMatches) {
+    let wasm = Path::new(matches.get_one::<String>("wasm").expect("--wasm is required"));
+    let authorize = matches.get_flag("authorize");
+    let timeout = std::time::Duration::from_secs(*matches.get_one::<u64>("timeout").expect("timeout has a default"));
+
+    let result = keyring::Signer::from_suri(matches.get_one::<String>("signer").expect("signer has a default"))
+        .map_err(|e| format!("Invalid --signer: {}", e).into())
+        .and_then(|signer| {
+            let code = std::fs::read(wasm).map_err(|e| format!("Failed to read runtime {:?}: {}", wasm, e))?;
+            let endpoint = rpc_endpoint(matches)?;
+            upgrade::upgrade(&endpoint, &code, &signer, authorize, timeout, &mut std::io::stdout()).map(|_| ())
+        });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn handle_runtime_info(matches: &clap::ArgMatches) {
+    let wasm = Path::new(matches.get_one::<String>("wasm").expect("WASM is required"));
+
+    let info = match runtime::inspect_wasm(wasm) {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("Failed to inspect runtime {:?}: {}", wasm, e);
+            process::exit(1);
+        }
+    };
+
+    match matches.get_one::<String>("format").map(String::as_str) {
+        Some("json") => println!("{}", serde_json::to_string_pretty(&info.to_json()).expect("runtime info serializes")),
+        _ => println!("{}", info.to_table()),
+    }
+}
@@ -0,0 +1,411 @@
+use std::error::Error;
+use frame_metadata::v14::{PalletConstantMetadata, PalletStorageMetadata, StorageEntryMetadata, StorageEntryType, StorageHasher};
+use frame_metadata::v15;
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed, META_RESERVED};
+use parity_scale_codec::{Decode, Encode};
+use scale_info::form::PortableForm;
+use scale_info::{Field, PortableRegistry, TypeDef};
+use serde_json::{json, Value};
+use crate::executor;
+use crate::hashing::{from_hex, to_hex, twox_128};
+use crate::rpc;
+use crate::scale;
+
+pub type PalletStorage = PalletStorageMetadata<PortableForm>;
+pub type StorageEntry = StorageEntryMetadata<PortableForm>;
+
+// The V14 and V15 metadata, reduced to what dot works with
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    pub version: u32,
+    pub types: PortableRegistry,
+    pub pallets: Vec<Pallet>,
+    pub extrinsic: Extrinsic,
+}
+
+// How signed extrinsics are built. V14 only names the address and signature types as parameters
+// of the extrinsic type, so they may be missing
+#[derive(Debug, Clone)]
+pub struct Extrinsic {
+    pub version: u8,
+    pub address: Option<u32>,
+    pub signature: Option<u32>,
+    pub signed_extensions: Vec<SignedExtension>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignedExtension {
+    pub identifier: String,
+    // The data added to the extrinsic, and the data only added to the signed payload
+    pub extra: u32,
+    pub additional_signed: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pallet {
+    pub name: String,
+    pub index: u8,
+    pub storage: Option<PalletStorage>,
+    pub calls: Option<u32>,
+    pub event: Option<u32>,
+    pub error: Option<u32>,
+    pub constants: Vec<PalletConstantMetadata<PortableForm>>,
+    pub docs: Vec<String>,
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Metadata, Box<dyn Error>> {
+    let prefixed = RuntimeMetadataPrefixed::decode(&mut &bytes[..]).map_err(|e| format!("Invalid metadata: {}", e))?;
+    if prefixed.0 != META_RESERVED {
+        return Err("Invalid metadata: missing the `meta` magic number".into());
+    }
+
+    // V14 describes pallets like V15, only without their docs
+    let (version, types, pallets, extrinsic) = match prefixed.1 {
+        RuntimeMetadata::V14(metadata) => {
+            let parameter = |name: &str| {
+                let ty = metadata.types.resolve(metadata.extrinsic.ty.id)?;
+                ty.type_params.iter().find(|param| param.name == name)?.ty.map(|ty| ty.id)
+            };
+            let extrinsic = Extrinsic {
+                version: metadata.extrinsic.version,
+                address: parameter("Address"),
+                signature: parameter("Signature"),
+                signed_extensions: metadata.extrinsic.signed_extensions.iter().map(|extension| SignedExtension {
+                    identifier: extension.identifier.clone(),
+                    extra: extension.ty.id,
+                    additional_signed: extension.additional_signed.id,
+                }).collect(),
+            };
+            let pallets = metadata.pallets.into_iter().map(|pallet| v15::PalletMetadata {
+                name: pallet.name,
+                storage: pallet.storage,
+                calls: pallet.calls,
+                event: pallet.event,
+                constants: pallet.constants,
+                error: pallet.error,
+                index: pallet.index,
+                docs: Vec::new(),
+            });
+            (14, metadata.types, pallets.collect(), extrinsic)
+        }
+        RuntimeMetadata::V15(metadata) => {
+            let extrinsic = Extrinsic {
+                version: metadata.extrinsic.version,
+                address: Some(metadata.extrinsic.address_ty.id),
+                signature: Some(metadata.extrinsic.signature_ty.id),
+                signed_extensions: metadata.extrinsic.signed_extensions.iter().map(|extension| SignedExtension {
+                    identifier: extension.identifier.clone(),
+                    extra: extension.ty.id,
+                    additional_signed: extension.additional_signed.id,
+                }).collect(),
+            };
+            (15, metadata.types, metadata.pallets, extrinsic)
+        }
+        other => return Err(format!("Unsupported metadata version V{}, only V14 and V15 are supported", other.version()).into()),
+    };
+    let pallets = pallets
+        .into_iter()
+        .map(|pallet: v15::PalletMetadata<PortableForm>| Pallet {
+            name: pallet.name,
+            index: pallet.index,
+            storage: pallet.storage,
+            calls: pallet.calls.map(|calls| calls.ty.id),
+            event: pallet.event.map(|event| event.ty.id),
+            error: pallet.error.map(|error| error.ty.id),
+            constants: pallet.constants,
+            docs: pallet.docs,
+        })
+        .collect();
+    Ok(Metadata { version, types, pallets, extrinsic })
+}
+
+// The newest metadata version dot understands, which runtimes only return on request
+const LATEST_VERSION: u32 = 15;
+
+// Fetches the metadata of a running node, at a given block or the best one. V15 is asked for
+// first, `state_getMetadata` only returns V14
+pub fn fetch(endpoint: &str, at: Option<&str>) -> Result<(Vec<u8>, Metadata), Box<dyn Error>> {
+    let mut params = vec![json!("Metadata_metadata_at_version"), json!(to_hex(&LATEST_VERSION.encode()))];
+    params.extend(at.map(|at| json!(at)));
+    let latest = rpc::request(endpoint, "state_call", Value::Array(params)).ok()
+        .and_then(|result| from_hex(result.as_str()?).ok())
+        .and_then(|bytes| Option::<Vec<u8>>::decode(&mut &bytes[..]).ok().flatten());
+    if let Some(bytes) = latest {
+        let metadata = decode(&bytes)?;
+        return Ok((bytes, metadata));
+    }
+    let params = match at {
+        Some(at) => json!([at]),
+        None => json!([]),
+    };
+    from_rpc(&rpc::request(endpoint, "state_getMetadata", params)?)
+}
+
+// Executes the metadata runtime API of a wasm blob, asking for V15 first like `fetch`
+pub fn from_wasm(code: &[u8]) -> Result<(Vec<u8>, Metadata), Box<dyn Error>> {
+    let latest = executor::call(code, "Metadata_metadata_at_version", &LATEST_VERSION.encode()).ok()
+        .and_then(|output| Option::<Vec<u8>>::decode(&mut &output[..]).ok().flatten());
+    let bytes = match latest {
+        Some(bytes) => bytes,
+        None => {
+            let output = executor::call(code, "Metadata_metadata", &[])?;
+            Vec::<u8>::decode(&mut &output[..]).map_err(|e| format!("Invalid Metadata_metadata output: {}", e))?
+        }
+    };
+    let metadata = decode(&bytes)?;
+    Ok((bytes, metadata))
+}
+
+// The complete metadata as JSON, in the layout of frame-metadata
+pub fn to_json(bytes: &[u8]) -> Result<Value, Box<dyn Error>> {
+    let prefixed = RuntimeMetadataPrefixed::decode(&mut &bytes[..]).map_err(|e| format!("Invalid metadata: {}", e))?;
+    Ok(serde_json::to_value(&prefixed)?)
+}
+
+// Decodes a `state_getMetadata` result, returning the raw bytes along with the metadata
+pub fn from_rpc(result: &Value) -> Result<(Vec<u8>, Metadata), Box<dyn Error>> {
+    let bytes = from_hex(result.as_str().ok_or("state_getMetadata response is not a hex string")?)?;
+    let metadata = decode(&bytes)?;
+    Ok((bytes, metadata))
+}
+
+impl Metadata {
+    pub fn pallet(&self, name: &str) -> Result<&Pallet, Box<dyn Error>> {
+        self.pallets.iter().find(|pallet| pallet.name.eq_ignore_ascii_case(name)).ok_or_else(|| format!("The runtime has no pallet {}", name).into())
+    }
+
+    pub fn storage_entry(&self, pallet: &str, item: &str) -> Result<(&PalletStorage, &StorageEntry), Box<dyn Error>> {
+        let pallet = self.pallet(pallet)?;
+        let storage = pallet.storage.as_ref().ok_or_else(|| format!("Pallet {} has no storage", pallet.name))?;
+        let entry = storage.entries.iter().find(|entry| entry.name.eq_ignore_ascii_case(item))
+            .ok_or_else(|| format!("Pallet {} has no storage item {}", pallet.name, item))?;
+        Ok((storage, entry))
+    }
+}
+
+// A readable listing of the calls, storage items, events, errors and constants of every pallet
+pub fn describe(metadata: &Metadata, pallet: Option<&str>) -> Result<String, Box<dyn Error>> {
+    let pallets = match pallet {
+        Some(name) => vec![metadata.pallet(name)?],
+        None => metadata.pallets.iter().collect(),
+    };
+    let types = &metadata.types;
+    let mut out = format!("Metadata V{}, {} pallets\n", metadata.version, metadata.pallets.len());
+    for pallet in pallets {
+        out.push_str(&format!("\n{} (index {})\n", pallet.name, pallet.index));
+        if let Some(doc) = pallet.docs.iter().find(|doc| !doc.trim().is_empty()) {
+            out.push_str(&format!("  {}\n", doc.trim()));
+        }
+        let section = |out: &mut String, title: &str, lines: Vec<String>| {
+            if !lines.is_empty() {
+                out.push_str(&format!("  {}:\n", title));
+                lines.iter().for_each(|line| out.push_str(&format!("    {}\n", line)));
+            }
+        };
+        section(&mut out, "calls", pallet.calls.map(|ty| variants(types, ty)).unwrap_or_default());
+        let storage = pallet.storage.iter().flat_map(|storage| &storage.entries).map(|entry| match &entry.ty {
+            StorageEntryType::Plain(value) => format!("{}: {}", entry.name, scale::type_name(types, value.id)),
+            StorageEntryType::Map { hashers, key, value } => {
+                let keys: Vec<String> = hashers.iter().zip(key_types(types, hashers, key.id)).map(|(hasher, key)| format!("{:?}({})", hasher, scale::type_name(types, key))).collect();
+                format!("{}: {} -> {}", entry.name, keys.join(", "), scale::type_name(types, value.id))
+            }
+        });
+        section(&mut out, "storage", storage.collect());
+        section(&mut out, "events", pallet.event.map(|ty| variants(types, ty)).unwrap_or_default());
+        section(&mut out, "errors", pallet.error.map(|ty| variants(types, ty)).unwrap_or_default());
+        let constants = pallet.constants.iter().map(|constant| {
+            let value = scale::decode_value(types, constant.ty.id, &mut &constant.value[..]).map(|value| value.to_string()).unwrap_or_else(|_| to_hex(&constant.value));
+            format!("{}: {} = {}", constant.name, scale::type_name(types, constant.ty.id), value)
+        });
+        section(&mut out, "constants", constants.collect());
+    }
+    Ok(out)
+}
+
+// The variants of a call, event or error enum with their fields, e.g. `transfer(dest: MultiAddress, value: Compact<u128>)`
+fn variants(types: &PortableRegistry, type_id: u32) -> Vec<String> {
+    let Some(TypeDef::Variant(variant)) = types.resolve(type_id).map(|ty| &ty.type_def) else {
+        return Vec::new();
+    };
+    variant.variants.iter().map(|variant| {
+        let fields = fields(types, &variant.fields);
+        if fields.is_empty() { variant.name.clone() } else { format!("{}({})", variant.name, fields.join(", ")) }
+    }).collect()
+}
+
+// Fields as `name: Type`, or just `Type` when unnamed
+pub fn fields(types: &PortableRegistry, fields: &[Field<PortableForm>]) -> Vec<String> {
+    fields.iter().map(|field| match &field.name {
+        Some(name) => format!("{}: {}", name, scale::type_name(types, field.ty.id)),
+        None => scale::type_name(types, field.ty.id),
+    }).collect()
+}
+
+// The type of every key of a storage map. Maps with several hashers are keyed by a tuple, one element per hasher
+pub fn key_types(types: &PortableRegistry, hashers: &[StorageHasher], key: u32) -> Vec<u32> {
+    match types.resolve(key).map(|ty| &ty.type_def) {
+        Some(TypeDef::Tuple(tuple)) if hashers.len() > 1 => tuple.fields.iter().map(|field| field.id).collect(),
+        _ => vec![key],
+    }
+}
+
+// The key of a storage value, or the prefix shared by all keys of a storage map
+pub fn storage_prefix(pallet_prefix: &str, item: &str) -> Vec<u8> {
+    [twox_128(pallet_prefix.as_bytes()), twox_128(item.as_bytes())].concat()
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{test_metadata, MultiAddress, MultiSignature};
+    use frame_metadata::v14;
+    use mockito::{mock, Matcher};
+    use scale_info::{meta_type, TypeInfo};
+
+    // A stand-in runtime whose `function` returns `output`
+    fn runtime_returning(function: &str, output: &[u8]) -> Vec<u8> {
+        let data: String = output.iter().map(|byte| format!("\\{:02x}", byte)).collect();
+        wat::parse_str(format!(
+            r#"(module
+                (memory (export "memory") 4)
+                (global (export "__heap_base") i32 (i32.const 8))
+                (data (i32.const 1024) "{}")
+                (func (export "{}") (param i32 i32) (result i64)
+                    (i64.or (i64.const 1024) (i64.shl (i64.const {}) (i64.const 32)))))"#,
+            data, function, output.len()
+        )).unwrap()
+    }
+
+    #[test]
+    fn test_decode() {
+        let metadata = decode(&test_metadata()).unwrap();
+        assert_eq!(metadata.version, 15);
+        let names: Vec<(&str, u8)> = metadata.pallets.iter().map(|pallet| (pallet.name.as_str(), pallet.index)).collect();
+        assert_eq!(names, [("System", 0), ("Balances", 10)]);
+        assert_eq!(metadata.pallet("balances").unwrap().docs, ["Docs of the pallet"]);
+
+        let (storage, entry) = metadata.storage_entry("System", "events").unwrap();
+        assert_eq!((storage.prefix.as_str(), entry.name.as_str()), ("System", "Events"));
+        assert_eq!(metadata.storage_entry("Balances", "Locks").unwrap_err().to_string(), "Pallet Balances has no storage item Locks");
+        assert_eq!(metadata.pallet("Staking").unwrap_err().to_string(), "The runtime has no pallet Staking");
+
+        let extensions: Vec<&str> = metadata.extrinsic.signed_extensions.iter().map(|extension| extension.identifier.as_str()).collect();
+        assert_eq!(extensions.len(), 9);
+        assert_eq!(extensions[4], "CheckMortality");
+        assert_eq!(scale::type_name(&metadata.types, metadata.extrinsic.address.unwrap()), "MultiAddress");
+    }
+
+    #[test]
+    fn test_describe() {
+        let metadata = decode(&test_metadata()).unwrap();
+        let listing = describe(&metadata, Some("system")).unwrap();
+        assert_eq!(listing, "Metadata V15, 2 pallets
+
+System (index 0)
+  Docs of the pallet
+  calls:
+    remark(remark: Vec<u8>)
+    set_heap_pages(pages: u64)
+  storage:
+    Events: Vec<EventRecord>
+    Account: Blake2_128Concat(AccountId32) -> AccountData
+    Number: u32
+  events:
+    ExtrinsicSuccess(weight: u64)
+    ExtrinsicFailed(dispatch_error: DispatchError)
+    CodeUpdated
+  errors:
+    CallFiltered
+  constants:
+    BlockHashCount: u32 = 250
+");
+        assert!(describe(&metadata, None).unwrap().ends_with("
+Balances (index 10)
+  Docs of the pallet
+  calls:
+    transfer_keep_alive(dest: MultiAddress, value: Compact<u128>)
+  storage:
+    Reserves: Twox64Concat(u32), Identity(AccountId32) -> u128
+  events:
+    Transfer(amount: u128)
+  errors:
+    ExistentialDeposit
+    InsufficientBalance
+"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = to_json(&test_metadata()).unwrap();
+        assert_eq!(json[1]["V15"]["pallets"][0]["name"], "System");
+    }
+
+    #[test]
+    fn test_from_wasm() {
+        // Runtimes without the versioned API only return V14 through Metadata_metadata
+        let code = runtime_returning("Metadata_metadata", &test_metadata().encode());
+        assert_eq!(from_wasm(&code).unwrap().0, test_metadata());
+
+        let code = runtime_returning("Metadata_metadata_at_version", &Some(test_metadata()).encode());
+        assert_eq!(from_wasm(&code).unwrap().1.pallets.len(), 2);
+
+        let code = runtime_returning("Metadata_metadata", &[1, 2]);
+        assert!(from_wasm(&code).unwrap_err().to_string().starts_with("Invalid Metadata_metadata output"));
+    }
+
+    #[test]
+    fn test_fetch() {
+        let metadata = to_hex(&test_metadata());
+        let _latest = mock("POST", "/metadata-latest")
+            .match_body(Matcher::PartialJson(json!({ "method": "state_call", "params": ["Metadata_metadata_at_version", "0x0f000000", "0xabcd"] })))
+            .with_body(json!({ "jsonrpc": "2.0", "id": 1, "result": to_hex(&Some(test_metadata()).encode()) }).to_string())
+            .create();
+        let endpoint = format!("{}/metadata-latest", mockito::server_url());
+        assert_eq!(fetch(&endpoint, Some("0xabcd")).unwrap().0, test_metadata());
+
+        let _unsupported = mock("POST", "/metadata-v14")
+            .match_body(Matcher::PartialJson(json!({ "method": "state_call" })))
+            .with_body(r#"{"jsonrpc":"2.0","id":1,"error":{"code":4003,"message":"Client error: Execution failed"}}"#)
+            .create();
+        let _v14 = mock("POST", "/metadata-v14")
+            .match_body(Matcher::PartialJson(json!({ "method": "state_getMetadata", "params": [] })))
+            .with_body(json!({ "jsonrpc": "2.0", "id": 1, "result": metadata }).to_string())
+            .create();
+        let endpoint = format!("{}/metadata-v14", mockito::server_url());
+        assert_eq!(fetch(&endpoint, None).unwrap().1.version, 15);
+    }
+
+    #[derive(TypeInfo)]
+    struct UncheckedExtrinsic<Address, Call, Signature, Extra>(std::marker::PhantomData<(Address, Call, Signature, Extra)>);
+
+    #[test]
+    fn test_decode_v14() {
+        let pallets = vec![v14::PalletMetadata { name: "System", storage: None, calls: None, event: None, constants: vec![], error: None, index: 0 }];
+        let extrinsic = v14::ExtrinsicMetadata { ty: meta_type::<UncheckedExtrinsic<MultiAddress, (), MultiSignature, ()>>(), version: 4, signed_extensions: vec![] };
+        let bytes = RuntimeMetadataPrefixed::from(v14::RuntimeMetadataV14::new(pallets, extrinsic, meta_type::<()>())).encode();
+
+        let metadata = decode(&bytes).unwrap();
+        assert_eq!(metadata.version, 14);
+        assert_eq!(metadata.pallet("System").unwrap().docs, Vec::<String>::new());
+        assert_eq!(metadata.storage_entry("System", "Events").unwrap_err().to_string(), "Pallet System has no storage");
+        // V14 only names the address and signature types as parameters of the extrinsic type
+        assert_eq!(scale::type_name(&metadata.types, metadata.extrinsic.signature.unwrap()), "MultiSignature");
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert!(decode(b"meta").unwrap_err().to_string().starts_with("Invalid metadata"));
+        let mut bytes = test_metadata();
+        bytes[0] = b'x';
+        assert_eq!(decode(&bytes).unwrap_err().to_string(), "Invalid metadata: missing the `meta` magic number");
+    }
+
+    #[test]
+    fn test_storage_prefix() {
+        assert_eq!(crate::hashing::to_hex(&storage_prefix("System", "Events")), "0x26aa394eea5630e07c48ae0c9558cef780d41e5e16056765bc8461851072c9d7");
+    }
+}
@@ -0,0 +1,274 @@
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+use crate::ports;
+
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+// omni-node registers its metrics as substrate_*, the polkadot binary as polkadot_*
+const METRIC_PREFIXES: [&str; 2] = ["substrate_", "polkadot_"];
+
+type Labels = Vec<(String, String)>;
+
+pub fn metrics_endpoint(port: u16) -> String {
+    format!("http://127.0.0.1:{}/metrics", port)
+}
+
+// The metrics endpoint of a served node or a node of a network, from its recorded ports
+pub fn node_endpoint(run_dir: &Path, name: &str) -> Result<String, Box<dyn Error>> {
+    match ports::read_ports(run_dir, name)?.prometheus {
+        Some(port) => Ok(metrics_endpoint(port)),
+        None => Err(format!("{} runs with --no-prometheus, so it has no metrics endpoint", name).into()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub name: String,
+    pub labels: Labels,
+    pub value: f64,
+}
+
+impl Sample {
+    fn label(&self, name: &str) -> Option<&str> {
+        self.labels.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+// Parses the Prometheus text exposition format, skipping comments and blank lines
+pub fn parse(text: &str) -> Result<Vec<Sample>, Box<dyn Error>> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| parse_sample(line).map_err(|e| format!("Invalid metric on line {}: {}", i + 1, e).into()))
+        .collect()
+}
+
+fn parse_sample(line: &str) -> Result<Sample, String> {
+    let name_end = line.find(|c: char| c == '{' || c.is_whitespace()).ok_or_else(|| format!("no value in {:?}", line))?;
+    let name = &line[..name_end];
+    if name.is_empty() {
+        return Err(format!("no metric name in {:?}", line));
+    }
+
+    let mut rest = &line[name_end..];
+    let mut labels = Vec::new();
+    if let Some(after_brace) = rest.strip_prefix('{') {
+        let (parsed, after_labels) = parse_labels(after_brace)?;
+        labels = parsed;
+        rest = after_labels;
+    }
+
+    // The value may be followed by a timestamp, which is ignored
+    let value = rest.split_whitespace().next().ok_or_else(|| format!("no value for {}", name))?;
+    let value = value.parse().map_err(|_| format!("invalid value {:?} for {}", value, name))?;
+    Ok(Sample { name: name.to_string(), labels, value })
+}
+
+// Reads `key="value",...}` and returns the labels with the text after the closing brace
+fn parse_labels(mut rest: &str) -> Result<(Labels, &str), String> {
+    let mut labels = Vec::new();
+    loop {
+        rest = rest.trim_start_matches([' ', ',']);
+        if let Some(after) = rest.strip_prefix('}') {
+            return Ok((labels, after));
+        }
+        let (key, after_key) = rest.split_once('=').ok_or("unterminated labels")?;
+        let mut chars = after_key.strip_prefix('"').ok_or_else(|| format!("label {} has no quoted value", key.trim()))?.char_indices();
+        let mut value = String::new();
+        let end = loop {
+            match chars.next() {
+                Some((i, '"')) => break i,
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, c)) => value.push(c),
+                    None => return Err("unterminated label value".to_string()),
+                },
+                Some((_, c)) => value.push(c),
+                None => return Err("unterminated label value".to_string()),
+            }
+        };
+        labels.push((key.trim().to_string(), value));
+        rest = &after_key[end + 2..];
+    }
+}
+
+pub fn scrape(endpoint: &str) -> Result<Vec<Sample>, Box<dyn Error>> {
+    let body = ureq::get(endpoint)
+        .timeout(SCRAPE_TIMEOUT)
+        .call()
+        .map_err(|e| format!("Failed to scrape {}: {}", endpoint, e))?
+        .into_string()?;
+    parse(&body)
+}
+
+// The first sample of a metric whose labels include all of the given ones
+fn find<'a>(samples: &'a [Sample], names: &[&str], labels: &[(&str, &str)]) -> Option<&'a Sample> {
+    samples.iter().find(|sample| {
+        let name = METRIC_PREFIXES.iter().find_map(|prefix| sample.name.strip_prefix(prefix));
+        name.is_some_and(|name| names.contains(&name)) && labels.iter().all(|(key, value)| sample.label(key) == Some(*value))
+    })
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    pub best_block: Option<u64>,
+    pub finalized_block: Option<u64>,
+    pub peers: Option<u64>,
+    pub ready_transactions: Option<u64>,
+    // Totals of the block import histogram, in seconds
+    pub import_time_sum: Option<f64>,
+    pub import_count: Option<u64>,
+}
+
+impl Summary {
+    pub fn from_samples(samples: &[Sample]) -> Summary {
+        let value = |names: &[&str], labels: &[(&str, &str)]| find(samples, names, labels).map(|sample| sample.value);
+        let count = |names: &[&str], labels: &[(&str, &str)]| value(names, labels).map(|value| value as u64);
+        Summary {
+            best_block: count(&["block_height"], &[("status", "best")]),
+            finalized_block: count(&["block_height"], &[("status", "finalized")]),
+            peers: count(&["sync_peers", "sub_libp2p_peers_count"], &[]),
+            ready_transactions: count(&["ready_transactions_number"], &[]),
+            import_time_sum: value(&["block_verification_and_import_time_sum"], &[]),
+            import_count: count(&["block_verification_and_import_time_count"], &[]),
+        }
+    }
+
+    // Average import time of the blocks imported since an earlier summary, or since the node started
+    pub fn average_import_time(&self, since: Option<&Summary>) -> Option<Duration> {
+        let (sum, count) = (self.import_time_sum?, self.import_count?);
+        let (sum, count) = match since {
+            Some(Summary { import_time_sum: Some(earlier_sum), import_count: Some(earlier_count), .. }) if *earlier_count <= count => {
+                (sum - earlier_sum, count - earlier_count)
+            }
+            _ => (sum, count),
+        };
+        if count == 0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64((sum / count as f64).max(0.0)))
+    }
+
+    pub fn print(&self) {
+        println!("Best block:       {}", display(self.best_block));
+        println!("Finalized block:  {}", display(self.finalized_block));
+        println!("Peers:            {}", display(self.peers));
+        println!("Ready txs:        {}", display(self.ready_transactions));
+        match (self.average_import_time(None), self.import_count) {
+            (Some(time), Some(count)) => println!("Import time:      {} (average over {} blocks)", display_duration(time), count),
+            _ => println!("Import time:      -"),
+        }
+    }
+}
+
+pub fn print_watch_header() {
+    println!("{:<10} {:<10} {:<10} {:<6} {:<10} IMPORT", "TIME", "BEST", "FINALIZED", "PEERS", "READY TXS");
+}
+
+pub fn print_watch_row(time: &str, summary: &Summary, previous: Option<&Summary>) {
+    // Between refreshes only the blocks imported in the meantime count
+    let import = summary.average_import_time(previous);
+    println!(
+        "{:<10} {:<10} {:<10} {:<6} {:<10} {}",
+        time,
+        display(summary.best_block),
+        display(summary.finalized_block),
+        display(summary.peers),
+        display(summary.ready_transactions),
+        import.map(display_duration).unwrap_or_else(|| "-".to_string())
+    );
+}
+
+fn display(value: Option<u64>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+fn display_duration(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::mock;
+
+    const METRICS: &str = r#"# HELP substrate_block_height Block height info of the chain
+# TYPE substrate_block_height gauge
+substrate_block_height{status="best",chain="asset-hub-westend-dev"} 42
+substrate_block_height{status="finalized",chain="asset-hub-westend-dev"} 40
+substrate_block_height{status="sync_target",chain="asset-hub-westend-dev"} 42
+substrate_sync_peers{chain="asset-hub-westend-dev"} 3
+substrate_ready_transactions_number{chain="asset-hub-westend-dev"} 5
+substrate_block_verification_and_import_time_bucket{chain="asset-hub-westend-dev",le="+Inf"} 40
+substrate_block_verification_and_import_time_sum{chain="asset-hub-westend-dev"} 0.5
+substrate_block_verification_and_import_time_count{chain="asset-hub-westend-dev"} 40
+"#;
+
+    #[test]
+    fn test_parse() {
+        let samples = parse(METRICS).unwrap();
+        assert_eq!(samples.len(), 8);
+        assert_eq!(samples[0], Sample {
+            name: "substrate_block_height".to_string(),
+            labels: vec![("status".to_string(), "best".to_string()), ("chain".to_string(), "asset-hub-westend-dev".to_string())],
+            value: 42.0,
+        });
+        assert_eq!(samples[5].value, 40.0);
+        assert_eq!(samples[5].label("le"), Some("+Inf"));
+    }
+
+    #[test]
+    fn test_parse_edge_cases() {
+        let samples = parse("up 1 1700000000000\nescaped{path=\"a\\\"b,c}\\\\\",kind=\"x\\ny\",} +Inf\nnan NaN\n").unwrap();
+        assert_eq!(samples[0], Sample { name: "up".to_string(), labels: vec![], value: 1.0 });
+        assert_eq!(samples[1].labels, vec![("path".to_string(), "a\"b,c}\\".to_string()), ("kind".to_string(), "x\ny".to_string())]);
+        assert_eq!(samples[1].value, f64::INFINITY);
+        assert!(samples[2].value.is_nan());
+
+        assert_eq!(parse("# comment\nbroken{a=\"1 2").unwrap_err().to_string(), "Invalid metric on line 2: unterminated label value");
+        assert_eq!(parse("height high").unwrap_err().to_string(), "Invalid metric on line 1: invalid value \"high\" for height");
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = Summary::from_samples(&parse(METRICS).unwrap());
+        assert_eq!(summary, Summary {
+            best_block: Some(42),
+            finalized_block: Some(40),
+            peers: Some(3),
+            ready_transactions: Some(5),
+            import_time_sum: Some(0.5),
+            import_count: Some(40),
+        });
+        assert_eq!(summary.average_import_time(None), Some(Duration::from_secs_f64(0.0125)));
+
+        let earlier = Summary { import_time_sum: Some(0.4), import_count: Some(38), ..Summary::default() };
+        assert_eq!(summary.average_import_time(Some(&earlier)), Some(Duration::from_secs_f64(0.05)));
+        assert_eq!(summary.average_import_time(Some(&summary)), None);
+
+        // The polkadot binary prefixes the same metrics with polkadot_
+        let relay = Summary::from_samples(&parse("polkadot_block_height{status=\"best\"} 7\npolkadot_sub_libp2p_peers_count 2").unwrap());
+        assert_eq!((relay.best_block, relay.peers, relay.finalized_block), (Some(7), Some(2), None));
+    }
+
+    #[test]
+    fn test_node_endpoint() {
+        let run_dir = tempfile::tempdir().unwrap();
+        ports::write_ports(run_dir.path(), "quiet", &ports::NodePorts { rpc: 9944, p2p: 30333, prometheus: None }).unwrap();
+        assert_eq!(node_endpoint(run_dir.path(), "quiet").unwrap_err().to_string(), "quiet runs with --no-prometheus, so it has no metrics endpoint");
+    }
+
+    #[test]
+    fn test_scrape() {
+        let _m = mock("GET", "/metrics").with_header("content-type", "text/plain; version=0.0.4").with_body(METRICS).create();
+        let samples = scrape(&format!("{}/metrics", mockito::server_url())).unwrap();
+        assert_eq!(Summary::from_samples(&samples).best_block, Some(42));
+
+        assert!(scrape("http://127.0.0.1:1/metrics").unwrap_err().to_string().starts_with("Failed to scrape http://127.0.0.1:1/metrics"));
+    }
+}
@@ -0,0 +1,490 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use ed25519_dalek::SigningKey;
+use serde_json::{json, Value};
+use crate::chain_specs;
+use crate::daemon;
+use crate::db;
+use crate::ports::{self, NodePorts, DEFAULT_P2P_PORT, DEFAULT_PROMETHEUS_PORT};
+use crate::rpc;
+use crate::serve;
+use crate::supervisor::RestartPolicy;
+use crate::topology::{NodeSpec, Topology, CHAIN_SPEC_BUILDER_PATH, DEV_ACCOUNTS};
+
+pub const NETWORK_DIR: &str = "./network";
+const NODE_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq)]
+pub struct NodePlan {
+    // Name used by `dot ps`, `dot logs` and `dot stop`, prefixed with the network name
+    pub name: String,
+    pub role: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub ports: NodePorts,
+    pub bootnode: String,
+}
+
+// Ports handed out to the nodes of a network before any of them listens
+struct PortPool {
+    taken: Vec<u16>,
+}
+
+impl PortPool {
+    fn next(&mut self, default: u16) -> Result<u16, String> {
+        let port = ports::find_free(default, &self.taken, ports::is_free).ok_or(format!("No free port found from {}", default))?;
+        self.taken.push(port);
+        Ok(port)
+    }
+}
+
+// A libp2p ed25519 node key, passed to the node with --node-key
+pub fn generate_node_key() -> Result<[u8; 32], Box<dyn Error>> {
+    let mut secret = [0u8; 32];
+    File::open("/dev/urandom")?.read_exact(&mut secret)?;
+    Ok(secret)
+}
+
+// base58 of the identity multihash of the protobuf encoded public key, i.e. 12D3KooW...
+pub fn peer_id(secret: &[u8; 32]) -> String {
+    let public = SigningKey::from_bytes(secret).verifying_key();
+    let mut encoded = vec![0x00, 36, 0x08, 0x01, 0x12, 32];
+    encoded.extend_from_slice(public.as_bytes());
+    bs58::encode(encoded).into_string()
+}
+
+pub fn bootnode(port: u16, peer_id: &str) -> String {
+    format!("/ip4/127.0.0.1/tcp/{}/p2p/{}", port, peer_id)
+}
+
+// Works out every node's command line. Validators boot from the first validator and collators
+// from the first collator of their parachain, their embedded relay chain nodes from the validator
+pub fn plan<K: FnMut() -> Result<[u8; 32], Box<dyn Error>>>(topology: &Topology, data_dir: &Path, relay_spec: &str, para_specs: &[String], mut node_key: K) -> Result<Vec<NodePlan>, Box<dyn Error>> {
+    // Ports set in the topology are reserved before any other port is handed out
+    let all_nodes = topology.relaychain.validators.iter().chain(topology.parachains.iter().flat_map(|parachain| &parachain.collators));
+    let mut pool = PortPool { taken: all_nodes.flat_map(|node| [node.rpc_port, node.port, node.prometheus_port]).flatten().collect() };
+    let mut nodes: Vec<NodePlan> = Vec::new();
+
+    let mut relay_bootnode = None;
+    for validator in &topology.relaychain.validators {
+        let name = topology.node_name(None, &validator.name);
+        let mut args = vec!["--chain".to_string(), relay_spec.to_string(), "--validator".to_string()];
+        let (ports, bootnode) = node_args(&mut args, &name, validator, data_dir, relay_bootnode.as_deref(), &mut pool, &mut node_key)?;
+        args.extend(validator.args.iter().cloned());
+        relay_bootnode.get_or_insert(bootnode.clone());
+        let role = "validator".to_string();
+        nodes.push(NodePlan { name, role, program: topology.relaychain.binary.clone(), args, env: validator.env.clone(), ports, bootnode });
+    }
+    let relay_bootnode = relay_bootnode.ok_or("The relay chain needs at least one validator")?;
+
+    for (parachain, chain) in topology.parachains.iter().zip(para_specs) {
+        let mut para_bootnode: Option<String> = None;
+        for collator in &parachain.collators {
+            let name = topology.node_name(Some(parachain.id), &collator.name);
+            let mut args = vec!["--chain".to_string(), chain.clone(), "--collator".to_string()];
+            let (ports, bootnode) = node_args(&mut args, &name, collator, data_dir, para_bootnode.as_deref(), &mut pool, &mut node_key)?;
+            para_bootnode.get_or_insert(bootnode.clone());
+
+            // The embedded relay chain node needs ports of its own
+            let (own_args, relay_args) = serve::split_relay_args(&collator.args);
+            args.extend(own_args.iter().cloned());
+            args.extend([
+                "--".to_string(),
+                "--chain".to_string(), relay_spec.to_string(),
+                "--port".to_string(), pool.next(DEFAULT_P2P_PORT)?.to_string(),
+                "--rpc-port".to_string(), pool.next(rpc::DEFAULT_RPC_PORT)?.to_string(),
+                "--prometheus-port".to_string(), pool.next(DEFAULT_PROMETHEUS_PORT)?.to_string(),
+                "--bootnodes".to_string(), relay_bootnode.clone(),
+            ]);
+            args.extend(relay_args.unwrap_or_default().iter().cloned());
+            let role = format!("collator of {}", parachain.id);
+            nodes.push(NodePlan { name, role, program: parachain.binary.clone(), args, env: collator.env.clone(), ports, bootnode });
+        }
+    }
+    Ok(nodes)
+}
+
+// Adds the flags every node gets and returns its ports and bootnode address
+fn node_args<K: FnMut() -> Result<[u8; 32], Box<dyn Error>>>(args: &mut Vec<String>, name: &str, node: &NodeSpec, data_dir: &Path, boot_from: Option<&str>, pool: &mut PortPool, node_key: &mut K) -> Result<(NodePorts, String), Box<dyn Error>> {
+    let key = node_key()?;
+    let p2p_port = match node.port {
+        Some(port) => port,
+        None => pool.next(DEFAULT_P2P_PORT)?,
+    };
+    let rpc_port = match node.rpc_port {
+        Some(port) => port,
+        None => pool.next(rpc::DEFAULT_RPC_PORT)?,
+    };
+    let prometheus_port = match node.prometheus_port {
+        Some(port) => port,
+        None => pool.next(DEFAULT_PROMETHEUS_PORT)?,
+    };
+
+    args.extend([
+        "--name".to_string(), name.to_string(),
+        "--base-path".to_string(), db::base_path(data_dir, name).display().to_string(),
+        "--node-key".to_string(), hex::encode(key),
+        "--port".to_string(), p2p_port.to_string(),
+        "--rpc-port".to_string(), rpc_port.to_string(),
+        "--prometheus-port".to_string(), prometheus_port.to_string(),
+        "--rpc-cors".to_string(), "all".to_string(),
+    ]);
+    if DEV_ACCOUNTS.contains(&node.name.as_str()) {
+        args.push(format!("--{}", node.name));
+    }
+    if let Some(boot_from) = boot_from {
+        args.extend(["--bootnodes".to_string(), boot_from.to_string()]);
+    }
+    let ports = NodePorts { rpc: rpc_port, p2p: p2p_port, prometheus: Some(prometheus_port) };
+    Ok((ports, bootnode(p2p_port, &peer_id(&key))))
+}
+
+// The chain spec of every parachain, generated with chain-spec-builder for those given as a runtime
+pub fn parachain_chain_specs(topology: &Topology, dir: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut specs = Vec::new();
+    for parachain in &topology.parachains {
+        let spec = match (&parachain.chain, &parachain.runtime) {
+            (Some(chain), _) => chain.clone(),
+            (None, Some(runtime)) => {
+                let path = dir.join(format!("parachain-{}-chain-spec.json", parachain.id));
+                let relay_chain = relay_chain_id(&topology.relaychain.chain)?;
+                chain_specs::create_chain_spec(Path::new(runtime), Path::new(CHAIN_SPEC_BUILDER_PATH), &path, &relay_chain, parachain.id)?;
+
+                // The runtime's preset has its own para id, which must match the registered one
+                let mut spec: Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+                if let Some(info) = spec["genesis"]["runtimeGenesis"]["patch"]["parachainInfo"].as_object_mut() {
+                    info.insert("parachainId".to_string(), json!(parachain.id));
+                    fs::write(&path, format!("{:#}\n", spec))?;
+                }
+                path.display().to_string()
+            }
+            (None, None) => return Err(format!("Parachain {} has no chain spec or runtime", parachain.id).into()),
+        };
+        specs.push(spec);
+    }
+    Ok(specs)
+}
+
+// The `id` of a relay chain spec file, or the name of a chain built into the relay binary
+fn relay_chain_id(chain: &str) -> Result<String, Box<dyn Error>> {
+    if !Path::new(chain).exists() {
+        return Ok(chain.to_string());
+    }
+    let spec: Value = serde_json::from_str(&fs::read_to_string(chain)?).map_err(|e| format!("Invalid relay chain spec {}: {}", chain, e))?;
+    Ok(spec["id"].as_str().ok_or_else(|| format!("Relay chain spec {} has no id", chain))?.to_string())
+}
+
+// Parachains are registered at genesis: the relay chain spec gets their genesis head and wasm
+pub fn relay_chain_spec(topology: &Topology, dir: &Path, para_specs: &[String]) -> Result<String, Box<dyn Error>> {
+    let relay = &topology.relaychain;
+    if topology.parachains.is_empty() {
+        return Ok(relay.chain.clone());
+    }
+
+    let content = if Path::new(&relay.chain).exists() {
+        fs::read_to_string(&relay.chain)?
+    } else {
+        run_for_output(&relay.binary, &["build-spec", "--chain", &relay.chain, "--disable-default-bootnode"])?
+    };
+    let mut spec: Value = serde_json::from_str(&content).map_err(|e| format!("Invalid relay chain spec {}: {}", relay.chain, e))?;
+
+    for (parachain, chain) in topology.parachains.iter().zip(para_specs) {
+        let head = run_for_output(&parachain.binary, &["export-genesis-head", "--chain", chain])?;
+        let code = run_for_output(&parachain.binary, &["export-genesis-wasm", "--chain", chain])?;
+        register_parachain(&mut spec, parachain.id, head.trim(), code.trim())?;
+    }
+
+    let path = dir.join("relay-chain-spec.json");
+    fs::write(&path, format!("{:#}\n", spec))?;
+    Ok(path.display().to_string())
+}
+
+pub fn register_parachain(spec: &mut Value, id: u32, genesis_head: &str, validation_code: &str) -> Result<(), Box<dyn Error>> {
+    let genesis = &mut spec["genesis"];
+    if genesis.get("raw").is_some() {
+        return Err("Parachains can only be registered in a plain relay chain spec, not a raw one".into());
+    }
+    let runtime = if genesis["runtimeGenesis"].get("patch").is_some() {
+        &mut genesis["runtimeGenesis"]["patch"]
+    } else if genesis["runtimeGenesis"].get("config").is_some() {
+        &mut genesis["runtimeGenesis"]["config"]
+    } else if genesis.get("runtime").is_some() {
+        &mut genesis["runtime"]
+    } else {
+        return Err("The relay chain spec has no runtime genesis config".into());
+    };
+
+    let paras = &mut runtime["paras"]["paras"];
+    if paras.is_null() {
+        *paras = json!([]);
+    }
+    let paras = paras.as_array_mut().ok_or("The relay chain spec has an invalid paras genesis config")?;
+    paras.push(json!([id, { "genesis_head": genesis_head, "validation_code": validation_code, "para_kind": true }]));
+    Ok(())
+}
+
+fn run_for_output(program: &str, args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!("{} {} failed: {}", program, args.join(" "), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn record_file(name: &str) -> PathBuf {
+    Path::new(NETWORK_DIR).join(name).join("network.json")
+}
+
+pub fn up(topology: &Topology) -> Result<Vec<NodePlan>, Box<dyn Error>> {
+    let record = record_file(&topology.name);
+    if record.exists() {
+        return Err(format!("Network {} is already up, stop it first with `dot network down {}`", topology.name, topology.name).into());
+    }
+
+    // Every `network up` starts from genesis, with fresh chain specs and databases
+    let dir = Path::new(NETWORK_DIR).join(&topology.name);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+
+    let para_specs = parachain_chain_specs(topology, &dir)?;
+    let relay_spec = relay_chain_spec(topology, &dir, &para_specs)?;
+    let data_dir = Path::new(db::DATA_DIR);
+    let nodes = plan(topology, data_dir, &relay_spec, &para_specs, generate_node_key)?;
+    for node in &nodes {
+        db::purge(data_dir, Path::new(daemon::RUN_DIR), &node.name)?;
+    }
+
+    start_nodes(&nodes, Path::new(daemon::RUN_DIR), |node| {
+        serve::spawn_supervised(&node.program, &node.args, &node.env, &node.name, NODE_GRACE_PERIOD, &RestartPolicy::never())
+    })?;
+
+    let names: Vec<&str> = nodes.iter().map(|node| node.name.as_str()).collect();
+    fs::write(&record, format!("{:#}\n", json!({ "nodes": names })))?;
+    Ok(nodes)
+}
+
+// Stops every node of the network and returns their names
+pub fn down(name: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let record = record_file(name);
+    let content = fs::read_to_string(&record).map_err(|_| format!("No network named {} is up", name))?;
+    let content: Value = serde_json::from_str(&content).map_err(|e| format!("Invalid network record {:?}: {}", record, e))?;
+    let nodes: Vec<String> = content["nodes"]
+        .as_array()
+        .map(|nodes| nodes.iter().filter_map(|node| node.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+
+    stop_nodes(Path::new(daemon::RUN_DIR), &nodes.iter().map(String::as_str).collect::<Vec<_>>());
+    fs::remove_file(&record)?;
+    Ok(nodes)
+}
+
+// Starts the nodes and records their ports like `dot serve` does, so that commands taking --node
+// find them. If one fails to start, those already started are stopped again
+fn start_nodes<F: FnMut(&NodePlan) -> Result<u32, Box<dyn Error>>>(nodes: &[NodePlan], run_dir: &Path, mut spawn: F) -> Result<(), Box<dyn Error>> {
+    let mut started: Vec<&str> = Vec::new();
+    for node in nodes {
+        if let Err(e) = spawn(node).and_then(|_| ports::write_ports(run_dir, &node.name, &node.ports)) {
+            started.push(&node.name);
+            stop_nodes(run_dir, &started);
+            return Err(format!("Failed to start {}: {}", node.name, e).into());
+        }
+        started.push(&node.name);
+    }
+    Ok(())
+}
+
+// Stopping removes a node's run files, its ports included, but they must not outlive a node that
+// could not be stopped either
+fn stop_nodes(run_dir: &Path, names: &[&str]) {
+    for name in names {
+        if let Err(e) = daemon::stop_node(run_dir, name, NODE_GRACE_PERIOD + Duration::from_secs(5)) {
+            eprintln!("Failed to stop {}: {}", name, e);
+        }
+        if let Err(e) = ports::remove_ports(run_dir, name) {
+            eprintln!("Failed to remove the ports of {}: {}", name, e);
+        }
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics;
+    use crate::topology;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_peer_id() {
+        let mut secret = [0u8; 32];
+        secret[31] = 1;
+        assert_eq!(peer_id(&secret), "12D3KooWEyoppNCUx8Yx66oV9fJnriXwCcXwDDUA2kj6vnc6iDEp");
+        assert_ne!(generate_node_key().unwrap(), generate_node_key().unwrap());
+    }
+
+    #[test]
+    fn test_plan_wires_nodes_together() {
+        let topology = topology::parse(r#"
+            name = "local"
+            [relaychain]
+            chain = "rococo-local"
+            validators = 2
+            [[parachains]]
+            id = 1000
+            chain = "para.json"
+            collators = ["charlie", { name = "collator", args = ["--pool-limit", "10", "--", "--sync", "fast"], env = { RUST_LOG = "debug" } }]
+        "#, "local").unwrap();
+
+        let mut count = 0u8;
+        let nodes = plan(&topology, Path::new("./data"), "relay.json", &["para.json".to_string()], || {
+            count += 1;
+            Ok([count; 32])
+        }).unwrap();
+
+        let names: Vec<&str> = nodes.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, ["local-alice", "local-bob", "local-1000-charlie", "local-1000-collator"]);
+        assert_eq!(nodes[0].program, topology::DEFAULT_RELAY_BINARY);
+        assert_eq!(nodes[2].program, serve::OMNI_NODE_PATH);
+
+        let alice = &nodes[0].args;
+        assert!(alice.windows(2).any(|pair| pair == ["--node-key", &hex::encode([1u8; 32])]));
+        assert!(alice.contains(&"--alice".to_string()) && alice.contains(&"--validator".to_string()));
+        assert!(!alice.contains(&"--bootnodes".to_string()));
+        assert!(nodes[1].args.windows(2).any(|pair| pair == ["--bootnodes", &nodes[0].bootnode]));
+
+        // The second collator boots from the first, its relay chain node from the first validator
+        let collator = &nodes[3].args;
+        assert!(!collator.iter().any(|arg| DEV_ACCOUNTS.iter().any(|account| *arg == format!("--{}", account))));
+        let (para, relay) = serve::split_relay_args(collator);
+        assert!(para.windows(2).any(|pair| pair == ["--bootnodes", &nodes[2].bootnode]));
+        assert!(relay.unwrap().windows(2).any(|pair| pair == ["--bootnodes", &nodes[0].bootnode]));
+
+        // The node's own args go to their section, its env to the supervisor
+        assert!(para.ends_with(&["--pool-limit".to_string(), "10".to_string()]));
+        assert!(relay.unwrap().ends_with(&["--sync".to_string(), "fast".to_string()]));
+        assert_eq!(nodes[3].env, vec![("RUST_LOG".to_string(), "debug".to_string())]);
+
+        // Every node gets its own ports
+        let mut ports: Vec<&String> = nodes.iter().flat_map(|node| node.args.windows(2).filter(|pair| pair[0].ends_with("port")).map(|pair| &pair[1])).collect();
+        let total = ports.len();
+        ports.sort();
+        ports.dedup();
+        assert_eq!(ports.len(), total);
+    }
+
+    #[test]
+    fn test_plan_keeps_explicit_ports() {
+        let topology = topology::parse(r#"
+            [relaychain]
+            chain = "rococo-local"
+            validators = ["alice", { name = "bob", rpc_port = 9944, port = 40000 }]
+        "#, "local").unwrap();
+
+        let nodes = plan(&topology, Path::new("./data"), "rococo-local", &[], generate_node_key).unwrap();
+        assert_eq!(nodes[1].ports.rpc, 9944);
+        assert!(nodes[1].bootnode.starts_with("/ip4/127.0.0.1/tcp/40000/p2p/12D3KooW"));
+        // alice does not get bob's RPC port even though it is the default
+        assert_ne!(nodes[0].ports.rpc, 9944);
+    }
+
+    #[test]
+    fn test_node_ports_recorded_while_up() {
+        let topology = topology::parse(r#"
+            [relaychain]
+            chain = "rococo-local"
+            validators = 2
+        "#, "local").unwrap();
+        let nodes = plan(&topology, Path::new("./data"), "rococo-local", &[], generate_node_key).unwrap();
+        let names: Vec<&str> = nodes.iter().map(|node| node.name.as_str()).collect();
+        let run_dir = tempfile::tempdir().unwrap();
+
+        start_nodes(&nodes, run_dir.path(), |_| Ok(1)).unwrap();
+        for node in &nodes {
+            assert_eq!(ports::read_ports(run_dir.path(), &node.name).unwrap(), node.ports);
+        }
+        stop_nodes(run_dir.path(), &names);
+        assert!(names.iter().all(|name| ports::read_ports(run_dir.path(), name).is_err()));
+
+        // Nodes started before one that fails are stopped and forgotten again
+        let error = start_nodes(&nodes, run_dir.path(), |node| if node.name == "local-bob" { Err("no binary".into()) } else { Ok(1) }).unwrap_err();
+        assert_eq!(error.to_string(), "Failed to start local-bob: no binary");
+        assert!(names.iter().all(|name| ports::read_ports(run_dir.path(), name).is_err()));
+    }
+
+    #[test]
+    fn test_metrics_endpoint_of_network_node() {
+        let topology = topology::parse(r#"
+            [relaychain]
+            chain = "rococo-local"
+            validators = ["alice", { name = "bob", prometheus_port = 9700 }]
+        "#, "local").unwrap();
+        let nodes = plan(&topology, Path::new("./data"), "rococo-local", &[], generate_node_key).unwrap();
+        let run_dir = tempfile::tempdir().unwrap();
+
+        start_nodes(&nodes, run_dir.path(), |_| Ok(1)).unwrap();
+        assert_eq!(metrics::node_endpoint(run_dir.path(), "local-bob").unwrap(), "http://127.0.0.1:9700/metrics");
+        assert_eq!(metrics::node_endpoint(run_dir.path(), "local-alice").unwrap(), metrics::metrics_endpoint(nodes[0].ports.prometheus.unwrap()));
+        assert_eq!(metrics::node_endpoint(run_dir.path(), "local-carol").unwrap_err().to_string(), "No ports are recorded for a node named local-carol");
+    }
+
+    #[test]
+    fn test_register_parachain() {
+        let mut spec = json!({ "genesis": { "runtimeGenesis": { "code": "0x00", "patch": { "balances": {} } } } });
+        register_parachain(&mut spec, 1000, "0x01", "0x02").unwrap();
+        register_parachain(&mut spec, 2000, "0x03", "0x04").unwrap();
+        assert_eq!(spec["genesis"]["runtimeGenesis"]["patch"]["paras"]["paras"], json!([
+            [1000, { "genesis_head": "0x01", "validation_code": "0x02", "para_kind": true }],
+            [2000, { "genesis_head": "0x03", "validation_code": "0x04", "para_kind": true }],
+        ]));
+
+        let mut raw = json!({ "genesis": { "raw": { "top": {} } } });
+        assert!(register_parachain(&mut raw, 1000, "0x01", "0x02").is_err());
+    }
+
+    #[test]
+    fn test_relay_chain_spec_registers_parachains() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = |name: &str, body: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            path.display().to_string()
+        };
+        let relay = script("polkadot", r#"echo '{"name":"Rococo Local","genesis":{"runtimeGenesis":{"patch":{}}}}'"#);
+        let para = script("omni-node", r#"if [ "$1" = export-genesis-head ]; then echo 0xhead; else echo 0xcode; fi"#);
+
+        let topology = topology::parse(&format!(r#"
+            [relaychain]
+            binary = "{}"
+            chain = "rococo-local"
+            validators = 1
+            [[parachains]]
+            id = 1000
+            binary = "{}"
+            chain = "para.json"
+            collators = 1
+        "#, relay, para), "local").unwrap();
+
+        let path = relay_chain_spec(&topology, dir.path(), &["para.json".to_string()]).unwrap();
+        let spec: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(spec["genesis"]["runtimeGenesis"]["patch"]["paras"]["paras"][0], json!([1000, { "genesis_head": "0xhead", "validation_code": "0xcode", "para_kind": true }]));
+    }
+
+    #[test]
+    fn test_down_unknown_network() {
+        assert_eq!(down("no-such-network").unwrap_err().to_string(), "No network named no-such-network is up");
+    }
+}
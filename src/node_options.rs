@@ -0,0 +1,192 @@
+use std::path::PathBuf;
+use crate::serve::has_flag;
+
+const LOG_LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+// First-class `dot serve` options for the omni-node flags people reach for most
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NodeOptions {
+    pub dev: bool,
+    pub name: Option<String>,
+    pub base_path: Option<PathBuf>,
+    pub tmp: bool,
+    pub port: Option<u16>,
+    pub rpc_port: Option<u16>,
+    pub prometheus_port: Option<u16>,
+    pub rpc_cors: Option<String>,
+    pub log: Option<String>,
+    pub dev_block_time: Option<u64>,
+    pub instant_seal: bool,
+}
+
+impl NodeOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tmp && self.base_path.is_some() {
+            return Err("--tmp and --base-path cannot be used together".to_string());
+        }
+        if self.instant_seal && self.dev_block_time.is_some() {
+            return Err("--instant-seal and --dev-block-time cannot be used together".to_string());
+        }
+        if self.dev_block_time == Some(0) {
+            return Err("--dev-block-time must be at least 1 millisecond".to_string());
+        }
+
+        let ports = [("--port", self.port), ("--rpc-port", self.rpc_port), ("--prometheus-port", self.prometheus_port)];
+        for (i, (flag, port)) in ports.iter().enumerate() {
+            if let Some(port) = port {
+                if let Some((other, _)) = ports[i + 1..].iter().find(|(_, other)| *other == Some(*port)) {
+                    return Err(format!("{} and {} both use port {}", flag, other, port));
+                }
+            }
+        }
+
+        if let Some(cors) = &self.rpc_cors {
+            validate_rpc_cors(cors)?;
+        }
+        if let Some(log) = &self.log {
+            validate_log(log)?;
+        }
+        Ok(())
+    }
+
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.dev {
+            args.push("--dev".to_string());
+        }
+        if let Some(name) = &self.name {
+            args.extend(["--name".to_string(), name.clone()]);
+        }
+        if let Some(base_path) = &self.base_path {
+            args.extend(["--base-path".to_string(), base_path.display().to_string()]);
+        }
+        if self.tmp {
+            args.push("--tmp".to_string());
+        }
+        for (flag, port) in [("--port", self.port), ("--rpc-port", self.rpc_port), ("--prometheus-port", self.prometheus_port)] {
+            if let Some(port) = port {
+                args.extend([flag.to_string(), port.to_string()]);
+            }
+        }
+        if let Some(cors) = &self.rpc_cors {
+            args.extend(["--rpc-cors".to_string(), cors.clone()]);
+        }
+        if let Some(log) = &self.log {
+            args.extend(["--log".to_string(), log.clone()]);
+        }
+        if let Some(block_time) = self.dev_block_time {
+            args.extend(["--dev-block-time".to_string(), block_time.to_string()]);
+        }
+        if self.instant_seal {
+            args.push("--instant-seal".to_string());
+        }
+        args
+    }
+
+    // Validates the options and puts them in front of the pass-through node arguments
+    pub fn apply<S: AsRef<str>>(&self, node_args: &[S]) -> Result<Vec<String>, String> {
+        self.validate()?;
+        let typed = self.to_args();
+        for flag in typed.iter().filter(|arg| arg.starts_with("--")) {
+            if has_flag(node_args, flag) {
+                return Err(format!("{} is given both as a dot serve option and as an omni-node argument", flag));
+            }
+        }
+        Ok(typed.into_iter().chain(node_args.iter().map(|arg| arg.as_ref().to_string())).collect())
+    }
+}
+
+// `all`, or a comma separated list of origins such as http://localhost:3000
+fn validate_rpc_cors(cors: &str) -> Result<(), String> {
+    if cors == "all" {
+        return Ok(());
+    }
+    for origin in cors.split(',') {
+        if !origin.trim().contains("://") {
+            return Err(format!("Invalid --rpc-cors origin {:?}: use `all` or origins like http://localhost:3000", origin));
+        }
+    }
+    Ok(())
+}
+
+// A comma separated list of `level` or `target=level` directives, e.g. info,runtime=debug
+fn validate_log(log: &str) -> Result<(), String> {
+    for directive in log.split(',') {
+        let (target, level) = match directive.split_once('=') {
+            Some((target, level)) => (Some(target), level),
+            None => (None, directive),
+        };
+        if target.map(str::is_empty).unwrap_or(false) {
+            return Err(format!("Invalid --log directive {:?}: the target is empty", directive));
+        }
+        if !LOG_LEVELS.contains(&level.to_ascii_lowercase().as_str()) {
+            return Err(format!("Invalid --log level {:?}: use one of {}", level, LOG_LEVELS.join(", ")));
+        }
+    }
+    Ok(())
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_args() {
+        let options = NodeOptions {
+            dev: true,
+            name: Some("alice".to_string()),
+            tmp: true,
+            rpc_port: Some(9955),
+            prometheus_port: Some(9616),
+            rpc_cors: Some("all".to_string()),
+            log: Some("info,runtime=debug".to_string()),
+            dev_block_time: Some(3000),
+            ..Default::default()
+        };
+        assert_eq!(options.to_args(), vec![
+            "--dev", "--name", "alice", "--tmp", "--rpc-port", "9955", "--prometheus-port", "9616",
+            "--rpc-cors", "all", "--log", "info,runtime=debug", "--dev-block-time", "3000",
+        ]);
+        assert!(NodeOptions::default().to_args().is_empty());
+    }
+
+    #[test]
+    fn test_validate_conflicts() {
+        let options = NodeOptions { tmp: true, base_path: Some(PathBuf::from("./data")), ..Default::default() };
+        assert_eq!(options.validate().unwrap_err(), "--tmp and --base-path cannot be used together");
+
+        let options = NodeOptions { instant_seal: true, dev_block_time: Some(1000), ..Default::default() };
+        assert_eq!(options.validate().unwrap_err(), "--instant-seal and --dev-block-time cannot be used together");
+
+        let options = NodeOptions { port: Some(9944), rpc_port: Some(9944), ..Default::default() };
+        assert_eq!(options.validate().unwrap_err(), "--port and --rpc-port both use port 9944");
+    }
+
+    #[test]
+    fn test_validate_values() {
+        let options = |cors: &str, log: &str| NodeOptions { rpc_cors: Some(cors.to_string()), log: Some(log.to_string()), ..Default::default() };
+        assert!(options("http://localhost:3000,https://polkadot.js.org", "warn,sync=TRACE").validate().is_ok());
+        assert!(options("localhost:3000", "info").validate().unwrap_err().starts_with("Invalid --rpc-cors origin \"localhost:3000\""));
+        assert!(options("all", "loud").validate().unwrap_err().starts_with("Invalid --log level \"loud\""));
+        assert!(options("all", "=info").validate().unwrap_err().contains("the target is empty"));
+
+        let options = NodeOptions { dev_block_time: Some(0), ..Default::default() };
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn test_apply_detects_duplicates() {
+        let options = NodeOptions { rpc_port: Some(9955), ..Default::default() };
+        assert_eq!(options.apply(&["--chain", "spec.json"]).unwrap(), vec!["--rpc-port", "9955", "--chain", "spec.json"]);
+        assert_eq!(
+            options.apply(&["--rpc-port=9966"]).unwrap_err(),
+            "--rpc-port is given both as a dot serve option and as an omni-node argument"
+        );
+        // The relay chain may use the same flag
+        assert!(options.apply(&["--", "--rpc-port", "9977"]).is_ok());
+    }
+}
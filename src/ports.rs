@@ -0,0 +1,226 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+use crate::metrics;
+use crate::rpc;
+use crate::serve::{flag_value, has_flag};
+
+pub const DEFAULT_P2P_PORT: u16 = 30333;
+pub const DEFAULT_PROMETHEUS_PORT: u16 = 9615;
+// How far past a taken default port to look for a free one
+const SEARCH_RANGE: u16 = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodePorts {
+    pub rpc: u16,
+    pub p2p: u16,
+    // None when the node runs with --no-prometheus
+    pub prometheus: Option<u16>,
+}
+
+impl NodePorts {
+    pub fn rpc_endpoint(&self) -> String {
+        rpc::local_endpoint(self.rpc)
+    }
+
+    pub fn print(&self) {
+        println!("RPC endpoint:     {} (ws://127.0.0.1:{})", self.rpc_endpoint(), self.rpc);
+        println!("p2p address:      /ip4/127.0.0.1/tcp/{}", self.p2p);
+        match self.prometheus {
+            Some(port) => println!("Metrics endpoint: {}", metrics::metrics_endpoint(port)),
+            None => println!("Metrics endpoint: disabled"),
+        }
+    }
+}
+
+// Whether nothing listens on the port yet, on any interface
+pub fn is_free(port: u16) -> bool {
+    TcpListener::bind(("0.0.0.0", port)).is_ok()
+}
+
+pub fn find_free<F: Fn(u16) -> bool>(start: u16, taken: &[u16], is_free: F) -> Option<u16> {
+    (start..=start.saturating_add(SEARCH_RANGE)).find(|port| !taken.contains(port) && is_free(*port))
+}
+
+// Checks the ports the node will use before it starts. Ports given explicitly must be free, the
+// defaults are moved to the next free port and the flag is added in front of the node arguments
+pub fn allocate<S: AsRef<str>, F: Fn(u16) -> bool>(args: &[S], is_free: F) -> Result<(Vec<String>, NodePorts), Box<dyn Error>> {
+    let prometheus = !has_flag(args, "--no-prometheus");
+    let wanted = [
+        ("--rpc-port", "RPC", rpc::DEFAULT_RPC_PORT, true),
+        ("--port", "p2p", DEFAULT_P2P_PORT, true),
+        ("--prometheus-port", "Prometheus", DEFAULT_PROMETHEUS_PORT, prometheus),
+    ];
+
+    // Explicit ports first, so that a default never takes a port the user asked for
+    let mut taken: Vec<u16> = Vec::new();
+    let mut allocated = [None; 3];
+    for (i, (flag, label, _, enabled)) in wanted.iter().enumerate() {
+        let value = match flag_value(args, flag) {
+            Some(value) if *enabled => value,
+            _ => continue,
+        };
+        let port: u16 = value.parse().map_err(|_| format!("Invalid {} {}", flag, value))?;
+        if taken.contains(&port) {
+            return Err(format!("{} port {} ({}) is already used by another port of the node", label, port, flag).into());
+        }
+        if !is_free(port) {
+            return Err(format!("{} port {} ({}) is already in use", label, port, flag).into());
+        }
+        taken.push(port);
+        allocated[i] = Some(port);
+    }
+
+    let mut flags = Vec::new();
+    for (i, (flag, label, default, enabled)) in wanted.iter().enumerate() {
+        if !enabled || allocated[i].is_some() {
+            continue;
+        }
+        let port = find_free(*default, &taken, &is_free)
+            .ok_or_else(|| format!("No free {} port between {} and {}", label, default, default.saturating_add(SEARCH_RANGE)))?;
+        if port != *default {
+            println!("{} port {} is in use, using {} instead", label, default, port);
+            flags.extend([flag.to_string(), port.to_string()]);
+        }
+        taken.push(port);
+        allocated[i] = Some(port);
+    }
+
+    let ports = NodePorts {
+        rpc: allocated[0].expect("RPC port is always allocated"),
+        p2p: allocated[1].expect("p2p port is always allocated"),
+        prometheus: allocated[2],
+    };
+    flags.extend(args.iter().map(|arg| arg.as_ref().to_string()));
+    Ok((flags, ports))
+}
+
+// Recorded next to the PID file so other commands can find a managed node's endpoints
+pub fn ports_file(run_dir: &Path, name: &str) -> PathBuf {
+    run_dir.join(format!("{}.ports.json", name))
+}
+
+pub fn write_ports(run_dir: &Path, name: &str, ports: &NodePorts) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(run_dir)?;
+    let content = json!({ "rpc": ports.rpc, "p2p": ports.p2p, "prometheus": ports.prometheus });
+    fs::write(ports_file(run_dir, name), format!("{:#}\n", content))?;
+    Ok(())
+}
+
+pub fn remove_ports(run_dir: &Path, name: &str) -> Result<(), Box<dyn Error>> {
+    match fs::remove_file(ports_file(run_dir, name)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+pub fn read_ports(run_dir: &Path, name: &str) -> Result<NodePorts, Box<dyn Error>> {
+    let path = ports_file(run_dir, name);
+    let content = fs::read_to_string(&path).map_err(|_| format!("No ports are recorded for a node named {}", name))?;
+    let content: Value = serde_json::from_str(&content).map_err(|e| format!("Invalid ports file {:?}: {}", path, e))?;
+    let port = |key: &str| content[key].as_u64().map(|port| port as u16);
+    Ok(NodePorts {
+        rpc: port("rpc").ok_or_else(|| format!("Invalid ports file {:?}: no RPC port", path))?,
+        p2p: port("p2p").ok_or_else(|| format!("Invalid ports file {:?}: no p2p port", path))?,
+        prometheus: port("prometheus"),
+    })
+}
+
+// The most recently served node that is still recorded, i.e. the one of the last `dot serve`
+pub fn last_served(run_dir: &Path) -> Option<(String, NodePorts)> {
+    let suffix = ".ports.json";
+    let entries = fs::read_dir(run_dir).ok()?;
+    let (_, name) = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().to_str()?.strip_suffix(suffix)?.to_string();
+            Some((entry.metadata().ok()?.modified().ok()?, name))
+        })
+        .max()?;
+    let ports = read_ports(run_dir, &name).ok()?;
+    Some((name, ports))
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_allocate_defaults_when_free() {
+        let (args, ports) = allocate(&["--chain", "spec.json"], |_| true).unwrap();
+        assert_eq!(args, vec!["--chain", "spec.json"]);
+        assert_eq!(ports, NodePorts { rpc: 9944, p2p: 30333, prometheus: Some(9615) });
+    }
+
+    #[test]
+    fn test_allocate_moves_taken_defaults() {
+        let busy = [9944, 9945, 30333];
+        let (args, ports) = allocate(&["--chain", "spec.json", "--no-prometheus"], |port| !busy.contains(&port)).unwrap();
+        assert_eq!(args, vec!["--rpc-port", "9946", "--port", "30334", "--chain", "spec.json", "--no-prometheus"]);
+        assert_eq!(ports, NodePorts { rpc: 9946, p2p: 30334, prometheus: None });
+    }
+
+    #[test]
+    fn test_allocate_rejects_taken_explicit_port() {
+        let result = allocate(&["--rpc-port=9955"], |port| port != 9955);
+        assert_eq!(result.unwrap_err().to_string(), "RPC port 9955 (--rpc-port) is already in use");
+
+        let result = allocate(&["--rpc-port", "9615", "--prometheus-port=9615"], |_| true);
+        assert_eq!(result.unwrap_err().to_string(), "Prometheus port 9615 (--prometheus-port) is already used by another port of the node");
+
+        assert_eq!(allocate(&["--port", "nope"], |_| true).unwrap_err().to_string(), "Invalid --port nope");
+    }
+
+    #[test]
+    fn test_allocate_skips_explicit_ports_of_the_node() {
+        // The Prometheus port is explicitly 9944, so the RPC default moves on
+        let (args, ports) = allocate(&["--prometheus-port", "9944"], |_| true).unwrap();
+        assert_eq!(args, vec!["--rpc-port", "9945", "--prometheus-port", "9944"]);
+        assert_eq!(ports, NodePorts { rpc: 9945, p2p: 30333, prometheus: Some(9944) });
+    }
+
+    #[test]
+    fn test_is_free() {
+        let listener = TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(!is_free(port));
+        drop(listener);
+        assert!(is_free(port));
+    }
+
+    #[test]
+    fn test_ports_round_trip() {
+        let run_dir = tempfile::tempdir().unwrap();
+        let ports = NodePorts { rpc: 9946, p2p: 30334, prometheus: None };
+        write_ports(run_dir.path(), "node", &ports).unwrap();
+        assert_eq!(read_ports(run_dir.path(), "node").unwrap(), ports);
+        assert_eq!(read_ports(run_dir.path(), "other").unwrap_err().to_string(), "No ports are recorded for a node named other");
+
+        remove_ports(run_dir.path(), "node").unwrap();
+        assert!(read_ports(run_dir.path(), "node").is_err());
+        // Nothing to remove is fine
+        remove_ports(run_dir.path(), "node").unwrap();
+    }
+
+    #[test]
+    fn test_last_served() {
+        let run_dir = tempfile::tempdir().unwrap();
+        assert_eq!(last_served(run_dir.path()), None);
+
+        let first = NodePorts { rpc: 9944, p2p: 30333, prometheus: Some(9615) };
+        let second = NodePorts { rpc: 9945, p2p: 30334, prometheus: None };
+        write_ports(run_dir.path(), "first", &first).unwrap();
+        write_ports(run_dir.path(), "second", &second).unwrap();
+        let earlier = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        File::options().write(true).open(ports_file(run_dir.path(), "first")).unwrap().set_modified(earlier).unwrap();
+        assert_eq!(last_served(run_dir.path()), Some(("second".to_string(), second)));
+    }
+}
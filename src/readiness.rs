@@ -0,0 +1,106 @@
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::rpc;
+
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, PartialEq)]
+pub struct Readiness {
+    pub peers: u64,
+    pub best_block: u64,
+}
+
+pub struct ReadyCheck {
+    pub timeout: Duration,
+    // When set, a node that is not ready in time is stopped and serve fails
+    pub required: bool,
+}
+
+// A node is ready once it is not major-syncing and has either authored/imported a block or found peers
+pub fn check(endpoint: &str) -> Result<Option<Readiness>, Box<dyn Error>> {
+    let health = rpc::system_health(endpoint)?;
+    if health.is_syncing {
+        return Ok(None);
+    }
+    let best_block = rpc::best_block_number(endpoint)?;
+    if best_block == 0 && health.peers == 0 {
+        return Ok(None);
+    }
+    Ok(Some(Readiness { peers: health.peers, best_block }))
+}
+
+pub fn wait_ready<F: FnMut() -> bool>(endpoint: &str, timeout: Duration, poll: Duration, mut is_running: F) -> Result<Readiness, Box<dyn Error>> {
+    let start = Instant::now();
+    loop {
+        if !is_running() {
+            return Err("Node exited before it became ready".into());
+        }
+        let last_status = match check(endpoint) {
+            Ok(Some(readiness)) => return Ok(readiness),
+            Ok(None) => "no blocks or peers yet".to_string(),
+            Err(e) => e.to_string(),
+        };
+        if start.elapsed() >= timeout {
+            return Err(format!("Node was not ready after {}s ({})", timeout.as_secs(), last_status).into());
+        }
+        thread::sleep(poll);
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher, Mock};
+    use serde_json::json;
+
+    fn mock_node(path: &str, peers: u64, is_syncing: bool, best_block: &str) -> (Mock, Mock) {
+        let health = mock("POST", path)
+            .match_body(Matcher::PartialJson(json!({ "method": "system_health" })))
+            .with_body(json!({ "jsonrpc": "2.0", "id": 1, "result": { "peers": peers, "isSyncing": is_syncing, "shouldHavePeers": false } }).to_string())
+            .create();
+        let header = mock("POST", path)
+            .match_body(Matcher::PartialJson(json!({ "method": "chain_getHeader" })))
+            .with_body(json!({ "jsonrpc": "2.0", "id": 1, "result": { "number": best_block } }).to_string())
+            .create();
+        (health, header)
+    }
+
+    #[test]
+    fn test_ready_once_blocks_are_produced() {
+        let _node = mock_node("/ready-blocks", 0, false, "0x3");
+        let endpoint = format!("{}/ready-blocks", mockito::server_url());
+
+        let readiness = wait_ready(&endpoint, Duration::from_secs(5), Duration::from_millis(10), || true).unwrap();
+        assert_eq!(readiness, Readiness { peers: 0, best_block: 3 });
+    }
+
+    #[test]
+    fn test_ready_with_peers_at_genesis() {
+        let _node = mock_node("/ready-peers", 2, false, "0x0");
+        let endpoint = format!("{}/ready-peers", mockito::server_url());
+        assert_eq!(check(&endpoint).unwrap(), Some(Readiness { peers: 2, best_block: 0 }));
+    }
+
+    #[test]
+    fn test_not_ready_while_syncing_or_idle() {
+        let _syncing = mock_node("/ready-syncing", 5, true, "0x10");
+        let _idle = mock_node("/ready-idle", 0, false, "0x0");
+
+        assert_eq!(check(&format!("{}/ready-syncing", mockito::server_url())).unwrap(), None);
+
+        let endpoint = format!("{}/ready-idle", mockito::server_url());
+        let result = wait_ready(&endpoint, Duration::from_millis(50), Duration::from_millis(10), || true);
+        assert_eq!(result.unwrap_err().to_string(), "Node was not ready after 0s (no blocks or peers yet)");
+    }
+
+    #[test]
+    fn test_wait_ready_node_exited() {
+        let result = wait_ready("http://127.0.0.1:1", Duration::from_secs(5), Duration::from_millis(10), || false);
+        assert_eq!(result.unwrap_err().to_string(), "Node exited before it became ready");
+    }
+}
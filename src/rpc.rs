@@ -0,0 +1,355 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use tungstenite::client::IntoClientRequest;
+use tungstenite::{Message, WebSocket};
+
+pub const DEFAULT_RPC_PORT: u16 = 9944;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn local_endpoint(port: u16) -> String {
+    format!("http://127.0.0.1:{}", port)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Health {
+    pub peers: u64,
+    pub is_syncing: bool,
+    pub should_have_peers: bool,
+}
+
+// A single JSON-RPC 2.0 request over HTTP, returning the `result` member
+pub fn call(endpoint: &str, method: &str, params: Value) -> Result<Value, Box<dyn Error>> {
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let body = ureq::post(endpoint)
+        .timeout(REQUEST_TIMEOUT)
+        .set("Content-Type", "application/json")
+        .send_string(&request.to_string())
+        .map_err(|e| format!("{} request to {} failed: {}", method, endpoint, e))?
+        .into_string()?;
+
+    let response: Value = serde_json::from_str(&body)
+        .map_err(|e| format!("Invalid {} response from {}: {}", method, endpoint, e))?;
+    into_result(response, endpoint, method)
+}
+
+fn into_result(mut response: Value, endpoint: &str, method: &str) -> Result<Value, Box<dyn Error>> {
+    if let Some(error) = response.get("error") {
+        let message = error["message"].as_str().unwrap_or("unknown error");
+        // Invalid transactions only give their reason in the data, e.g. "Inability to pay some fees"
+        return Err(match error["data"].as_str() {
+            Some(data) => format!("{} failed with RPC error {}: {} ({})", method, error["code"], message, data),
+            None => format!("{} failed with RPC error {}: {}", method, error["code"], message),
+        }.into());
+    }
+    match response.get_mut("result") {
+        Some(result) => Ok(result.take()),
+        None => Err(format!("{} response from {} has no result", method, endpoint).into()),
+    }
+}
+
+// Plain calls go over HTTP unless a ws:// endpoint is given
+pub fn request(endpoint: &str, method: &str, params: Value) -> Result<Value, Box<dyn Error>> {
+    if is_ws(endpoint) {
+        WsClient::connect(endpoint)?.request(method, params)
+    } else {
+        call(endpoint, method, params)
+    }
+}
+
+pub fn methods(endpoint: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let result = request(endpoint, "rpc_methods", json!([]))?;
+    let methods = result["methods"].as_array().ok_or("rpc_methods response has no methods")?;
+    let mut methods: Vec<String> = methods.iter().filter_map(|method| method.as_str().map(String::from)).collect();
+    methods.sort();
+    Ok(methods)
+}
+
+// The node serves HTTP and WebSocket on the same port
+pub fn ws_endpoint(endpoint: &str) -> String {
+    match endpoint.strip_prefix("http://") {
+        Some(rest) => format!("ws://{}", rest),
+        None => endpoint.to_string(),
+    }
+}
+
+pub fn is_ws(endpoint: &str) -> bool {
+    endpoint.starts_with("ws://")
+}
+
+// The method that cancels a subscription, or None when the method does not subscribe
+pub fn unsubscribe_method(method: &str) -> Option<String> {
+    const SUBSCRIPTIONS: [(&str, &str); 3] = [
+        ("author_submitAndWatchExtrinsic", "author_unwatchExtrinsic"),
+        ("chainHead_v1_follow", "chainHead_v1_unfollow"),
+        ("transactionWatch_v1_submitAndWatch", "transactionWatch_v1_unwatch"),
+    ];
+    if let Some((_, unsubscribe)) = SUBSCRIPTIONS.iter().find(|(subscribe, _)| *subscribe == method) {
+        return Some(unsubscribe.to_string());
+    }
+    method.contains("_subscribe").then(|| method.replacen("_subscribe", "_unsubscribe", 1))
+}
+
+// Command line parameters are taken as JSON when they parse as JSON, and as strings otherwise
+pub fn parse_params<S: AsRef<str>>(params: &[S]) -> Value {
+    Value::Array(params.iter().map(|param| parse_param(param.as_ref())).collect())
+}
+
+// Integers beyond 64 bits, e.g. u128 balances, would lose precision as JSON numbers, so they are
+// kept as strings
+fn parse_param(param: &str) -> Value {
+    let digits = param.strip_prefix('-').unwrap_or(param);
+    let integer = !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit());
+    if integer && param.parse::<i64>().is_err() && param.parse::<u64>().is_err() {
+        return json!(param);
+    }
+    serde_json::from_str(param).unwrap_or_else(|_| json!(param))
+}
+
+// A JSON-RPC 2.0 connection over WebSocket, needed for subscriptions
+pub struct WsClient {
+    endpoint: String,
+    socket: WebSocket<TcpStream>,
+    next_id: u64,
+    // Notifications that arrived while waiting for a response
+    pending: VecDeque<Value>,
+}
+
+impl WsClient {
+    pub fn connect(endpoint: &str) -> Result<WsClient, Box<dyn Error>> {
+        let request = endpoint.into_client_request().map_err(|e| format!("Invalid endpoint {}: {}", endpoint, e))?;
+        let host = request.uri().host().ok_or_else(|| format!("Endpoint {} has no host", endpoint))?.to_string();
+        let port = request.uri().port_u16().unwrap_or(80);
+        let stream = TcpStream::connect((host.as_str(), port)).map_err(|e| format!("Failed to connect to {}: {}", endpoint, e))?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        let (socket, _) = tungstenite::client(request, stream).map_err(|e| format!("WebSocket handshake with {} failed: {}", endpoint, e))?;
+        Ok(WsClient { endpoint: endpoint.to_string(), socket, next_id: 1, pending: VecDeque::new() })
+    }
+
+    pub fn request(&mut self, method: &str, params: Value) -> Result<Value, Box<dyn Error>> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        self.socket.send(Message::Text(request.to_string())).map_err(|e| format!("{} request to {} failed: {}", method, self.endpoint, e))?;
+
+        self.socket.get_ref().set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        loop {
+            let message = self.read(method)?;
+            if message["id"] == json!(id) {
+                return into_result(message, &self.endpoint, method);
+            }
+            if message.get("params").is_some() {
+                self.pending.push_back(message);
+            }
+        }
+    }
+
+    // Starts a subscription and returns its id, to tell its notifications apart
+    pub fn start_subscription(&mut self, method: &str, params: Value) -> Result<Value, Box<dyn Error>> {
+        if unsubscribe_method(method).is_none() {
+            return Err(format!("{} is not a subscription", method).into());
+        }
+        self.request(method, params)
+    }
+
+    // Waits for the next notification of any subscription, returning its subscription id and result
+    pub fn next_notification(&mut self) -> Result<(Value, Value), Box<dyn Error>> {
+        self.wait_for_notification(None)
+    }
+
+    // Like next_notification, but fails once the deadline has passed
+    pub fn next_notification_until(&mut self, deadline: Instant) -> Result<(Value, Value), Box<dyn Error>> {
+        self.wait_for_notification(Some(deadline))
+    }
+
+    fn wait_for_notification(&mut self, deadline: Option<Instant>) -> Result<(Value, Value), Box<dyn Error>> {
+        let timed_out = |endpoint: &str| format!("Timed out waiting for a notification from {}", endpoint);
+        let mut notification = match self.pending.pop_front() {
+            Some(notification) => notification,
+            // Notifications come whenever the node has something to report
+            None => loop {
+                let timeout = match deadline.map(|deadline| deadline.saturating_duration_since(Instant::now())) {
+                    Some(left) if left.is_zero() => return Err(timed_out(&self.endpoint).into()),
+                    timeout => timeout,
+                };
+                self.socket.get_ref().set_read_timeout(timeout)?;
+                match self.read("subscription") {
+                    Ok(message) if message.get("params").is_some() => break message,
+                    Ok(_) => {}
+                    Err(_) if deadline.is_some_and(|deadline| Instant::now() >= deadline) => return Err(timed_out(&self.endpoint).into()),
+                    Err(e) => return Err(e),
+                }
+            },
+        };
+        let mut params = notification["params"].take();
+        Ok((params["subscription"].take(), params["result"].take()))
+    }
+
+    // Calls `on_item` with every notification until it returns false, then unsubscribes
+    pub fn subscribe<F: FnMut(Value) -> bool>(&mut self, method: &str, params: Value, mut on_item: F) -> Result<(), Box<dyn Error>> {
+        let subscription = self.start_subscription(method, params)?;
+        loop {
+            let (id, item) = self.next_notification()?;
+            if id == subscription && !on_item(item) {
+                break;
+            }
+        }
+        let unsubscribe = unsubscribe_method(method).expect("checked when subscribing");
+        self.request(&unsubscribe, json!([subscription]))?;
+        Ok(())
+    }
+
+    fn read(&mut self, method: &str) -> Result<Value, Box<dyn Error>> {
+        loop {
+            let text = match self.socket.read().map_err(|e| format!("{} on {} failed: {}", method, self.endpoint, e))? {
+                Message::Text(text) => text,
+                Message::Close(_) => return Err(format!("{} closed the connection", self.endpoint).into()),
+                _ => continue,
+            };
+            return Ok(serde_json::from_str(&text).map_err(|e| format!("Invalid {} message from {}: {}", method, self.endpoint, e))?);
+        }
+    }
+}
+
+pub fn system_health(endpoint: &str) -> Result<Health, Box<dyn Error>> {
+    let health = call(endpoint, "system_health", json!([]))?;
+    Ok(Health {
+        peers: health["peers"].as_u64().ok_or("system_health response has no peers")?,
+        is_syncing: health["isSyncing"].as_bool().unwrap_or(false),
+        should_have_peers: health["shouldHavePeers"].as_bool().unwrap_or(false),
+    })
+}
+
+pub fn best_block_number(endpoint: &str) -> Result<u64, Box<dyn Error>> {
+    let header = call(endpoint, "chain_getHeader", json!([]))?;
+    let number = header["number"].as_str().ok_or("chain_getHeader response has no block number")?;
+    parse_hex_number(number)
+}
+
+pub fn parse_hex_number(value: &str) -> Result<u64, Box<dyn Error>> {
+    let digits = value.strip_prefix("0x").ok_or_else(|| format!("Expected a 0x-prefixed number, got {}", value))?;
+    Ok(u64::from_str_radix(digits, 16).map_err(|e| format!("Invalid number {}: {}", value, e))?)
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ws_node;
+    use mockito::{mock, Matcher};
+
+    #[test]
+    fn test_call_returns_result() {
+        let _m = mock("POST", "/rpc-call")
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::PartialJson(json!({ "jsonrpc": "2.0", "method": "system_name" })))
+            .with_body(r#"{"jsonrpc":"2.0","id":1,"result":"Polkadot Omni Node"}"#)
+            .create();
+
+        let endpoint = format!("{}/rpc-call", mockito::server_url());
+        assert_eq!(call(&endpoint, "system_name", json!([])).unwrap(), json!("Polkadot Omni Node"));
+    }
+
+    #[test]
+    fn test_call_reports_rpc_error() {
+        let _m = mock("POST", "/rpc-error")
+            .with_body(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"Method not found"}}"#)
+            .create();
+
+        let endpoint = format!("{}/rpc-error", mockito::server_url());
+        let result = call(&endpoint, "chain_nope", json!([]));
+        assert_eq!(result.unwrap_err().to_string(), "chain_nope failed with RPC error -32601: Method not found");
+    }
+
+    #[test]
+    fn test_health_and_header() {
+        let _health = mock("POST", "/rpc-health")
+            .match_body(Matcher::PartialJson(json!({ "method": "system_health" })))
+            .with_body(r#"{"jsonrpc":"2.0","id":1,"result":{"peers":3,"isSyncing":true,"shouldHavePeers":true}}"#)
+            .create();
+        let _header = mock("POST", "/rpc-health")
+            .match_body(Matcher::PartialJson(json!({ "method": "chain_getHeader" })))
+            .with_body(r#"{"jsonrpc":"2.0","id":1,"result":{"number":"0x1a","parentHash":"0x00"}}"#)
+            .create();
+
+        let endpoint = format!("{}/rpc-health", mockito::server_url());
+        assert_eq!(system_health(&endpoint).unwrap(), Health { peers: 3, is_syncing: true, should_have_peers: true });
+        assert_eq!(best_block_number(&endpoint).unwrap(), 26);
+    }
+
+    #[test]
+    fn test_call_unreachable_endpoint() {
+        let result = call("http://127.0.0.1:1", "system_health", json!([]));
+        assert!(result.unwrap_err().to_string().starts_with("system_health request to http://127.0.0.1:1 failed"));
+    }
+
+    #[test]
+    fn test_ws_request_skips_notifications() {
+        let (endpoint, node) = ws_node(|request| vec![
+            json!({ "jsonrpc": "2.0", "method": "chain_newHead", "params": { "subscription": "other", "result": {} } }),
+            json!({ "jsonrpc": "2.0", "id": request["id"], "result": "Polkadot Omni Node" }),
+        ]);
+        assert_eq!(request(&endpoint, "system_name", json!([])).unwrap(), json!("Polkadot Omni Node"));
+        assert_eq!(node.join().unwrap(), ["system_name"]);
+    }
+
+    #[test]
+    fn test_ws_subscribe() {
+        let (endpoint, node) = ws_node(|request| match request["method"].as_str().unwrap() {
+            "chain_subscribeNewHeads" => {
+                let heads = [("other", "0x1"), ("heads", "0x2"), ("heads", "0x3"), ("heads", "0x4")]
+                    .map(|(subscription, number)| json!({ "jsonrpc": "2.0", "method": "chain_newHead", "params": { "subscription": subscription, "result": { "number": number } } }));
+                std::iter::once(json!({ "jsonrpc": "2.0", "id": request["id"], "result": "heads" })).chain(heads).collect()
+            }
+            _ => vec![json!({ "jsonrpc": "2.0", "id": request["id"], "result": true })],
+        });
+
+        let mut client = WsClient::connect(&endpoint).unwrap();
+        let mut numbers = Vec::new();
+        client.subscribe("chain_subscribeNewHeads", json!([]), |head| {
+            numbers.push(head["number"].as_str().unwrap().to_string());
+            numbers.len() < 2
+        }).unwrap();
+        drop(client);
+
+        assert_eq!(numbers, ["0x2", "0x3"]);
+        assert_eq!(node.join().unwrap(), ["chain_subscribeNewHeads", "chain_unsubscribeNewHeads"]);
+    }
+
+    #[test]
+    fn test_ws_notification_deadline() {
+        let (endpoint, _) = ws_node(|request| vec![json!({ "jsonrpc": "2.0", "id": request["id"], "result": "heads" })]);
+        let mut client = WsClient::connect(&endpoint).unwrap();
+        client.start_subscription("chain_subscribeNewHeads", json!([])).unwrap();
+        let error = client.next_notification_until(Instant::now() + Duration::from_millis(200)).unwrap_err();
+        assert_eq!(error.to_string(), format!("Timed out waiting for a notification from {}", endpoint));
+    }
+
+    #[test]
+    fn test_unsubscribe_method() {
+        assert_eq!(unsubscribe_method("chain_subscribeNewHeads").as_deref(), Some("chain_unsubscribeNewHeads"));
+        assert_eq!(unsubscribe_method("state_subscribeStorage").as_deref(), Some("state_unsubscribeStorage"));
+        assert_eq!(unsubscribe_method("author_submitAndWatchExtrinsic").as_deref(), Some("author_unwatchExtrinsic"));
+        assert_eq!(unsubscribe_method("chain_getHeader"), None);
+    }
+
+    #[test]
+    fn test_parse_params() {
+        assert_eq!(parse_params(&["0", "0xabcd", "true", "[1,2]", "alice"]), json!([0, "0xabcd", true, [1, 2], "alice"]));
+        assert_eq!(parse_params::<&str>(&[]), json!([]));
+        assert_eq!(parse_params(&["18446744073709551615", "18446744073709551616", "-9223372036854775809"]), json!([u64::MAX, "18446744073709551616", "-9223372036854775809"]));
+        assert_eq!(ws_endpoint("http://127.0.0.1:9944"), "ws://127.0.0.1:9944");
+    }
+
+    #[test]
+    fn test_parse_hex_number() {
+        assert_eq!(parse_hex_number("0x0").unwrap(), 0);
+        assert_eq!(parse_hex_number("0xff").unwrap(), 255);
+        assert!(parse_hex_number("255").is_err());
+    }
+}
@@ -0,0 +1,237 @@
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use parity_scale_codec::Decode;
+use serde_json::{json, Value};
+use crate::hashing::{blake2_256, blake2_64, to_hex};
+
+// Magic prefix of a zstd compressed runtime blob (sp-maybe-compressed-blob)
+const ZSTD_PREFIX: [u8; 8] = [82, 188, 83, 118, 70, 219, 142, 5];
+const CODE_BLOB_BOMB_LIMIT: u64 = 50 * 1024 * 1024;
+const WASM_MAGIC: [u8; 4] = [0x00, 0x61, 0x73, 0x6d];
+
+const KNOWN_APIS: [&str; 24] = [
+    "Core", "Metadata", "BlockBuilder", "TaggedTransactionQueue", "OffchainWorkerApi",
+    "AuraApi", "SessionKeys", "AccountNonceApi", "TransactionPaymentApi", "TransactionPaymentCallApi",
+    "CollectCollationInfo", "GenesisBuilder", "XcmPaymentApi", "DryRunApi", "LocationToAccountApi",
+    "AuraUnincludedSegmentApi", "FungiblesApi", "AssetConversionApi", "GetParachainInfo", "TrustedQueryApi",
+    "BabeApi", "GrandpaApi", "ParachainHost", "BeefyApi",
+];
+
+#[derive(Debug, Decode)]
+struct EncodedRuntimeVersion {
+    spec_name: String,
+    impl_name: String,
+    authoring_version: u32,
+    spec_version: u32,
+    impl_version: u32,
+    apis: Vec<([u8; 8], u32)>,
+    transaction_version: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RuntimeInfo {
+    pub spec_name: String,
+    pub impl_name: String,
+    pub authoring_version: u32,
+    pub spec_version: u32,
+    pub impl_version: u32,
+    pub transaction_version: u32,
+    pub apis: Vec<(String, u32)>,
+    pub code_hash: [u8; 32],
+    pub compressed_size: Option<usize>,
+    pub size: usize,
+}
+
+pub fn inspect_wasm(path: &Path) -> Result<RuntimeInfo, Box<dyn Error>> {
+    let blob = fs::read(path).map_err(|e| format!("Failed to read runtime {:?}: {}", path, e))?;
+    inspect_blob(&blob)
+}
+
+pub fn inspect_blob(blob: &[u8]) -> Result<RuntimeInfo, Box<dyn Error>> {
+    let code = decompress(blob)?;
+    let section = custom_section(&code, "runtime_version")?
+        .ok_or("Runtime has no `runtime_version` custom section")?;
+    let version = EncodedRuntimeVersion::decode(&mut &section[..])
+        .map_err(|e| format!("Failed to decode runtime_version section: {}", e))?;
+
+    Ok(RuntimeInfo {
+        spec_name: version.spec_name,
+        impl_name: version.impl_name,
+        authoring_version: version.authoring_version,
+        spec_version: version.spec_version,
+        impl_version: version.impl_version,
+        transaction_version: version.transaction_version,
+        apis: version.apis.iter().map(|(id, v)| (api_name(id), *v)).collect(),
+        code_hash: blake2_256(blob),
+        compressed_size: if code.len() != blob.len() { Some(blob.len()) } else { None },
+        size: code.len(),
+    })
+}
+
+pub fn decompress(blob: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    match blob.strip_prefix(&ZSTD_PREFIX[..]) {
+        Some(compressed) => {
+            let mut code = Vec::new();
+            zstd::stream::Decoder::new(compressed)?
+                .take(CODE_BLOB_BOMB_LIMIT + 1)
+                .read_to_end(&mut code)
+                .map_err(|e| format!("Failed to decompress runtime: {}", e))?;
+            if code.len() as u64 > CODE_BLOB_BOMB_LIMIT {
+                return Err("Decompressed runtime exceeds the 50 MiB limit".into());
+            }
+            Ok(code)
+        }
+        None => Ok(blob.to_vec()),
+    }
+}
+
+pub fn custom_section<'a>(code: &'a [u8], name: &str) -> Result<Option<&'a [u8]>, Box<dyn Error>> {
+    if code.len() < 8 || code[..4] != WASM_MAGIC {
+        return Err("Not a wasm module".into());
+    }
+
+    let mut cursor = &code[8..];
+    while !cursor.is_empty() {
+        let id = cursor[0];
+        cursor = &cursor[1..];
+        let size = read_leb128(&mut cursor)? as usize;
+        if size > cursor.len() {
+            return Err("Truncated wasm section".into());
+        }
+        let (mut section, rest) = cursor.split_at(size);
+        cursor = rest;
+
+        if id == 0 {
+            let name_len = read_leb128(&mut section)? as usize;
+            if name_len > section.len() {
+                return Err("Truncated wasm custom section name".into());
+            }
+            if &section[..name_len] == name.as_bytes() {
+                return Ok(Some(&section[name_len..]));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn read_leb128(cursor: &mut &[u8]) -> Result<u64, Box<dyn Error>> {
+    let mut result = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = cursor.split_first().ok_or("Truncated LEB128 value")?;
+        *cursor = rest;
+        result |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+    }
+    Err("LEB128 value too long".into())
+}
+
+fn api_name(id: &[u8; 8]) -> String {
+    KNOWN_APIS
+        .iter()
+        .find(|name| blake2_64(name.as_bytes()) == *id)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| to_hex(id))
+}
+
+impl RuntimeInfo {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "spec_name": self.spec_name,
+            "impl_name": self.impl_name,
+            "authoring_version": self.authoring_version,
+            "spec_version": self.spec_version,
+            "impl_version": self.impl_version,
+            "transaction_version": self.transaction_version,
+            "apis": self.apis.iter().map(|(name, version)| json!([name, version])).collect::<Vec<_>>(),
+            "code_hash": to_hex(&self.code_hash),
+            "compressed_size": self.compressed_size,
+            "size": self.size,
+        })
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut rows = vec![
+            ("spec_name", self.spec_name.clone()),
+            ("impl_name", self.impl_name.clone()),
+            ("spec_version", self.spec_version.to_string()),
+            ("impl_version", self.impl_version.to_string()),
+            ("transaction_version", self.transaction_version.to_string()),
+            ("authoring_version", self.authoring_version.to_string()),
+            ("code_hash", to_hex(&self.code_hash)),
+            ("size", format!("{} bytes", self.size)),
+        ];
+        if let Some(compressed) = self.compressed_size {
+            rows.push(("compressed_size", format!("{} bytes", compressed)));
+        }
+        rows.push(("apis", self.apis.iter().map(|(name, v)| format!("{} v{}", name, v)).collect::<Vec<_>>().join(", ")));
+
+        rows.iter()
+            .map(|(key, value)| format!("{:<20} {}", key, value))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::mock_runtime;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_inspect_uncompressed_runtime() {
+        let wasm = mock_runtime(1_017_001);
+        let info = inspect_blob(&wasm).unwrap();
+
+        assert_eq!(info.spec_name, "asset-hub-westend");
+        assert_eq!(info.spec_version, 1_017_001);
+        assert_eq!(info.transaction_version, 16);
+        assert_eq!(info.apis, vec![("Core".to_string(), 5), ("0x0101010101010101".to_string(), 1)]);
+        assert_eq!(info.code_hash, blake2_256(&wasm));
+        assert_eq!(info.compressed_size, None);
+    }
+
+    #[test]
+    fn test_inspect_compressed_runtime() {
+        let wasm = mock_runtime(42);
+        let mut blob = ZSTD_PREFIX.to_vec();
+        blob.extend(zstd::encode_all(&wasm[..], 3).unwrap());
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("runtime.compact.compressed.wasm");
+        fs::write(&path, &blob).unwrap();
+
+        let info = inspect_wasm(&path).unwrap();
+        assert_eq!(info.spec_version, 42);
+        assert_eq!(info.size, wasm.len());
+        assert_eq!(info.compressed_size, Some(blob.len()));
+        assert_eq!(info.code_hash, blake2_256(&blob));
+        assert_eq!(info.to_json()["apis"][0], json!(["Core", 5]));
+    }
+
+    #[test]
+    fn test_inspect_not_wasm() {
+        let result = inspect_blob(b"definitely not wasm");
+        assert_eq!(result.unwrap_err().to_string(), "Not a wasm module");
+    }
+
+    #[test]
+    fn test_inspect_missing_section() {
+        let wasm = [&WASM_MAGIC[..], &[1, 0, 0, 0]].concat();
+        let result = inspect_blob(&wasm);
+        assert_eq!(result.unwrap_err().to_string(), "Runtime has no `runtime_version` custom section");
+    }
+
+    #[test]
+    fn test_core_api_id() {
+        assert_eq!(to_hex(&blake2_64(b"Core")), "0xdf6acb689907609b");
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+
+const POLKADOT_SDK_RELEASE: &str = "https://github.com/paritytech/polkadot-sdk/releases/download/polkadot-stable2412";
+const PASEO_RELEASE: &str = "https://github.com/paseo-network/runtimes/releases/download/v1.3.4";
+const PASEO_VERSION: &str = "v1003004";
+
+pub const DEFAULT_RUNTIME: &str = "asset-hub-westend";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    PolkadotSdk,
+    Paseo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RuntimeSpec {
+    pub name: &'static str,
+    pub relay_chain: &'static str,
+    pub para_id: u32,
+    pub source: Source,
+}
+
+// Collectives only runs on westend: polkadot-sdk releases no collectives-rococo runtime and Paseo
+// has no collectives parachain, see UNRELEASED_RUNTIMES
+pub const RUNTIMES: [RuntimeSpec; 13] = [
+    RuntimeSpec { name: "asset-hub-westend", relay_chain: "westend", para_id: 1000, source: Source::PolkadotSdk },
+    RuntimeSpec { name: "collectives-westend", relay_chain: "westend", para_id: 1001, source: Source::PolkadotSdk },
+    RuntimeSpec { name: "bridge-hub-westend", relay_chain: "westend", para_id: 1002, source: Source::PolkadotSdk },
+    RuntimeSpec { name: "people-westend", relay_chain: "westend", para_id: 1004, source: Source::PolkadotSdk },
+    RuntimeSpec { name: "coretime-westend", relay_chain: "westend", para_id: 1005, source: Source::PolkadotSdk },
+    RuntimeSpec { name: "asset-hub-rococo", relay_chain: "rococo", para_id: 1000, source: Source::PolkadotSdk },
+    RuntimeSpec { name: "people-rococo", relay_chain: "rococo", para_id: 1004, source: Source::PolkadotSdk },
+    RuntimeSpec { name: "coretime-rococo", relay_chain: "rococo", para_id: 1005, source: Source::PolkadotSdk },
+    RuntimeSpec { name: "bridge-hub-rococo", relay_chain: "rococo", para_id: 1013, source: Source::PolkadotSdk },
+    RuntimeSpec { name: "asset-hub-paseo", relay_chain: "paseo", para_id: 1000, source: Source::Paseo },
+    RuntimeSpec { name: "bridge-hub-paseo", relay_chain: "paseo", para_id: 1002, source: Source::Paseo },
+    RuntimeSpec { name: "people-paseo", relay_chain: "paseo", para_id: 1004, source: Source::Paseo },
+    RuntimeSpec { name: "coretime-paseo", relay_chain: "paseo", para_id: 1005, source: Source::Paseo },
+];
+
+// System parachain runtimes that have no release to download, with the reason why
+const UNRELEASED_RUNTIMES: [(&str, &str); 2] = [
+    ("collectives-rococo", "polkadot-sdk does not release a collectives runtime for rococo"),
+    ("collectives-paseo", "Paseo has no collectives parachain"),
+];
+
+pub fn find_runtime(name: &str) -> Result<&'static RuntimeSpec, String> {
+    RUNTIMES.iter().find(|runtime| runtime.name == name).ok_or_else(|| {
+        let available: Vec<&str> = RUNTIMES.iter().map(|runtime| runtime.name).collect();
+        let reason = match UNRELEASED_RUNTIMES.iter().find(|(unreleased, _)| *unreleased == name) {
+            Some((_, reason)) => format!(" ({})", reason),
+            None => String::new(),
+        };
+        format!("Unknown runtime {}{}. Available runtimes: {}", name, reason, available.join(", "))
+    })
+}
+
+pub fn default_runtime() -> &'static RuntimeSpec {
+    find_runtime(DEFAULT_RUNTIME).expect("default runtime is in the catalog")
+}
+
+impl RuntimeSpec {
+    // Release asset name, e.g. asset_hub_westend_runtime.compact.compressed.wasm
+    pub fn file_name(&self) -> String {
+        match self.source {
+            Source::PolkadotSdk => format!("{}_runtime.compact.compressed.wasm", self.name.replace('-', "_")),
+            Source::Paseo => format!("{}_runtime-{}.compact.compressed.wasm", self.name, PASEO_VERSION),
+        }
+    }
+
+    pub fn url(&self) -> String {
+        let release = match self.source {
+            Source::PolkadotSdk => POLKADOT_SDK_RELEASE,
+            Source::Paseo => PASEO_RELEASE,
+        };
+        format!("{}/{}", release, self.file_name())
+    }
+
+    pub fn wasm_path(&self) -> PathBuf {
+        PathBuf::from("./nodes").join(self.file_name())
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_runtime_matches_previous_download() {
+        let runtime = default_runtime();
+        assert_eq!(runtime.relay_chain, "westend");
+        assert_eq!(runtime.para_id, 1000);
+        assert_eq!(runtime.wasm_path(), PathBuf::from("./nodes/asset_hub_westend_runtime.compact.compressed.wasm"));
+        assert_eq!(
+            runtime.url(),
+            "https://github.com/paritytech/polkadot-sdk/releases/download/polkadot-stable2412/asset_hub_westend_runtime.compact.compressed.wasm"
+        );
+    }
+
+    #[test]
+    fn test_paseo_runtime_url() {
+        let runtime = find_runtime("people-paseo").unwrap();
+        assert_eq!(runtime.para_id, 1004);
+        assert_eq!(
+            runtime.url(),
+            "https://github.com/paseo-network/runtimes/releases/download/v1.3.4/people-paseo_runtime-v1003004.compact.compressed.wasm"
+        );
+    }
+
+    #[test]
+    fn test_find_runtime_unknown() {
+        let result = find_runtime("asset-hub-kusama");
+        assert!(result.unwrap_err().starts_with("Unknown runtime asset-hub-kusama. Available runtimes: asset-hub-westend, "));
+
+        let result = find_runtime("collectives-paseo");
+        assert!(result.unwrap_err().starts_with("Unknown runtime collectives-paseo (Paseo has no collectives parachain). Available runtimes: "));
+    }
+
+    #[test]
+    fn test_runtime_names_are_unique() {
+        for runtime in RUNTIMES.iter() {
+            assert_eq!(RUNTIMES.iter().filter(|other| other.name == runtime.name).count(), 1, "{} is listed twice", runtime.name);
+        }
+    }
+}
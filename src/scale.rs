@@ -0,0 +1,416 @@
+use parity_scale_codec::{Compact, Decode, Encode};
+use scale_info::form::PortableForm;
+use scale_info::{Field, PortableRegistry, TypeDef, TypeDefPrimitive};
+use serde_json::{json, Map, Value};
+use crate::hashing::{from_hex, to_hex};
+use crate::ss58;
+
+// Decodes a SCALE encoded value of a metadata type into JSON. Byte sequences become hex strings,
+// account ids SS58 addresses, Option its value or null, and enums {"Variant": fields} or "Variant"
+pub fn decode_value(types: &PortableRegistry, type_id: u32, input: &mut &[u8]) -> Result<Value, String> {
+    let ty = types.resolve(type_id).ok_or_else(|| format!("Unknown type {}", type_id))?;
+    let path = ty.path.segments.join("::");
+
+    match &ty.type_def {
+        TypeDef::Composite(_) if path.ends_with("AccountId32") => {
+            let account: [u8; 32] = take(input, 32, &path)?.try_into().expect("took 32 bytes");
+            Ok(json!(ss58::encode(ss58::GENERIC_PREFIX, &account)))
+        }
+        TypeDef::Composite(composite) => decode_fields(types, &composite.fields, input),
+        TypeDef::Variant(variant) => {
+            let index = take(input, 1, &path)?[0];
+            let chosen = variant.variants.iter().find(|v| v.index == index)
+                .ok_or_else(|| format!("Invalid variant index {} for {}", index, path))?;
+            if path == "Option" {
+                return match chosen.fields.first() {
+                    Some(field) => decode_value(types, field.ty.id, input),
+                    None => Ok(Value::Null),
+                };
+            }
+            if chosen.fields.is_empty() {
+                return Ok(json!(chosen.name));
+            }
+            Ok(json!({ chosen.name.clone(): decode_fields(types, &chosen.fields, input)? }))
+        }
+        TypeDef::Sequence(sequence) => {
+            let len = Compact::<u32>::decode(input).map_err(|e| format!("Invalid length of {}: {}", describe(&path), e))?.0 as usize;
+            decode_items(types, sequence.type_param.id, len, input)
+        }
+        TypeDef::Array(array) => decode_items(types, array.type_param.id, array.len as usize, input),
+        TypeDef::Tuple(tuple) if tuple.fields.is_empty() => Ok(Value::Null),
+        TypeDef::Tuple(tuple) => tuple.fields.iter().map(|field| decode_value(types, field.id, input)).collect::<Result<_, _>>().map(Value::Array),
+        TypeDef::Primitive(primitive) => decode_primitive(primitive, input),
+        TypeDef::Compact(_) => {
+            let value = Compact::<u128>::decode(input).map_err(|e| format!("Invalid compact value: {}", e))?.0;
+            Ok(number(value))
+        }
+        TypeDef::BitSequence(_) => {
+            // Shown as the raw bits, assuming the u8 store with Lsb0 order the runtimes use
+            let bits = Compact::<u32>::decode(input).map_err(|e| format!("Invalid bit sequence: {}", e))?.0 as usize;
+            let bytes = take(input, bits.div_ceil(8), "bit sequence")?;
+            Ok(json!((0..bits).map(|bit| if bytes[bit / 8] >> (bit % 8) & 1 == 1 { '1' } else { '0' }).collect::<String>()))
+        }
+    }
+}
+
+// A readable name for a metadata type, e.g. `Vec<AccountId32>` or `AccountInfo<u32, AccountData<u128>>`
+pub fn type_name(types: &PortableRegistry, type_id: u32) -> String {
+    let Some(ty) = types.resolve(type_id) else {
+        return format!("<unknown type {}>", type_id);
+    };
+    let list = |ids: Vec<u32>| ids.into_iter().map(|id| type_name(types, id)).collect::<Vec<_>>().join(", ");
+    match &ty.type_def {
+        TypeDef::Sequence(sequence) => format!("Vec<{}>", type_name(types, sequence.type_param.id)),
+        TypeDef::Array(array) => format!("[{}; {}]", type_name(types, array.type_param.id), array.len),
+        TypeDef::Tuple(tuple) => format!("({})", list(tuple.fields.iter().map(|field| field.id).collect())),
+        TypeDef::Primitive(primitive) => format!("{:?}", primitive).to_lowercase(),
+        TypeDef::Compact(compact) => format!("Compact<{}>", type_name(types, compact.type_param.id)),
+        TypeDef::BitSequence(_) => "BitVec".to_string(),
+        TypeDef::Composite(_) | TypeDef::Variant(_) => {
+            let name = ty.path.segments.last().cloned().unwrap_or_else(|| format!("<type {}>", type_id));
+            let params: Vec<u32> = ty.type_params.iter().filter_map(|param| param.ty.map(|ty| ty.id)).collect();
+            if params.is_empty() { name } else { format!("{}<{}>", name, list(params)) }
+        }
+    }
+}
+
+// Encodes JSON as a value of a metadata type, accepting what `decode_value` returns. Account ids may
+// also be given as hex, byte sequences as plain text and integers as decimal strings
+pub fn encode_value(types: &PortableRegistry, type_id: u32, value: &Value, out: &mut Vec<u8>) -> Result<(), String> {
+    let ty = types.resolve(type_id).ok_or_else(|| format!("Unknown type {}", type_id))?;
+    let path = ty.path.segments.join("::");
+    let expected = || format!("Expected {} but got {}", type_name(types, type_id), value);
+
+    match &ty.type_def {
+        TypeDef::Composite(_) if path.ends_with("AccountId32") => {
+            let text = value.as_str().ok_or_else(expected)?;
+            let account = match ss58::decode(text) {
+                Ok((_, account)) => account,
+                Err(_) => from_hex(text).ok().and_then(|bytes| bytes.try_into().ok()).ok_or_else(|| format!("Invalid account {}", text))?,
+            };
+            out.extend(account);
+            Ok(())
+        }
+        TypeDef::Composite(composite) => encode_fields(types, &composite.fields, value, out),
+        TypeDef::Variant(variant) => {
+            if path == "Option" {
+                let some = variant.variants.iter().find(|v| v.name == "Some").ok_or_else(expected)?;
+                return match (value, some.fields.first()) {
+                    (Value::Null, _) => {
+                        out.push(0);
+                        Ok(())
+                    }
+                    (value, Some(field)) => {
+                        out.push(1);
+                        encode_value(types, field.ty.id, value, out)
+                    }
+                    (_, None) => Err(expected()),
+                };
+            }
+            let (name, fields) = match value {
+                // A MultiAddress may also be given as just the account
+                Value::String(account) if path.ends_with("MultiAddress") && !variant.variants.iter().any(|v| v.name == *account) => ("Id", value),
+                Value::String(name) => (name.as_str(), &Value::Null),
+                Value::Object(object) if object.len() == 1 => object.iter().next().map(|(name, fields)| (name.as_str(), fields)).expect("one entry"),
+                _ => return Err(expected()),
+            };
+            let chosen = variant.variants.iter().find(|v| v.name == *name)
+                .ok_or_else(|| format!("{} has no variant {}", type_name(types, type_id), name))?;
+            out.push(chosen.index);
+            encode_fields(types, &chosen.fields, fields, out)
+        }
+        TypeDef::Sequence(sequence) => {
+            if let (Some(text), true) = (value.as_str(), is_byte(types, sequence.type_param.id)) {
+                let bytes = if text.starts_with("0x") { from_hex(text)? } else { text.as_bytes().to_vec() };
+                Compact(bytes.len() as u32).encode_to(out);
+                out.extend(bytes);
+                return Ok(());
+            }
+            let items = value.as_array().ok_or_else(expected)?;
+            Compact(items.len() as u32).encode_to(out);
+            items.iter().try_for_each(|item| encode_value(types, sequence.type_param.id, item, out))
+        }
+        TypeDef::Array(array) => {
+            if let (Some(text), true) = (value.as_str(), is_byte(types, array.type_param.id)) {
+                let bytes = from_hex(text)?;
+                if bytes.len() != array.len as usize {
+                    return Err(format!("Expected {} bytes but got {}", array.len, bytes.len()));
+                }
+                out.extend(bytes);
+                return Ok(());
+            }
+            let items = value.as_array().filter(|items| items.len() == array.len as usize).ok_or_else(expected)?;
+            items.iter().try_for_each(|item| encode_value(types, array.type_param.id, item, out))
+        }
+        TypeDef::Tuple(tuple) => match (tuple.fields.as_slice(), value) {
+            ([], Value::Null) => Ok(()),
+            ([field], value) if !value.is_array() => encode_value(types, field.id, value, out),
+            (fields, Value::Array(items)) if items.len() == fields.len() => {
+                fields.iter().zip(items).try_for_each(|(field, item)| encode_value(types, field.id, item, out))
+            }
+            _ => Err(expected()),
+        },
+        TypeDef::Primitive(primitive) => encode_primitive(primitive, value, out).ok_or_else(expected),
+        TypeDef::Compact(_) => {
+            Compact(unsigned(value).ok_or_else(expected)?).encode_to(out);
+            Ok(())
+        }
+        TypeDef::BitSequence(_) => {
+            let bits = value.as_str().filter(|bits| bits.chars().all(|c| c == '0' || c == '1')).ok_or_else(expected)?;
+            let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+            bits.chars().enumerate().filter(|(_, c)| *c == '1').for_each(|(bit, _)| bytes[bit / 8] |= 1 << (bit % 8));
+            Compact(bits.len() as u32).encode_to(out);
+            out.extend(bytes);
+            Ok(())
+        }
+    }
+}
+
+fn encode_fields(types: &PortableRegistry, fields: &[Field<PortableForm>], value: &Value, out: &mut Vec<u8>) -> Result<(), String> {
+    match fields {
+        [] if value.is_null() => Ok(()),
+        [] => Err(format!("Expected no fields but got {}", value)),
+        [field] if field.name.is_none() => encode_value(types, field.ty.id, value, out),
+        _ if fields.iter().all(|field| field.name.is_some()) => {
+            let object = value.as_object().ok_or_else(|| format!("Expected an object with {} but got {}", field_names(fields), value))?;
+            for field in fields {
+                let name = field.name.as_ref().expect("named field");
+                let field_value = object.get(name).ok_or_else(|| format!("Missing field {} in {}", name, value))?;
+                encode_value(types, field.ty.id, field_value, out)?;
+            }
+            Ok(())
+        }
+        _ => {
+            let items = value.as_array().filter(|items| items.len() == fields.len())
+                .ok_or_else(|| format!("Expected a list of {} values but got {}", fields.len(), value))?;
+            fields.iter().zip(items).try_for_each(|(field, item)| encode_value(types, field.ty.id, item, out))
+        }
+    }
+}
+
+fn field_names(fields: &[Field<PortableForm>]) -> String {
+    fields.iter().filter_map(|field| field.name.clone()).collect::<Vec<_>>().join(", ")
+}
+
+// None when the value does not fit the primitive
+fn encode_primitive(primitive: &TypeDefPrimitive, value: &Value, out: &mut Vec<u8>) -> Option<()> {
+    match primitive {
+        TypeDefPrimitive::Bool => value.as_bool()?.encode_to(out),
+        TypeDefPrimitive::Char => {
+            let mut chars = value.as_str()?.chars();
+            let c = chars.next().filter(|_| chars.next().is_none())?;
+            (c as u32).encode_to(out)
+        }
+        TypeDefPrimitive::Str => value.as_str()?.encode_to(out),
+        TypeDefPrimitive::U8 => u8::try_from(unsigned(value)?).ok()?.encode_to(out),
+        TypeDefPrimitive::U16 => u16::try_from(unsigned(value)?).ok()?.encode_to(out),
+        TypeDefPrimitive::U32 => u32::try_from(unsigned(value)?).ok()?.encode_to(out),
+        TypeDefPrimitive::U64 => u64::try_from(unsigned(value)?).ok()?.encode_to(out),
+        TypeDefPrimitive::U128 => unsigned(value)?.encode_to(out),
+        TypeDefPrimitive::I8 => i8::try_from(signed(value)?).ok()?.encode_to(out),
+        TypeDefPrimitive::I16 => i16::try_from(signed(value)?).ok()?.encode_to(out),
+        TypeDefPrimitive::I32 => i32::try_from(signed(value)?).ok()?.encode_to(out),
+        TypeDefPrimitive::I64 => i64::try_from(signed(value)?).ok()?.encode_to(out),
+        TypeDefPrimitive::I128 => signed(value)?.encode_to(out),
+        TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => {
+            let bytes = from_hex(value.as_str()?).ok().filter(|bytes| bytes.len() == 32)?;
+            out.extend(bytes)
+        }
+    }
+    Some(())
+}
+
+fn unsigned(value: &Value) -> Option<u128> {
+    match value {
+        Value::Number(number) => number.as_u64().map(u128::from),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+fn signed(value: &Value) -> Option<i128> {
+    match value {
+        Value::Number(number) => number.as_i64().map(i128::from),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+fn is_byte(types: &PortableRegistry, type_id: u32) -> bool {
+    matches!(types.resolve(type_id).map(|ty| &ty.type_def), Some(TypeDef::Primitive(TypeDefPrimitive::U8)))
+}
+
+fn decode_fields(types: &PortableRegistry, fields: &[Field<PortableForm>], input: &mut &[u8]) -> Result<Value, String> {
+    match fields {
+        [] => Ok(Value::Null),
+        // Newtypes such as `struct Balance(u128)` show just their value
+        [field] if field.name.is_none() => decode_value(types, field.ty.id, input),
+        _ if fields.iter().all(|field| field.name.is_some()) => {
+            let mut object = Map::new();
+            for field in fields {
+                object.insert(field.name.clone().expect("named field"), decode_value(types, field.ty.id, input)?);
+            }
+            Ok(Value::Object(object))
+        }
+        _ => fields.iter().map(|field| decode_value(types, field.ty.id, input)).collect::<Result<_, _>>().map(Value::Array),
+    }
+}
+
+fn decode_items(types: &PortableRegistry, item_type: u32, len: usize, input: &mut &[u8]) -> Result<Value, String> {
+    if is_byte(types, item_type) {
+        return Ok(json!(to_hex(take(input, len, "byte sequence")?)));
+    }
+    (0..len).map(|_| decode_value(types, item_type, input)).collect::<Result<_, _>>().map(Value::Array)
+}
+
+fn decode_primitive(primitive: &TypeDefPrimitive, input: &mut &[u8]) -> Result<Value, String> {
+    let error = |e: parity_scale_codec::Error| format!("Invalid {:?} value: {}", primitive, e);
+    Ok(match primitive {
+        TypeDefPrimitive::Bool => json!(bool::decode(input).map_err(error)?),
+        TypeDefPrimitive::Char => json!(char::from_u32(u32::decode(input).map_err(error)?).ok_or("Invalid char value")?),
+        TypeDefPrimitive::Str => json!(String::decode(input).map_err(error)?),
+        TypeDefPrimitive::U8 => json!(u8::decode(input).map_err(error)?),
+        TypeDefPrimitive::U16 => json!(u16::decode(input).map_err(error)?),
+        TypeDefPrimitive::U32 => json!(u32::decode(input).map_err(error)?),
+        TypeDefPrimitive::U64 => json!(u64::decode(input).map_err(error)?),
+        TypeDefPrimitive::U128 => number(u128::decode(input).map_err(error)?),
+        TypeDefPrimitive::I8 => json!(i8::decode(input).map_err(error)?),
+        TypeDefPrimitive::I16 => json!(i16::decode(input).map_err(error)?),
+        TypeDefPrimitive::I32 => json!(i32::decode(input).map_err(error)?),
+        TypeDefPrimitive::I64 => json!(i64::decode(input).map_err(error)?),
+        TypeDefPrimitive::I128 => {
+            let value = i128::decode(input).map_err(error)?;
+            i64::try_from(value).map(|value| json!(value)).unwrap_or_else(|_| json!(value.to_string()))
+        }
+        TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => json!(to_hex(take(input, 32, "256-bit integer")?)),
+    })
+}
+
+// Balances exceed what JSON numbers hold exactly, so values past u64 are given as strings
+fn number(value: u128) -> Value {
+    u64::try_from(value).map(|value| json!(value)).unwrap_or_else(|_| json!(value.to_string()))
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize, what: &str) -> Result<&'a [u8], String> {
+    if input.len() < len {
+        return Err(format!("Not enough data for {}: {} of {} bytes left", describe(what), input.len(), len));
+    }
+    let (taken, rest) = input.split_at(len);
+    *input = rest;
+    Ok(taken)
+}
+
+fn describe(path: &str) -> &str {
+    if path.is_empty() { "value" } else { path }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scale_info::{MetaType, Registry, TypeInfo};
+
+    #[derive(Encode, TypeInfo)]
+    struct AccountId32([u8; 32]);
+
+    #[derive(Encode, TypeInfo)]
+    struct AccountData {
+        free: u128,
+        #[codec(compact)]
+        nonce: u32,
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum Event {
+        Remarked,
+        Transfer { from: AccountId32, to: AccountId32, amount: u128 },
+        Memo(Vec<u8>, Option<u32>),
+    }
+
+    fn registry<T: TypeInfo + 'static>() -> (PortableRegistry, u32) {
+        let mut registry = Registry::new();
+        let id = registry.register_type(&MetaType::new::<T>()).id;
+        (registry.into(), id)
+    }
+
+    fn decode<T: TypeInfo + Encode + 'static>(value: &T) -> Value {
+        let (types, id) = registry::<T>();
+        let encoded = value.encode();
+        let mut input = &encoded[..];
+        let decoded = decode_value(&types, id, &mut input).unwrap();
+        assert!(input.is_empty(), "{} bytes left over", input.len());
+        decoded
+    }
+
+    #[test]
+    fn test_decode_composites_and_numbers() {
+        assert_eq!(decode(&AccountData { free: 5, nonce: 7 }), json!({ "free": 5, "nonce": 7 }));
+        assert_eq!(decode(&AccountData { free: u128::MAX, nonce: 0 }), json!({ "free": u128::MAX.to_string(), "nonce": 0 }));
+        assert_eq!(decode(&(true, -3i32, "dot".to_string())), json!([true, -3, "dot"]));
+        assert_eq!(decode(&vec![1u16, 2]), json!([1, 2]));
+        assert_eq!(decode(&()), Value::Null);
+    }
+
+    #[test]
+    fn test_decode_enums() {
+        let alice = AccountId32(crate::hashing::from_hex("0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d").unwrap().try_into().unwrap());
+        let bob = AccountId32([0; 32]);
+        assert_eq!(decode(&Event::Remarked), json!("Remarked"));
+        assert_eq!(
+            decode(&Event::Transfer { from: alice, to: bob, amount: 10 }),
+            json!({ "Transfer": { "from": "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY", "to": "5C4hrfjw9DjXZTzV3MwzrrAr9P1MJhSrvWGWqi1eSuyUpnhM", "amount": 10 } })
+        );
+        assert_eq!(decode(&Event::Memo(b"hi".to_vec(), Some(3))), json!({ "Memo": ["0x6869", 3] }));
+        assert_eq!(decode(&Event::Memo(vec![], None)), json!({ "Memo": ["0x", null] }));
+    }
+
+    fn encode<T: TypeInfo + 'static>(value: Value) -> Result<Vec<u8>, String> {
+        let (types, id) = registry::<T>();
+        let mut out = Vec::new();
+        encode_value(&types, id, &value, &mut out).map(|_| out)
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let transfer = Event::Transfer { from: AccountId32([1; 32]), to: AccountId32([2; 32]), amount: u128::MAX };
+        assert_eq!(encode::<Event>(decode(&transfer)).unwrap(), transfer.encode());
+        assert_eq!(encode::<Event>(decode(&Event::Memo(b"hi".to_vec(), None))).unwrap(), Event::Memo(b"hi".to_vec(), None).encode());
+        assert_eq!(encode::<Event>(json!("Remarked")).unwrap(), Event::Remarked.encode());
+        let data = AccountData { free: 5, nonce: 300 };
+        assert_eq!(encode::<AccountData>(decode(&data)).unwrap(), data.encode());
+        assert_eq!(encode::<(bool, i32, String)>(json!([true, -3, "dot"])).unwrap(), (true, -3i32, "dot".to_string()).encode());
+    }
+
+    #[test]
+    fn test_encode_conveniences_and_errors() {
+        // Accounts as hex, byte sequences as text, integers as strings
+        assert_eq!(encode::<AccountId32>(json!(to_hex(&[7; 32]))).unwrap(), [7; 32]);
+        assert_eq!(encode::<Vec<u8>>(json!("hi")).unwrap(), b"hi".to_vec().encode());
+        assert_eq!(encode::<u128>(json!("340282366920938463463374607431768211455")).unwrap(), u128::MAX.encode());
+
+        assert_eq!(encode::<u8>(json!(256)).unwrap_err(), "Expected u8 but got 256");
+        assert_eq!(encode::<AccountId32>(json!("5Grw")).unwrap_err(), "Invalid account 5Grw");
+        assert_eq!(encode::<AccountData>(json!({ "free": 1 })).unwrap_err(), "Missing field nonce in {\"free\":1}");
+        assert_eq!(encode::<Event>(json!("Burned")).unwrap_err(), "Event has no variant Burned");
+        assert_eq!(encode::<[u8; 4]>(json!("0x0102")).unwrap_err(), "Expected 4 bytes but got 2");
+    }
+
+    #[test]
+    fn test_type_name() {
+        let (types, id) = registry::<(Vec<AccountData>, Option<[u8; 4]>, parity_scale_codec::Compact<u32>)>();
+        assert_eq!(type_name(&types, id), "(Vec<AccountData>, Option<[u8; 4]>, Compact<u32>)");
+    }
+
+    #[test]
+    fn test_decode_truncated_input() {
+        let (types, id) = registry::<AccountData>();
+        let result = decode_value(&types, id, &mut &[1u8, 2, 3][..]);
+        assert!(result.unwrap_err().starts_with("Invalid U128 value"));
+
+        let (types, id) = registry::<Event>();
+        assert_eq!(decode_value(&types, id, &mut &[9u8][..]).unwrap_err(), "Invalid variant index 9 for dot::scale::tests::Event");
+    }
+}
@@ -1,29 +1,212 @@
+use std::os::unix::process::CommandExt;
 use std::process::{Command, ExitStatus};
 use std::io;
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+use crate::chain_specs;
+use crate::daemon;
+use crate::db;
+use crate::logs;
+use crate::node_options::NodeOptions;
+use crate::readiness::{self, ReadyCheck, Readiness};
+use crate::ports;
+use crate::supervisor::{self, LoggedChild, RestartPolicy, ShutdownSignals};
+
+pub const OMNI_NODE_PATH: &str = "./binaries/polkadot-omni-node";
+pub const DEFAULT_NODE_NAME: &str = "omni-node";
+pub const DEFAULT_CHAIN_SPEC: &str = "./chain-specs/chain_spec.json";
 
 pub struct RealCommand {
     command: Command,
 }
 
-pub fn run(args: &[&str]) {
+pub struct ServeOptions {
+    // Name used for the node's log file and for `dot ps` / `dot stop`
+    pub name: String,
+    pub ready: ReadyCheck,
+    // How long a node may take to stop after Ctrl-C/SIGTERM before it is killed
+    pub grace: Duration,
+    pub restart: RestartPolicy,
+    // Typed omni-node flags, put in front of the pass-through arguments
+    pub node: NodeOptions,
+}
+
+pub fn run(args: &[&str], options: &ServeOptions) -> Result<(), Box<dyn Error>> {
+    run_with(OMNI_NODE_PATH, Path::new(daemon::RUN_DIR), Path::new(logs::LOG_DIR), args, options)
+}
+
+fn run_with(program: &str, run_dir: &Path, log_dir: &Path, args: &[&str], options: &ServeOptions) -> Result<(), Box<dyn Error>> {
+    let name = options.name.as_str();
+    daemon::ensure_not_running(run_dir, name)?;
+
+    let args = managed_base_path(options.node.apply(args)?, name);
+    let (args, ports) = ports::allocate(&args, ports::is_free)?;
     println!("Running omni-node...");
     println!("args: {:?}", args);
+    let log_file = logs::log_path(log_dir, name);
+    println!("Logging to {:?}", log_file);
+
+    // Its own process group keeps Ctrl-C from reaching omni-node directly, dot forwards it instead
+    let signals = ShutdownSignals::register()?;
+    let spawn = || supervisor::spawn_logged(Command::new(program).args(&args).process_group(0), &log_file, true);
+    let node = spawn().map_err(|e| format!("Failed to run omni-node: {}", e))?;
 
-    let command = RealCommand::new("./binaries/polkadot-omni-node")
-        .args(args) 
-        .status();
+    // Registered like a detached node so `dot ps` shows its restarts and `dot stop` reaches it
+    daemon::write_pid(run_dir, name, std::process::id())?;
+    ports::write_ports(run_dir, name, &ports)?;
+    ports.print();
+    let result = supervise_foreground(node, spawn, &signals, options, &ports.rpc_endpoint(), run_dir);
+    let _ = daemon::remove_run_files(run_dir, name);
+    result
+}
 
-    match command {
-        Ok(status) if status.success() => {
-            println!("Omni-node is now running.");
+fn supervise_foreground<F: FnMut() -> io::Result<LoggedChild>>(mut node: LoggedChild, spawn: F, signals: &ShutdownSignals, options: &ServeOptions, endpoint: &str, run_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let ready = &options.ready;
+    match readiness::wait_ready(endpoint, ready.timeout, readiness::POLL_INTERVAL, || node.is_running() && !signals.pending()) {
+        Ok(status) => print_ready(&status, endpoint),
+        Err(_) if signals.pending() || !node.is_running() => {}
+        Err(e) if ready.required => {
+            eprintln!("{}, stopping omni-node", e);
+            let exit = node.terminate(options.grace)?;
+            eprintln!("Omni-node {}", exit);
+            return Err(e);
         }
-        Ok(status) => {
-            eprintln!("Omni-node failed to start with exit status: {}", status);
+        Err(e) => eprintln!("{}, still waiting for omni-node", e),
+    }
+
+    let exit = supervisor::keep_alive(node, spawn, signals, options.grace, &options.restart, run_dir, &options.name, |message| eprintln!("{}", message))?;
+    if !exit.success() {
+        return Err(format!("Omni-node {}", exit).into());
+    }
+    println!("Omni-node {}.", exit);
+    Ok(())
+}
+
+pub fn run_detached(args: &[&str], options: &ServeOptions) -> Result<u32, Box<dyn Error>> {
+    let name = options.name.as_str();
+    let run_dir = Path::new(daemon::RUN_DIR);
+    daemon::ensure_not_running(run_dir, name)?;
+
+    let args = managed_base_path(options.node.apply(args)?, name);
+    let (args, ports) = ports::allocate(&args, ports::is_free)?;
+    let endpoint = ports.rpc_endpoint();
+    println!("Starting omni-node in the background as {}...", name);
+    println!("args: {:?}", args);
+
+    let pid = spawn_supervised(OMNI_NODE_PATH, &args, &[], name, options.grace, &options.restart)?;
+    ports::write_ports(run_dir, name, &ports)?;
+    println!("{} is running in the background with pid {}. Stop it with `dot stop {}`.", name, pid, name);
+    ports.print();
+    println!("Follow its output with `dot logs {} --follow`.", name);
+
+    let ready = &options.ready;
+    if ready.required {
+        println!("Waiting for {} to become ready...", name);
+        match readiness::wait_ready(&endpoint, ready.timeout, readiness::POLL_INTERVAL, || daemon::is_alive(pid)) {
+            Ok(status) => print_ready(&status, &endpoint),
+            Err(e) => {
+                // The supervisor itself waits up to the grace period for the node
+                daemon::stop_node(run_dir, name, options.grace + Duration::from_secs(5))?;
+                return Err(format!("{}. See `dot logs {}` for its output", e, name).into());
+            }
         }
-        Err(e) => {
-            eprintln!("Failed to run omni-node: {}", e);
+    }
+    Ok(pid)
+}
+
+// Runs a node under a background `dot supervise`, which owns its log file and restarts it. The node
+// inherits `env` from the supervisor
+pub fn spawn_supervised<S: AsRef<str>>(program: &str, args: &[S], env: &[(String, String)], name: &str, grace: Duration, restart: &RestartPolicy) -> Result<u32, Box<dyn Error>> {
+    let dot = std::env::current_exe().map_err(|e| format!("Failed to locate the dot executable: {}", e))?;
+    let grace_secs = grace.as_secs().to_string();
+    let restart_args = restart.to_args();
+    let mut supervise_args = vec!["supervise", "--name", name, "--grace-period", &grace_secs];
+    supervise_args.extend(restart_args.iter().map(String::as_str));
+    supervise_args.extend_from_slice(&["--", program]);
+    supervise_args.extend(args.iter().map(AsRef::as_ref));
+    daemon::spawn_detached(&dot, &supervise_args, env, Path::new(daemon::RUN_DIR), name)
+}
+
+fn print_ready(status: &Readiness, endpoint: &str) {
+    println!("Omni-node is now running: best block #{}, {} peer(s), RPC at {}", status.best_block, status.peers, endpoint);
+}
+
+// Splits node arguments at the first `--`: omni-node's own flags, then those of its embedded relay chain node
+pub fn split_relay_args<S: AsRef<str>>(args: &[S]) -> (&[S], Option<&[S]>) {
+    match args.iter().position(|arg| arg.as_ref() == "--") {
+        Some(separator) => (&args[..separator], Some(&args[separator + 1..])),
+        None => (args, None),
+    }
+}
+
+pub fn has_flag<S: AsRef<str>>(args: &[S], flag: &str) -> bool {
+    let (args, _) = split_relay_args(args);
+    args.iter().any(|arg| arg.as_ref() == flag || arg.as_ref().strip_prefix(flag).map(|rest| rest.starts_with('=')).unwrap_or(false))
+}
+
+// Prepends dot's default flags unless the user passed them to omni-node already. The relay chain
+// section after `--` is forwarded verbatim
+pub fn merge_node_args<S: AsRef<str>>(defaults: &[(&str, &str)], user_args: &[S]) -> Vec<String> {
+    let mut args = Vec::new();
+    for (flag, value) in defaults {
+        if !has_flag(user_args, flag) {
+            args.push(flag.to_string());
+            args.push(value.to_string());
         }
     }
+    args.extend(user_args.iter().map(|arg| arg.as_ref().to_string()));
+    args
+}
+
+// Nodes keep their database under ./data/<name> for `dot db`, unless they get their own
+// --base-path or run with --tmp
+fn managed_base_path(args: Vec<String>, name: &str) -> Vec<String> {
+    if has_flag(&args, "--tmp") {
+        return args;
+    }
+    let base_path = db::base_path(Path::new(db::DATA_DIR), name);
+    merge_node_args(&[("--base-path", &base_path.display().to_string())], &args)
+}
+
+// The value of a flag in omni-node's own section, either `--flag value` or `--flag=value`
+pub fn flag_value<'a, S: AsRef<str>>(args: &'a [S], flag: &str) -> Option<&'a str> {
+    let (args, _) = split_relay_args(args);
+    let mut value = None;
+    for (i, arg) in args.iter().map(AsRef::as_ref).enumerate() {
+        if let Some(rest) = arg.strip_prefix(flag).and_then(|rest| rest.strip_prefix('=')) {
+            value = Some(rest);
+        } else if arg == flag {
+            value = args.get(i + 1).map(AsRef::as_ref);
+        }
+    }
+    value
+}
+
+pub fn serve_runtime(wasm: &Path, args: &[&str], options: &ServeOptions) -> Result<(), Box<dyn Error>> {
+    // The scratch chain spec lives only as long as the node runs
+    let scratch = tempfile::Builder::new().prefix("dot-runtime-").tempdir()?;
+    serve_runtime_with(wasm, args, &scratch.path().join("chain_spec.json"), None, |node_args| run(node_args, options))?
+}
+
+pub fn serve_runtime_detached(wasm: &Path, args: &[&str], options: &ServeOptions) -> Result<u32, Box<dyn Error>> {
+    // Kept next to the PID file and removed by `dot stop`
+    let run_dir = Path::new(daemon::RUN_DIR);
+    daemon::ensure_not_running(run_dir, &options.name)?;
+    let chain_spec = run_dir.join(format!("{}.chain_spec.json", options.name));
+    serve_runtime_with(wasm, args, &chain_spec, None, |node_args| run_detached(node_args, options))?
+}
+
+fn serve_runtime_with<T, F: FnOnce(&[&str]) -> T>(wasm: &Path, args: &[&str], chain_spec: &Path, chain_spec_builder_path: Option<&Path>, run_node: F) -> Result<T, Box<dyn Error>> {
+    if has_flag(args, "--chain") {
+        return Err("--runtime generates the chain spec, do not pass --chain to omni-node as well".into());
+    }
+    println!("Generating a chain spec for {:?}", wasm);
+    chain_specs::gen_chain_spec(Some(wasm), chain_spec_builder_path, Some(chain_spec), None)?;
+
+    let chain_spec = chain_spec.to_str().ok_or("Chain spec path is not valid UTF-8")?;
+    let node_args = merge_node_args(&[("--chain", chain_spec)], args);
+    Ok(run_node(&node_args.iter().map(String::as_str).collect::<Vec<_>>()))
 }
 
 pub trait CommandRunner {
@@ -56,57 +239,144 @@ impl CommandRunner for RealCommand {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::process::ExitStatus;
-    use std::os::unix::process::ExitStatusExt;
+    use std::os::unix::fs::PermissionsExt;
+    use std::fs;
+    use tempfile::TempDir;
 
-    // Mock Command for testing purposes
-    struct MockCommand {
-        args: Vec<String>,
-        success: bool,
+    // A stand-in omni-node that exits with the given code
+    fn mock_node(temp_dir: &TempDir, code: i32) -> String {
+        let program = temp_dir.path().join("polkadot-omni-node");
+        fs::write(&program, format!("#!/bin/sh\nexit {}\n", code)).unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        program.to_str().unwrap().to_string()
     }
 
-    impl CommandRunner for MockCommand {
-        fn new(_program: &str) -> Self {
-            MockCommand {
-                args: Vec::new(),
-                success: true,
-            }
+    fn mock_options() -> ServeOptions {
+        ServeOptions {
+            name: "mock-node".to_string(),
+            ready: ReadyCheck { timeout: Duration::from_secs(1), required: false },
+            grace: Duration::from_secs(1),
+            restart: RestartPolicy::never(),
+            node: NodeOptions::default(),
         }
+    }
 
-        fn args(&mut self, args: &[&str]) -> &mut Self {
-            self.args.extend(args.iter().map(|&arg| arg.to_string()));
-            self
-        }
+    #[test]
+    fn test_run_rejects_conflicting_node_options() {
+        let mut options = mock_options();
+        options.node = NodeOptions { rpc_port: Some(9955), ..Default::default() };
+        let result = run(&["--chain", "./mock-specs/mock_chain.json", "--rpc-port", "9966"], &options);
+        assert_eq!(result.unwrap_err().to_string(), "--rpc-port is given both as a dot serve option and as an omni-node argument");
 
-        fn status(&mut self) -> io::Result<ExitStatus> {
-            if self.success {
-                Ok(ExitStatus::from_raw(0)) // Mock success
-            } else {
-                Ok(ExitStatus::from_raw(1)) // Mock failure
-            }
-        }
+        options.node = NodeOptions { tmp: true, base_path: Some("./data".into()), ..Default::default() };
+        assert!(run_detached(&["--chain", "./mock-specs/mock_chain.json"], &options).is_err());
     }
 
     #[test]
-    fn test_run_success() {
-        let mut mock_command = MockCommand::new("./mock-path");
-        mock_command.success = true;
+    fn test_run_missing_binary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = temp_dir.path().join("polkadot-omni-node");
+        let result = run_with(program.to_str().unwrap(), &temp_dir.path().join("run"), &temp_dir.path().join("logs"), &["--tmp"], &mock_options());
+        assert!(result.unwrap_err().to_string().starts_with("Failed to run omni-node: "));
+    }
 
-        // Call run with mock behavior
-        run(&["--chain", "./mock-specs/mock_chain.json"]);
+    #[test]
+    fn test_run_success() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = mock_node(&temp_dir, 0);
+        let (run_dir, log_dir) = (temp_dir.path().join("run"), temp_dir.path().join("logs"));
+        run_with(&program, &run_dir, &log_dir, &["--tmp"], &mock_options()).unwrap();
 
-        // Add assertions as needed
+        // The node was registered while it ran and is forgotten once it exits
+        assert!(log_dir.join("mock-node.log").exists());
+        assert_eq!(fs::read_dir(&run_dir).unwrap().count(), 0);
     }
 
     #[test]
     fn test_run_failure() {
-        let mut mock_command = MockCommand::new("./mock-path");
-        mock_command.success = false;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = mock_node(&temp_dir, 3);
+        let run_dir = temp_dir.path().join("run");
+        let result = run_with(&program, &run_dir, &temp_dir.path().join("logs"), &["--tmp"], &mock_options());
+        assert_eq!(result.unwrap_err().to_string(), "Omni-node exited with code 3");
+        assert_eq!(fs::read_dir(&run_dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_merge_node_args() {
+        let defaults = [("--chain", DEFAULT_CHAIN_SPEC)];
+        assert_eq!(merge_node_args::<&str>(&defaults, &[]), vec!["--chain", DEFAULT_CHAIN_SPEC]);
+        assert_eq!(merge_node_args(&defaults, &["--dev", "--rpc-cors=all"]), vec!["--chain", DEFAULT_CHAIN_SPEC, "--dev", "--rpc-cors=all"]);
 
-        // Call run with mock behavior
-        run(&["--chain", "./mock-specs/mock_chain.json"]);
+        // The user's --chain wins over the default, in either form
+        assert_eq!(merge_node_args(&defaults, &["--chain", "mine.json"]), vec!["--chain", "mine.json"]);
+        assert_eq!(merge_node_args(&defaults, &["--chain=mine.json"]), vec!["--chain=mine.json"]);
 
-        // Add assertions as needed
+        // A relay chain --chain does not replace the parachain default
+        let args = ["--dev", "--", "--chain", "./chain-specs/westend.json"];
+        assert_eq!(merge_node_args(&defaults, &args), vec!["--chain", DEFAULT_CHAIN_SPEC, "--dev", "--", "--chain", "./chain-specs/westend.json"]);
     }
 
+    #[test]
+    fn test_split_relay_args() {
+        let args = ["--chain", "para.json", "--", "--chain", "relay.json"];
+        let (parachain, relay) = split_relay_args(&args);
+        assert_eq!(parachain, &["--chain", "para.json"]);
+        assert_eq!(relay, Some(&["--chain", "relay.json"][..]));
+        assert!(!has_flag(&["--", "--chain", "relay.json"], "--chain"));
+        assert!(!has_flag(&["--chainx"], "--chain"));
+    }
+
+    #[test]
+    fn test_serve_runtime_rejects_chain_argument() {
+        let result = serve_runtime_with(Path::new("runtime.wasm"), &["--chain", "other.json"], Path::new("unused.json"), None, |_| panic!("node should not start"));
+        assert_eq!(result.unwrap_err().to_string(), "--runtime generates the chain spec, do not pass --chain to omni-node as well");
+    }
+
+    #[test]
+    fn test_managed_base_path() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(managed_base_path(args(&["--dev"]), "alice"), args(&["--base-path", "./data/alice", "--dev"]));
+        assert_eq!(managed_base_path(args(&["--base-path=/srv"]), "alice"), args(&["--base-path=/srv"]));
+        assert_eq!(managed_base_path(args(&["--tmp"]), "alice"), args(&["--tmp"]));
+    }
+
+    #[test]
+    fn test_flag_value() {
+        assert_eq!(flag_value(&["--chain", "spec.json"], "--rpc-port"), None);
+        assert_eq!(flag_value(&["--rpc-port", "9955"], "--rpc-port"), Some("9955"));
+        assert_eq!(flag_value(&["--rpc-port=9966", "--dev"], "--rpc-port"), Some("9966"));
+        assert_eq!(flag_value(&["--rpc-port-x=1", "--port", "1"], "--rpc-port"), None);
+        assert_eq!(flag_value(&["--dev", "--", "--rpc-port", "9988"], "--rpc-port"), None);
+    }
+
+    #[test]
+    fn test_serve_runtime_generates_scratch_chain_spec() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let wasm = temp_dir.path().join("runtime.wasm");
+        fs::write(&wasm, "mock wasm content").unwrap();
+        let builder = temp_dir.path().join("chain-spec-builder");
+        fs::write(&builder, "#!/bin/sh\necho '{\"name\":\"Custom\"}' > \"$2\"\n").unwrap();
+        fs::set_permissions(&builder, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut served: Vec<String> = Vec::new();
+        let mut spec_content = String::new();
+        let chain_spec = temp_dir.path().join("scratch/chain_spec.json");
+        serve_runtime_with(&wasm, &["--dev"], &chain_spec, Some(&builder), |args| {
+            served = args.iter().map(|arg| arg.to_string()).collect();
+            spec_content = fs::read_to_string(args[1]).unwrap();
+        })
+        .unwrap();
+
+        assert_eq!(served[0], "--chain");
+        assert_eq!(served[2], "--dev");
+        assert_eq!(served[1], chain_spec.to_str().unwrap());
+        assert_eq!(spec_content.trim(), "{\"name\":\"Custom\"}");
+    }
+
+    #[test]
+    fn test_serve_runtime_missing_wasm() {
+        let result = serve_runtime_with(Path::new("missing_runtime.wasm"), &[], Path::new("unused.json"), None, |_| panic!("node should not start"));
+        assert_eq!(result.unwrap_err().to_string(), "WASM file not found: \"missing_runtime.wasm\"");
+    }
 }
@@ -0,0 +1,87 @@
+use crate::hashing::blake2_512;
+
+const CHECKSUM_PREFIX: &[u8] = b"SS58PRE";
+const CHECKSUM_LEN: usize = 2;
+
+pub fn decode(address: &str) -> Result<(u16, [u8; 32]), String> {
+    let data = bs58::decode(address)
+        .into_vec()
+        .map_err(|e| format!("Invalid SS58 address {}: {}", address, e))?;
+
+    let (prefix, prefix_len) = match data.first() {
+        Some(&first) if first < 64 => (first as u16, 1),
+        Some(&first) if first < 128 && data.len() > 1 => {
+            let lower = (first << 2) | (data[1] >> 6);
+            let upper = data[1] & 0b0011_1111;
+            ((lower as u16) | ((upper as u16) << 8), 2)
+        }
+        _ => return Err(format!("Invalid SS58 address {}: unsupported prefix", address)),
+    };
+
+    if data.len() != prefix_len + 32 + CHECKSUM_LEN {
+        return Err(format!("Invalid SS58 address {}: unexpected length {}", address, data.len()));
+    }
+
+    let (payload, checksum) = data.split_at(data.len() - CHECKSUM_LEN);
+    let hash = blake2_512(&[CHECKSUM_PREFIX, payload].concat());
+    if hash[..CHECKSUM_LEN] != *checksum {
+        return Err(format!("Invalid SS58 address {}: checksum mismatch", address));
+    }
+
+    let mut account = [0u8; 32];
+    account.copy_from_slice(&payload[prefix_len..]);
+    Ok((prefix, account))
+}
+
+pub const GENERIC_PREFIX: u16 = 42;
+
+pub fn encode(prefix: u16, account: &[u8; 32]) -> String {
+    let mut data = match prefix {
+        0..=63 => vec![prefix as u8],
+        _ => {
+            let first = ((prefix & 0b1111_1100) as u8 >> 2) | 0b0100_0000;
+            let second = ((prefix >> 8) as u8) | ((prefix & 0b11) as u8) << 6;
+            vec![first, second]
+        }
+    };
+    data.extend_from_slice(account);
+    let hash = blake2_512(&[CHECKSUM_PREFIX, &data].concat());
+    data.extend_from_slice(&hash[..CHECKSUM_LEN]);
+    bs58::encode(data).into_string()
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashing::to_hex;
+
+    #[test]
+    fn test_decode_alice() {
+        let (prefix, account) = decode("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY").unwrap();
+        assert_eq!(prefix, 42);
+        assert_eq!(to_hex(&account), "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d");
+    }
+
+    #[test]
+    fn test_decode_bad_checksum() {
+        let result = decode("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQZ");
+        assert!(result.unwrap_err().contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let (_, account) = decode("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY").unwrap();
+        assert_eq!(encode(GENERIC_PREFIX, &account), "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+        assert_eq!(decode(&encode(0, &account)).unwrap(), (0, account));
+        assert_eq!(decode(&encode(2007, &account)).unwrap(), (2007, account));
+    }
+
+    #[test]
+    fn test_decode_not_base58() {
+        assert!(decode("0xd43593c7").is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use std::error::Error;
+use frame_metadata::v14::{StorageEntryModifier, StorageEntryType, StorageHasher};
+use serde_json::{json, Value};
+use crate::hashing::{blake2_128, blake2_256, from_hex, to_hex, twox_128, twox_256, twox_64};
+use crate::metadata::{self, Metadata, StorageEntry};
+use crate::rpc;
+use crate::scale;
+
+// The storage key of `Pallet.Item`: its prefix followed by every map key hashed with its hasher
+pub fn storage_key(metadata: &Metadata, pallet: &str, item: &str, keys: &[Value]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (storage, entry) = metadata.storage_entry(pallet, item)?;
+    let types = &metadata.types;
+    let mut key = metadata::storage_prefix(&storage.prefix, &entry.name);
+
+    let (hashers, key_types) = match &entry.ty {
+        StorageEntryType::Plain(_) => (&[][..], Vec::new()),
+        StorageEntryType::Map { hashers, key, .. } => (&hashers[..], metadata::key_types(types, hashers, key.id)),
+    };
+    if keys.len() != key_types.len() {
+        let names: Vec<String> = key_types.iter().map(|ty| scale::type_name(types, *ty)).collect();
+        return Err(format!(
+            "{}.{} takes {} key{}{} but {} {} given",
+            storage.prefix,
+            entry.name,
+            key_types.len(),
+            if key_types.len() == 1 { "" } else { "s" },
+            if names.is_empty() { String::new() } else { format!(" ({})", names.join(", ")) },
+            keys.len(),
+            if keys.len() == 1 { "was" } else { "were" }
+        ).into());
+    }
+    for ((hasher, ty), value) in hashers.iter().zip(key_types).zip(keys) {
+        let mut encoded = Vec::new();
+        scale::encode_value(types, ty, value, &mut encoded).map_err(|e| format!("Invalid key for {}.{}: {}", storage.prefix, entry.name, e))?;
+        key.extend(hash(hasher, &encoded));
+    }
+    Ok(key)
+}
+
+fn hash(hasher: &StorageHasher, data: &[u8]) -> Vec<u8> {
+    match hasher {
+        StorageHasher::Blake2_128 => blake2_128(data).to_vec(),
+        StorageHasher::Blake2_256 => blake2_256(data).to_vec(),
+        StorageHasher::Blake2_128Concat => [&blake2_128(data)[..], data].concat(),
+        StorageHasher::Twox128 => twox_128(data).to_vec(),
+        StorageHasher::Twox256 => twox_256(data).to_vec(),
+        StorageHasher::Twox64Concat => [&twox_64(data)[..], data].concat(),
+        StorageHasher::Identity => data.to_vec(),
+    }
+}
+
+// Decodes a `state_getStorage` result. A missing value is null for optional items and the
+// declared default otherwise
+pub fn decode_storage(metadata: &Metadata, entry: &StorageEntry, result: &Value) -> Result<Value, Box<dyn Error>> {
+    let value_type = match &entry.ty {
+        StorageEntryType::Plain(value) | StorageEntryType::Map { value, .. } => value.id,
+    };
+    let bytes = match result.as_str() {
+        Some(hex) => from_hex(hex)?,
+        None if matches!(entry.modifier, StorageEntryModifier::Optional) => return Ok(Value::Null),
+        None => entry.default.clone(),
+    };
+    let mut input = &bytes[..];
+    let value = scale::decode_value(&metadata.types, value_type, &mut input).map_err(|e| format!("Failed to decode {}: {}", entry.name, e))?;
+    if !input.is_empty() {
+        return Err(format!("Failed to decode {}: {} bytes left over", entry.name, input.len()).into());
+    }
+    Ok(value)
+}
+
+// Reads `Pallet.Item` at the best block of a running node
+pub fn query(endpoint: &str, pallet: &str, item: &str, keys: &[Value]) -> Result<Value, Box<dyn Error>> {
+    let (_, metadata) = metadata::fetch(endpoint, None)?;
+    let key = storage_key(&metadata, pallet, item, keys)?;
+    let (_, entry) = metadata.storage_entry(pallet, item)?;
+    let result = rpc::request(endpoint, "state_getStorage", json!([to_hex(&key)]))?;
+    decode_storage(&metadata, entry, &result)
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{test_metadata, AccountData};
+    use mockito::{mock, Matcher};
+    use parity_scale_codec::Encode;
+
+    const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+    #[test]
+    fn test_storage_key() {
+        let metadata = metadata::decode(&test_metadata()).unwrap();
+        assert_eq!(
+            to_hex(&storage_key(&metadata, "System", "Number", &[]).unwrap()),
+            "0x26aa394eea5630e07c48ae0c9558cef702a5c1b19ab7a04f536c519aca4983ac"
+        );
+        // Same as the key polkadot.js computes for Alice's account
+        assert_eq!(
+            to_hex(&storage_key(&metadata, "system", "account", &[json!(ALICE)]).unwrap()),
+            "0x26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9de1e86a9a8c739864cf3cc5ec2bea59fd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
+        );
+
+        let key = storage_key(&metadata, "Balances", "Reserves", &[json!(7), json!(ALICE)]).unwrap();
+        let (_, alice) = crate::ss58::decode(ALICE).unwrap();
+        assert_eq!(key, [metadata::storage_prefix("Balances", "Reserves"), twox_64(&7u32.encode()).to_vec(), 7u32.encode(), alice.to_vec()].concat());
+    }
+
+    #[test]
+    fn test_storage_key_errors() {
+        let metadata = metadata::decode(&test_metadata()).unwrap();
+        assert_eq!(storage_key(&metadata, "System", "Account", &[]).unwrap_err().to_string(), "System.Account takes 1 key (AccountId32) but 0 were given");
+        assert_eq!(storage_key(&metadata, "System", "Number", &[json!(1)]).unwrap_err().to_string(), "System.Number takes 0 keys but 1 was given");
+        assert_eq!(storage_key(&metadata, "System", "Account", &[json!(1)]).unwrap_err().to_string(), "Invalid key for System.Account: Expected AccountId32 but got 1");
+        assert_eq!(storage_key(&metadata, "System", "Nope", &[]).unwrap_err().to_string(), "Pallet System has no storage item Nope");
+    }
+
+    #[test]
+    fn test_decode_storage() {
+        let metadata = metadata::decode(&test_metadata()).unwrap();
+        let (_, account) = metadata.storage_entry("System", "Account").unwrap();
+        let stored = to_hex(&AccountData { free: 10, nonce: 2 }.encode());
+        assert_eq!(decode_storage(&metadata, account, &json!(stored)).unwrap(), json!({ "free": 10, "nonce": 2 }));
+        assert_eq!(decode_storage(&metadata, account, &Value::Null).unwrap(), json!({ "free": 0, "nonce": 0 }));
+        assert!(decode_storage(&metadata, account, &json!("0x0102")).is_err());
+
+        let (_, reserves) = metadata.storage_entry("Balances", "Reserves").unwrap();
+        assert_eq!(decode_storage(&metadata, reserves, &Value::Null).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_query() {
+        let metadata = test_metadata();
+        let _v15 = mock("POST", "/storage-query").match_body(Matcher::PartialJson(json!({ "method": "state_call" })))
+            .with_body(json!({ "jsonrpc": "2.0", "id": 1, "result": to_hex(&Some(metadata).encode()) }).to_string()).create();
+        let _storage = mock("POST", "/storage-query").match_body(Matcher::PartialJson(json!({ "method": "state_getStorage" })))
+            .with_body(json!({ "jsonrpc": "2.0", "id": 1, "result": to_hex(&42u32.encode()) }).to_string()).create();
+
+        let endpoint = format!("{}/storage-query", mockito::server_url());
+        assert_eq!(query(&endpoint, "System", "Number", &[]).unwrap(), json!(42));
+    }
+}
@@ -0,0 +1,507 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use crate::daemon::{self, NodeState};
+use crate::logs::{self, RotatingWriter};
+
+type SharedLog = Arc<Mutex<Option<RotatingWriter>>>;
+
+pub struct LoggedChild {
+    child: Child,
+    pumps: Vec<JoinHandle<()>>,
+}
+
+// Runs a node with its stdout/stderr copied into a rotating log file, and to the terminal when `echo` is set
+pub fn run_logged(command: &mut Command, log_file: &Path, echo: bool) -> io::Result<ExitStatus> {
+    spawn_logged(command, log_file, echo)?.wait()
+}
+
+pub fn spawn_logged(command: &mut Command, log_file: &Path, echo: bool) -> io::Result<LoggedChild> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // A node without a log file is still better than no node
+    let writer = match RotatingWriter::open(log_file, logs::DEFAULT_MAX_BYTES, logs::DEFAULT_MAX_FILES) {
+        Ok(writer) => Some(writer),
+        Err(e) => {
+            eprintln!("Failed to open log file {:?}, output will not be captured: {}", log_file, e);
+            None
+        }
+    };
+    let log: SharedLog = Arc::new(Mutex::new(writer));
+
+    let mut pumps = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        pumps.push(pump(stdout, log.clone(), echo.then(|| Box::new(io::stdout()) as Box<dyn Write + Send>)));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        pumps.push(pump(stderr, log.clone(), echo.then(|| Box::new(io::stderr()) as Box<dyn Write + Send>)));
+    }
+    Ok(LoggedChild { child, pumps })
+}
+
+impl LoggedChild {
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    // Children started in their own process group are signalled as a group, so `cargo run` reaches the node too
+    fn signal(&self, signal: i32) {
+        let _ = daemon::signal_group(self.child.id(), signal);
+    }
+
+    // SIGTERM first so the node can flush its database, SIGKILL if it is still around after the grace period
+    pub fn terminate(self, grace: Duration) -> io::Result<Exit> {
+        self.signal(libc::SIGTERM);
+        self.wait_until_exit(None, Some(libc::SIGTERM), grace)
+    }
+
+    // Waits for the child, forwarding SIGINT/SIGTERM/SIGHUP received by dot and escalating to SIGKILL
+    // after the grace period or on a second signal
+    pub fn wait_with_shutdown(self, signals: &ShutdownSignals, grace: Duration) -> io::Result<Exit> {
+        self.wait_until_exit(Some(signals), None, grace)
+    }
+
+    fn wait_until_exit(mut self, signals: Option<&ShutdownSignals>, mut forwarded: Option<i32>, grace: Duration) -> io::Result<Exit> {
+        let mut deadline = forwarded.map(|_| Instant::now() + grace);
+        let mut killed = false;
+        let status = loop {
+            if let Some(status) = self.child.try_wait()? {
+                break status;
+            }
+            if let Some(signal) = signals.and_then(ShutdownSignals::take) {
+                if forwarded.is_none() {
+                    self.signal(signal);
+                    forwarded = Some(signal);
+                    deadline = Some(Instant::now() + grace);
+                } else if !killed {
+                    self.signal(libc::SIGKILL);
+                    killed = true;
+                }
+            }
+            if !killed && deadline.map(|deadline| Instant::now() >= deadline).unwrap_or(false) {
+                self.signal(libc::SIGKILL);
+                killed = true;
+            }
+            thread::sleep(Duration::from_millis(100));
+        };
+
+        for handle in self.pumps {
+            let _ = handle.join();
+        }
+        Ok(Exit { status, forwarded, killed })
+    }
+
+    pub fn wait(mut self) -> io::Result<ExitStatus> {
+        let status = self.child.wait()?;
+        for handle in self.pumps {
+            let _ = handle.join();
+        }
+        Ok(status)
+    }
+}
+
+#[derive(Debug)]
+pub struct Exit {
+    pub status: ExitStatus,
+    // The shutdown signal passed on to the child, if any
+    pub forwarded: Option<i32>,
+    pub killed: bool,
+}
+
+impl Exit {
+    // Stopping on request counts as success as long as the child did not have to be killed
+    pub fn success(&self) -> bool {
+        if self.forwarded.is_some() {
+            !self.killed
+        } else {
+            self.status.success()
+        }
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match (self.status.code(), self.status.signal()) {
+            (Some(0), _) => "exited successfully".to_string(),
+            (Some(code), _) => format!("exited with code {}", code),
+            (None, Some(signal)) => format!("was terminated by {}", signal_name(signal)),
+            _ => format!("exited with {}", self.status),
+        };
+        match self.forwarded {
+            Some(signal) if self.killed => write!(f, "did not stop after {} and was killed with SIGKILL", signal_name(signal)),
+            Some(signal) => write!(f, "received {} and {}", signal_name(signal), outcome),
+            None => write!(f, "{}", outcome),
+        }
+    }
+}
+
+pub fn signal_name(signal: i32) -> String {
+    match signal {
+        libc::SIGHUP => "SIGHUP".to_string(),
+        libc::SIGINT => "SIGINT".to_string(),
+        libc::SIGQUIT => "SIGQUIT".to_string(),
+        libc::SIGABRT => "SIGABRT".to_string(),
+        libc::SIGKILL => "SIGKILL".to_string(),
+        libc::SIGSEGV => "SIGSEGV".to_string(),
+        libc::SIGTERM => "SIGTERM".to_string(),
+        other => format!("signal {}", other),
+    }
+}
+
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestartPolicy {
+    pub on_failure: bool,
+    pub max_restarts: u32,
+    // Delay before the first restart, doubled for every further one
+    pub backoff: Duration,
+}
+
+impl RestartPolicy {
+    pub fn never() -> Self {
+        RestartPolicy { on_failure: false, max_restarts: 0, backoff: Duration::ZERO }
+    }
+
+    pub fn delay(&self, restart: u32) -> Duration {
+        let factor = 1u32.checked_shl(restart.saturating_sub(1)).unwrap_or(u32::MAX);
+        self.backoff.saturating_mul(factor).min(MAX_BACKOFF)
+    }
+
+    // The flags that recreate this policy for `dot supervise`
+    pub fn to_args(&self) -> Vec<String> {
+        if !self.on_failure {
+            return Vec::new();
+        }
+        vec![
+            "--restart".to_string(), "on-failure".to_string(),
+            "--max-restarts".to_string(), self.max_restarts.to_string(),
+            "--backoff".to_string(), self.backoff.as_secs().to_string(),
+        ]
+    }
+}
+
+// Waits for the node and, when the policy allows, restarts it after a failure. Restart counts and
+// the last exit are kept in run/<name>.state.json for `dot ps`
+#[allow(clippy::too_many_arguments)]
+pub fn keep_alive<F, R>(
+    mut child: LoggedChild,
+    mut respawn: F,
+    signals: &ShutdownSignals,
+    grace: Duration,
+    policy: &RestartPolicy,
+    run_dir: &Path,
+    name: &str,
+    mut report: R,
+) -> io::Result<Exit>
+where
+    F: FnMut() -> io::Result<LoggedChild>,
+    R: FnMut(&str),
+{
+    let mut restarts = 0;
+    loop {
+        let exit = child.wait_with_shutdown(signals, grace)?;
+        // A node stopped on request is never restarted
+        let failed = exit.forwarded.is_none() && !exit.success();
+        let restart = policy.on_failure && failed && restarts < policy.max_restarts;
+
+        let state = NodeState { restarting: restart, restarts, last_exit: Some(exit.to_string()) };
+        let _ = daemon::write_state(run_dir, name, &state);
+
+        if !restart {
+            if policy.on_failure && failed {
+                report(&format!("{} {}, giving up after {} restart(s)", name, exit, restarts));
+            }
+            return Ok(exit);
+        }
+
+        restarts += 1;
+        let delay = policy.delay(restarts);
+        report(&format!("{} {}, restarting in {}s (restart {} of {})", name, exit, delay.as_secs_f32(), restarts, policy.max_restarts));
+        if signals.wait(delay) {
+            return Ok(exit);
+        }
+        child = respawn()?;
+        let _ = daemon::write_state(run_dir, name, &NodeState { restarting: false, ..state });
+    }
+}
+
+// Catches the signals that would otherwise kill dot and leave its node without anyone reading its output
+pub struct ShutdownSignals {
+    received: Arc<AtomicUsize>,
+    ids: Vec<signal_hook::SigId>,
+}
+
+impl ShutdownSignals {
+    pub fn register() -> io::Result<Self> {
+        let received = Arc::new(AtomicUsize::new(0));
+        let mut ids = Vec::new();
+        for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            ids.push(signal_hook::flag::register_usize(signal, received.clone(), signal as usize)?);
+        }
+        Ok(ShutdownSignals { received, ids })
+    }
+
+    pub fn pending(&self) -> bool {
+        self.received.load(Ordering::SeqCst) != 0
+    }
+
+    // Sleeps for the timeout, returning early with true if a shutdown signal arrives
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            if self.pending() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50).min(timeout - start.elapsed()));
+        }
+        self.pending()
+    }
+
+    pub fn take(&self) -> Option<i32> {
+        match self.received.swap(0, Ordering::SeqCst) {
+            0 => None,
+            signal => Some(signal as i32),
+        }
+    }
+}
+
+impl Drop for ShutdownSignals {
+    fn drop(&mut self) {
+        for id in self.ids.drain(..) {
+            signal_hook::low_level::unregister(id);
+        }
+    }
+}
+
+// Entry point of the hidden `dot supervise` command that runs detached nodes, returns the exit code
+pub fn supervise(name: &str, program: &str, args: &[&str], grace: Duration, policy: &RestartPolicy) -> i32 {
+    let log_file = logs::log_path(Path::new(logs::LOG_DIR), name);
+    let _ = log_line(&log_file, &format!("starting {} {}", program, args.join(" ")));
+
+    // The node shares the supervisor's process group, so a SIGKILL from `dot stop` reaches both
+    let spawn = || spawn_logged(Command::new(program).args(args), &log_file, false);
+    let report = |message: &str| {
+        let _ = log_line(&log_file, message);
+    };
+    let result = ShutdownSignals::register()
+        .and_then(|signals| keep_alive(spawn()?, spawn, &signals, grace, policy, Path::new(daemon::RUN_DIR), name, report));
+    match result {
+        Ok(exit) => {
+            let _ = log_line(&log_file, &format!("{} {}", program, exit));
+            if exit.success() { 0 } else { exit.status.code().unwrap_or(1) }
+        }
+        Err(e) => {
+            let _ = log_line(&log_file, &format!("failed to start {}: {}", program, e));
+            1
+        }
+    }
+}
+
+// Writes a supervisor message into the node's log so it shows up in `dot logs`
+pub fn log_line(log_file: &Path, message: &str) -> io::Result<()> {
+    let mut writer = RotatingWriter::open(log_file, logs::DEFAULT_MAX_BYTES, logs::DEFAULT_MAX_FILES)?;
+    writeln!(writer, "{} [dot] {}", logs::now().format("%Y-%m-%d %H:%M:%S%.3f"), message)
+}
+
+fn pump<R: Read + Send + 'static>(source: R, log: SharedLog, mut echo: Option<Box<dyn Write + Send>>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(source);
+        let mut line = Vec::new();
+        // Whole lines keep stdout and stderr from interleaving mid-line in the log
+        while matches!(reader.read_until(b'\n', &mut line), Ok(read) if read > 0) {
+            if let Some(writer) = log.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+                let _ = writer.write_all(&line);
+            }
+            if let Some(out) = echo.as_mut() {
+                let _ = out.write_all(&line);
+                let _ = out.flush();
+            }
+            line.clear();
+        }
+    })
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::process::CommandExt;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_run_logged_captures_stdout_and_stderr() {
+        let temp_dir = tempdir().unwrap();
+        let log_file = logs::log_path(&temp_dir.path().join("logs"), "node");
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo out; echo err >&2; exit 3"]).current_dir(temp_dir.path());
+        let status = run_logged(&mut command, &log_file, false).unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        let mut lines: Vec<String> = fs::read_to_string(&log_file).unwrap().lines().map(String::from).collect();
+        lines.sort();
+        assert_eq!(lines, vec!["err", "out"]);
+    }
+
+    #[test]
+    fn test_run_logged_missing_program() {
+        let temp_dir = tempdir().unwrap();
+        let log_file = logs::log_path(temp_dir.path(), "node");
+
+        assert!(run_logged(&mut Command::new("./definitely-not-a-node"), &log_file, false).is_err());
+        assert!(!log_file.exists());
+    }
+
+    fn pending(signal: i32) -> ShutdownSignals {
+        ShutdownSignals { received: Arc::new(AtomicUsize::new(signal as usize)), ids: Vec::new() }
+    }
+
+    // Returns once the script has printed `ready`, i.e. after its traps are installed
+    fn spawn_shell(script: &str, dir: &Path) -> LoggedChild {
+        let log_file = logs::log_path(dir, "node");
+        let mut command = Command::new("sh");
+        command.args(["-c", script]).current_dir(dir).process_group(0);
+        let child = spawn_logged(&mut command, &log_file, false).unwrap();
+
+        let start = Instant::now();
+        while !fs::read_to_string(&log_file).map(|log| log.contains("ready")).unwrap_or(false) {
+            assert!(start.elapsed() < Duration::from_secs(5), "script never became ready");
+            thread::sleep(Duration::from_millis(10));
+        }
+        child
+    }
+
+    #[test]
+    fn test_terminate_running_child() {
+        let temp_dir = tempdir().unwrap();
+        let log_file = logs::log_path(temp_dir.path(), "node");
+
+        let mut child = spawn_logged(Command::new("sleep").arg("30").current_dir(temp_dir.path()), &log_file, false).unwrap();
+        assert!(child.is_running());
+        let exit = child.terminate(Duration::from_secs(5)).unwrap();
+        assert_eq!(exit.status.signal(), Some(libc::SIGTERM));
+        assert_eq!(exit.to_string(), "received SIGTERM and was terminated by SIGTERM");
+        assert!(exit.success());
+    }
+
+    #[test]
+    fn test_forwards_signal_to_process_group() {
+        let temp_dir = tempdir().unwrap();
+        let child = spawn_shell("trap 'echo stopping; exit 0' INT; echo ready; while true; do sleep 0.1; done", temp_dir.path());
+
+        let exit = child.wait_with_shutdown(&pending(libc::SIGINT), Duration::from_secs(5)).unwrap();
+        assert_eq!(exit.to_string(), "received SIGINT and exited successfully");
+        assert!(!exit.killed);
+        assert!(fs::read_to_string(logs::log_path(temp_dir.path(), "node")).unwrap().contains("stopping"));
+    }
+
+    #[test]
+    fn test_escalates_to_sigkill_after_grace_period() {
+        let temp_dir = tempdir().unwrap();
+        let child = spawn_shell("trap '' TERM; echo ready; while true; do sleep 0.1; done", temp_dir.path());
+
+        let exit = child.wait_with_shutdown(&pending(libc::SIGTERM), Duration::from_millis(300)).unwrap();
+        assert!(exit.killed);
+        assert_eq!(exit.status.signal(), Some(libc::SIGKILL));
+        assert_eq!(exit.to_string(), "did not stop after SIGTERM and was killed with SIGKILL");
+        assert!(!exit.success());
+    }
+
+    #[test]
+    fn test_exit_without_signal() {
+        let temp_dir = tempdir().unwrap();
+        let child = spawn_shell("echo ready; exit 4", temp_dir.path());
+
+        let exit = child.wait_with_shutdown(&pending(0), Duration::from_secs(1)).unwrap();
+        assert_eq!(exit.to_string(), "exited with code 4");
+        assert!(!exit.success());
+    }
+
+    #[test]
+    fn test_keep_alive_restarts_failed_node() {
+        let temp_dir = tempdir().unwrap();
+        let run_dir = temp_dir.path().join("run");
+        let counter = temp_dir.path().join("starts");
+        // Fails twice, then exits cleanly
+        let script = format!("echo x >> {0}; test $(wc -l < {0}) -ge 3", counter.display());
+        let spawn = || spawn_logged(Command::new("sh").args(["-c", &script]).current_dir(temp_dir.path()), &logs::log_path(temp_dir.path(), "node"), false);
+
+        let policy = RestartPolicy { on_failure: true, max_restarts: 5, backoff: Duration::from_millis(10) };
+        let mut reports = Vec::new();
+        let exit = keep_alive(spawn().unwrap(), spawn, &pending(0), Duration::from_secs(1), &policy, &run_dir, "node", |message| reports.push(message.to_string())).unwrap();
+
+        assert!(exit.success());
+        assert_eq!(fs::read_to_string(&counter).unwrap().lines().count(), 3);
+        assert_eq!(reports, vec![
+            "node exited with code 1, restarting in 0.01s (restart 1 of 5)",
+            "node exited with code 1, restarting in 0.02s (restart 2 of 5)",
+        ]);
+        let state = daemon::read_state(&run_dir, "node");
+        assert_eq!(state, NodeState { restarting: false, restarts: 2, last_exit: Some("exited successfully".to_string()) });
+    }
+
+    #[test]
+    fn test_keep_alive_gives_up_after_max_restarts() {
+        let temp_dir = tempdir().unwrap();
+        let run_dir = temp_dir.path().join("run");
+        let spawn = || spawn_logged(Command::new("sh").args(["-c", "exit 101"]).current_dir(temp_dir.path()), &logs::log_path(temp_dir.path(), "node"), false);
+
+        let policy = RestartPolicy { on_failure: true, max_restarts: 1, backoff: Duration::ZERO };
+        let mut reports = Vec::new();
+        let exit = keep_alive(spawn().unwrap(), spawn, &pending(0), Duration::from_secs(1), &policy, &run_dir, "node", |message| reports.push(message.to_string())).unwrap();
+
+        assert_eq!(exit.status.code(), Some(101));
+        assert_eq!(reports.last().unwrap(), "node exited with code 101, giving up after 1 restart(s)");
+        assert_eq!(daemon::read_state(&run_dir, "node").restarts, 1);
+    }
+
+    #[test]
+    fn test_keep_alive_does_not_restart_without_policy() {
+        let temp_dir = tempdir().unwrap();
+        let run_dir = temp_dir.path().join("run");
+        let spawn = || spawn_logged(Command::new("sh").args(["-c", "exit 1"]).current_dir(temp_dir.path()), &logs::log_path(temp_dir.path(), "node"), false);
+
+        let exit = keep_alive(spawn().unwrap(), || panic!("restarted"), &pending(0), Duration::from_secs(1), &RestartPolicy::never(), &run_dir, "node", |_| {}).unwrap();
+        assert_eq!(exit.status.code(), Some(1));
+    }
+
+    #[test]
+    fn test_restart_policy_backoff() {
+        let policy = RestartPolicy { on_failure: true, max_restarts: 10, backoff: Duration::from_secs(2) };
+        assert_eq!(policy.delay(1), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(8));
+        assert_eq!(policy.delay(40), MAX_BACKOFF);
+        assert_eq!(policy.to_args(), vec!["--restart", "on-failure", "--max-restarts", "10", "--backoff", "2"]);
+        assert!(RestartPolicy::never().to_args().is_empty());
+    }
+
+    #[test]
+    fn test_log_line_is_timestamped() {
+        let temp_dir = tempdir().unwrap();
+        let log_file = logs::log_path(temp_dir.path(), "node");
+
+        log_line(&log_file, "node exited").unwrap();
+        let lines = logs::read_lines(&log_file, None, Some(logs::parse_since("1m", logs::now()).unwrap())).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].ends_with(" [dot] node exited"));
+    }
+}
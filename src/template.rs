@@ -2,8 +2,13 @@ use std::process::Command;
 use std::path::Path;
 use std::error::Error;
 use tempfile::tempdir;
+use std::os::unix::process::CommandExt;
+use std::time::Duration;
+use crate::logs;
+use crate::ports;
+use crate::supervisor::{self, ShutdownSignals};
 
-pub fn run_template(args: &[&str], template: &str) -> Result<(), Box<dyn Error>>{
+pub fn run_template(args: &[&str], template: &str, grace: Duration) -> Result<(), Box<dyn Error>>{
     println!("Running {}...{:?}", template, args);
 
     let destination = format!("./templates/{}-template", template);
@@ -32,27 +37,42 @@ pub fn run_template(args: &[&str], template: &str) -> Result<(), Box<dyn Error>>
 
     println!("args: {:?}", args);
 
-    let _ = serve_template(args, repo_path);
+    let _ = serve_template(args, repo_path, Path::new(logs::LOG_DIR), grace);
 
     println!("{} is now running.", template);
     Ok(())
 }
 
-fn serve_template(args: &[&str], repo_path: &Path) -> Result<(), Box<dyn Error>>{
+fn serve_template(args: &[&str], repo_path: &Path, log_dir: &Path, grace: Duration) -> Result<(), Box<dyn Error>>{
     if !repo_path.exists() {
         return Err(format!("The specified template directory does not exist: {:?}", repo_path).into());
     }
 
-    let output = Command::new("cargo")
-        .args(&["run", "--release", "--", "--dev"])
-        .args(args)
-        .current_dir(repo_path)
-        .output()
-        .expect("Failed to run project");
-
-    println!("{}", String::from_utf8_lossy(&output.stdout));
-
-    if !output.status.success() {
+    // Logs are named after the template directory, e.g. ./logs/minimal-template.log
+    let name = repo_path.file_name().and_then(|name| name.to_str()).unwrap_or("template");
+    let log_file = logs::log_path(log_dir, name);
+    println!("Logging to {:?}", log_file);
+
+    // Template nodes use the same default ports as omni-node, so both can run side by side
+    let (args, ports) = ports::allocate(args, ports::is_free)?;
+
+    // Ctrl-C is forwarded to the whole `cargo run` process group, see supervisor::ShutdownSignals
+    let signals = ShutdownSignals::register()?;
+    let node = supervisor::spawn_logged(
+        Command::new("cargo")
+            .args(&["run", "--release", "--", "--dev"])
+            .args(&args)
+            .current_dir(repo_path)
+            .process_group(0),
+        &log_file,
+        true,
+    )
+    .expect("Failed to run project");
+    ports.print();
+    let exit = node.wait_with_shutdown(&signals, grace)?;
+    println!("Template node {}.", exit);
+
+    if !exit.success() {
         return Err(format!("Failed to run a node").into());
     }
     Ok(()) 
@@ -91,7 +111,11 @@ mod tests {
         "#)?;
     
         // Call the serve_template function
-        serve_template(&["--example-arg"], repo_path)?;
+        let log_dir = tempdir()?;
+        serve_template(&["--example-arg"], repo_path, log_dir.path(), Duration::from_secs(1))?;
+
+        let log = fs::read_to_string(logs::log_path(log_dir.path(), repo_path.file_name().unwrap().to_str().unwrap()))?;
+        assert!(log.contains("Hello, world!"));
     
         Ok(())
     }
@@ -104,7 +128,7 @@ mod tests {
         let template = "unknown_template"; // Use an unrecognized template
 
         // Act: Run the function you are testing
-        let result = run_template(&args, template);
+        let result = run_template(&args, template, Duration::from_secs(1));
 
         // Assert: Check that the result is an error
         assert!(result.is_err(), "Expected run_template to return an error for unrecognized template");
@@ -135,7 +159,7 @@ mod tests {
         let destination_path = Path::new(&destination);
 
         // Act: Run the function you are testing
-        let result = serve_template(&args, destination_path);
+        let result = serve_template(&args, destination_path, Path::new(logs::LOG_DIR), Duration::from_secs(1));
 
         // Assert: Check if the result is an error
         assert!(result.is_err(), "Expected serve_template to return an error");
@@ -0,0 +1,277 @@
+// Fixtures shared by the tests of several modules: a small runtime's metadata and types, a
+// stand-in node and a stand-in runtime wasm
+use std::net::TcpListener;
+use std::thread;
+use frame_metadata::v14::{PalletCallMetadata, PalletConstantMetadata, PalletErrorMetadata, PalletEventMetadata, PalletStorageMetadata, StorageEntryMetadata, StorageEntryModifier, StorageEntryType, StorageHasher};
+use frame_metadata::v15::{self, CustomMetadata, ExtrinsicMetadata, OuterEnums, PalletMetadata, RuntimeMetadataV15};
+use frame_metadata::RuntimeMetadataPrefixed;
+use parity_scale_codec::Encode;
+use scale_info::{meta_type, MetaType, TypeInfo};
+use serde_json::Value;
+use tungstenite::Message;
+use crate::hashing::blake2_64;
+
+#[derive(Encode, TypeInfo)]
+pub struct AccountId32(pub [u8; 32]);
+
+#[derive(Encode, TypeInfo)]
+pub struct AccountData {
+    pub free: u128,
+    pub nonce: u32,
+}
+
+#[allow(dead_code, non_camel_case_types)]
+#[derive(Encode, TypeInfo)]
+pub enum SystemCall {
+    remark { remark: Vec<u8> },
+    set_heap_pages { pages: u64 },
+}
+
+#[allow(dead_code)]
+#[derive(Encode, TypeInfo)]
+pub enum SystemError {
+    CallFiltered,
+}
+
+#[derive(Encode, TypeInfo)]
+pub struct ModuleError {
+    pub index: u8,
+    pub error: [u8; 4],
+}
+
+#[allow(dead_code)]
+#[derive(Encode, TypeInfo)]
+pub enum DispatchError {
+    BadOrigin,
+    Module(ModuleError),
+}
+
+#[derive(Encode, TypeInfo)]
+pub enum SystemEvent {
+    ExtrinsicSuccess { weight: u64 },
+    ExtrinsicFailed { dispatch_error: DispatchError },
+    CodeUpdated,
+}
+
+#[allow(dead_code)]
+#[derive(Encode, TypeInfo)]
+pub enum MultiAddress {
+    Id(AccountId32),
+    Index(#[codec(compact)] u32),
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Encode, TypeInfo)]
+pub enum BalancesCall {
+    transfer_keep_alive { dest: MultiAddress, #[codec(compact)] value: u128 },
+}
+
+#[allow(dead_code)]
+#[derive(Encode, TypeInfo)]
+pub enum BalancesError {
+    ExistentialDeposit,
+    InsufficientBalance,
+}
+
+#[derive(Encode, TypeInfo)]
+pub enum BalancesEvent {
+    Transfer { amount: u128 },
+}
+
+#[allow(dead_code)]
+#[derive(Encode, TypeInfo)]
+pub enum MultiSignature {
+    Ed25519([u8; 64]),
+    Sr25519([u8; 64]),
+}
+
+// The signed extensions of a typical runtime
+#[allow(dead_code)]
+#[derive(Encode, TypeInfo)]
+pub enum Era {
+    Immortal,
+    Mortal1(u8),
+}
+
+#[derive(Encode, TypeInfo)]
+pub struct CheckNonce(#[codec(compact)] pub u32);
+
+#[derive(Encode, TypeInfo)]
+pub struct ChargeTransactionPayment(#[codec(compact)] pub u128);
+
+#[allow(dead_code)]
+#[derive(Encode, TypeInfo)]
+pub enum Mode {
+    Disabled,
+    Enabled,
+}
+
+#[derive(Encode, TypeInfo)]
+pub struct CheckMetadataHash {
+    pub mode: Mode,
+}
+
+#[derive(Encode, TypeInfo)]
+pub enum RuntimeEvent {
+    #[codec(index = 0)]
+    System(SystemEvent),
+    #[codec(index = 10)]
+    Balances(BalancesEvent),
+}
+
+#[derive(Encode, TypeInfo)]
+pub enum Phase {
+    ApplyExtrinsic(u32),
+    Finalization,
+}
+
+#[derive(Encode, TypeInfo)]
+pub struct EventRecord {
+    pub phase: Phase,
+    pub event: RuntimeEvent,
+    pub topics: Vec<[u8; 32]>,
+}
+
+pub fn pallet(name: &'static str, index: u8, event: MetaType, entries: Vec<StorageEntryMetadata>) -> PalletMetadata {
+    PalletMetadata {
+        name,
+        storage: Some(PalletStorageMetadata { prefix: name, entries }),
+        calls: None,
+        event: Some(PalletEventMetadata { ty: event }),
+        constants: vec![],
+        error: None,
+        index,
+        docs: vec!["Docs of the pallet"],
+    }
+}
+
+pub fn entry(name: &'static str, ty: StorageEntryType, default: Vec<u8>) -> StorageEntryMetadata {
+    StorageEntryMetadata { name, modifier: StorageEntryModifier::Default, ty, default, docs: vec![] }
+}
+
+fn signed_extension(identifier: &'static str, ty: MetaType, additional_signed: MetaType) -> v15::SignedExtensionMetadata {
+    v15::SignedExtensionMetadata { identifier, ty, additional_signed }
+}
+
+// A System pallet holding `Events` with the given event records, `Account` and `Number`
+pub fn system_pallet(records: MetaType, calls: MetaType) -> PalletMetadata {
+    let account = StorageEntryType::Map {
+        hashers: vec![StorageHasher::Blake2_128Concat],
+        key: meta_type::<AccountId32>(),
+        value: meta_type::<AccountData>(),
+    };
+    let mut system = pallet("System", 0, meta_type::<SystemEvent>(), vec![
+        entry("Events", StorageEntryType::Plain(records), vec![0]),
+        entry("Account", account, AccountData { free: 0, nonce: 0 }.encode()),
+        entry("Number", StorageEntryType::Plain(meta_type::<u32>()), 0u32.encode()),
+    ]);
+    system.calls = Some(PalletCallMetadata { ty: calls });
+    system.error = Some(PalletErrorMetadata { ty: meta_type::<SystemError>() });
+    system.constants = vec![PalletConstantMetadata { name: "BlockHashCount", ty: meta_type::<u32>(), value: 250u32.encode(), docs: vec![] }];
+    system
+}
+
+// V15 metadata of the pallets, with the extrinsic format and signed extensions of a typical runtime
+pub fn runtime_metadata(pallets: Vec<PalletMetadata>, event_enum: MetaType) -> Vec<u8> {
+    let metadata = RuntimeMetadataV15::new(
+        pallets,
+        ExtrinsicMetadata {
+            version: 4,
+            address_ty: meta_type::<MultiAddress>(),
+            call_ty: meta_type::<()>(),
+            signature_ty: meta_type::<MultiSignature>(),
+            extra_ty: meta_type::<()>(),
+            signed_extensions: vec![
+                signed_extension("CheckNonZeroSender", meta_type::<()>(), meta_type::<()>()),
+                signed_extension("CheckSpecVersion", meta_type::<()>(), meta_type::<u32>()),
+                signed_extension("CheckTxVersion", meta_type::<()>(), meta_type::<u32>()),
+                signed_extension("CheckGenesis", meta_type::<()>(), meta_type::<[u8; 32]>()),
+                signed_extension("CheckMortality", meta_type::<Era>(), meta_type::<[u8; 32]>()),
+                signed_extension("CheckNonce", meta_type::<CheckNonce>(), meta_type::<()>()),
+                signed_extension("CheckWeight", meta_type::<()>(), meta_type::<()>()),
+                signed_extension("ChargeTransactionPayment", meta_type::<ChargeTransactionPayment>(), meta_type::<()>()),
+                signed_extension("CheckMetadataHash", meta_type::<CheckMetadataHash>(), meta_type::<Option<[u8; 32]>>()),
+            ],
+        },
+        meta_type::<()>(),
+        vec![],
+        OuterEnums { call_enum_ty: meta_type::<()>(), event_enum_ty: event_enum, error_enum_ty: meta_type::<()>() },
+        CustomMetadata { map: Default::default() },
+    );
+    RuntimeMetadataPrefixed::from(metadata).encode()
+}
+
+// A small runtime with a System pallet holding `Events` and `Account` and a Balances pallet
+// holding the optional double map `Reserves`
+pub fn test_metadata() -> Vec<u8> {
+    let system = system_pallet(meta_type::<Vec<EventRecord>>(), meta_type::<SystemCall>());
+
+    let reserves = StorageEntryType::Map {
+        hashers: vec![StorageHasher::Twox64Concat, StorageHasher::Identity],
+        key: meta_type::<(u32, AccountId32)>(),
+        value: meta_type::<u128>(),
+    };
+    let reserves = StorageEntryMetadata { modifier: StorageEntryModifier::Optional, ..entry("Reserves", reserves, vec![0]) };
+
+    let mut balances = pallet("Balances", 10, meta_type::<BalancesEvent>(), vec![reserves]);
+    balances.calls = Some(PalletCallMetadata { ty: meta_type::<BalancesCall>() });
+    balances.error = Some(PalletErrorMetadata { ty: meta_type::<BalancesError>() });
+
+    runtime_metadata(vec![system, balances], meta_type::<RuntimeEvent>())
+}
+
+// A stand-in node on a free port that answers every request with the messages `reply` returns,
+// handing back the methods it was called with once the client disconnects
+pub fn ws_node<F: Fn(&Value) -> Vec<Value> + Send + 'static>(reply: F) -> (String, thread::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let mut socket = tungstenite::accept(listener.accept().unwrap().0).unwrap();
+        let mut methods = Vec::new();
+        while let Ok(Message::Text(text)) = socket.read() {
+            let request: Value = serde_json::from_str(&text).unwrap();
+            methods.push(request["method"].as_str().unwrap().to_string());
+            for message in reply(&request) {
+                socket.send(Message::Text(message.to_string())).unwrap();
+            }
+        }
+        methods
+    });
+    (endpoint, handle)
+}
+
+// A minimal wasm module carrying only a `runtime_version` custom section
+pub fn mock_runtime(spec_version: u32) -> Vec<u8> {
+    let version = (
+        "asset-hub-westend".to_string(),
+        "asset-hub-westend".to_string(),
+        1u32,
+        spec_version,
+        0u32,
+        vec![(blake2_64(b"Core"), 5u32), ([1u8; 8], 1u32)],
+        16u32,
+        1u8,
+    )
+        .encode();
+
+    let name = b"runtime_version";
+    let mut section = vec![name.len() as u8];
+    section.extend_from_slice(name);
+    section.extend_from_slice(&version);
+
+    let mut wasm = b"\0asm".to_vec();
+    wasm.extend_from_slice(&[1, 0, 0, 0]);
+    wasm.push(0);
+    let mut size = section.len();
+    loop {
+        let byte = (size & 0x7f) as u8;
+        size >>= 7;
+        if size == 0 {
+            wasm.push(byte);
+            break;
+        }
+        wasm.push(byte | 0x80);
+    }
+    wasm.extend_from_slice(&section);
+    wasm
+}
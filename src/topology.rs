@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use toml::Value;
+use crate::daemon;
+use crate::serve;
+
+pub const DEFAULT_RELAY_BINARY: &str = "./binaries/polkadot";
+// Generates the chain specs of parachains given as a runtime
+pub const CHAIN_SPEC_BUILDER_PATH: &str = "./binaries/chain-spec-builder";
+// Nodes named after these accounts are started with their well-known dev session keys
+pub const DEV_ACCOUNTS: [&str; 6] = ["alice", "bob", "charlie", "dave", "eve", "ferdie"];
+// Set by dot for every node, so they cannot be passed in a node's args
+const MANAGED_FLAGS: [&str; 6] = ["--chain", "--name", "--base-path", "--node-key", "--validator", "--collator"];
+const PORT_FLAGS: [(&str, &str); 3] = [("--rpc-port", "rpc_port"), ("--port", "port"), ("--prometheus-port", "prometheus_port")];
+
+#[derive(Debug, PartialEq)]
+pub struct Topology {
+    pub name: String,
+    pub relaychain: RelayChain,
+    pub parachains: Vec<Parachain>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RelayChain {
+    pub binary: String,
+    // A chain spec file or a chain the relay binary knows, e.g. rococo-local
+    pub chain: String,
+    pub validators: Vec<NodeSpec>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Parachain {
+    pub id: u32,
+    pub binary: String,
+    // Either a chain spec, or a runtime wasm dot generates one from
+    pub chain: Option<String>,
+    pub runtime: Option<String>,
+    pub collators: Vec<NodeSpec>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct NodeSpec {
+    pub name: String,
+    // The chain's args and env come first, the node's own are added to them
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub rpc_port: Option<u16>,
+    pub port: Option<u16>,
+    pub prometheus_port: Option<u16>,
+}
+
+impl Topology {
+    // The name dot manages a node under, e.g. local-alice or local-1000-alice
+    pub fn node_name(&self, parachain: Option<u32>, node: &str) -> String {
+        match parachain {
+            Some(id) => format!("{}-{}-{}", self.name, id, node),
+            None => format!("{}-{}", self.name, node),
+        }
+    }
+}
+
+// Reads a TOML topology, or a JSON one with the same structure. The network is named after the
+// file unless it sets `name`
+pub fn load(path: &Path) -> Result<Topology, Box<dyn Error>> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read topology {:?}: {}", path, e))?;
+    let value: Value = if path.extension().map(|ext| ext == "json").unwrap_or(false) {
+        serde_json::from_str(&content).map_err(|e| format!("Invalid topology {:?}: {}", path, e))?
+    } else {
+        content.parse().map_err(|e: toml::de::Error| format!("Invalid topology {:?}: {}", path, e.message()))?
+    };
+
+    let default_name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("network");
+    let topology = from_value(&value, default_name).map_err(|e| format!("Invalid topology {:?}: {}", path, e))?;
+    let problems = validate(&topology);
+    if !problems.is_empty() {
+        return Err(format!("Invalid topology {:?}:\n  - {}", path, problems.join("\n  - ")).into());
+    }
+    Ok(topology)
+}
+
+pub fn parse(content: &str, default_name: &str) -> Result<Topology, String> {
+    let value: Value = content.parse().map_err(|e: toml::de::Error| e.message().to_string())?;
+    from_value(&value, default_name)
+}
+
+fn from_value(topology: &Value, default_name: &str) -> Result<Topology, String> {
+    let name = match topology.get("name") {
+        Some(name) => name.as_str().ok_or("name must be a string")?.to_string(),
+        None => default_name.to_string(),
+    };
+
+    let relay = topology.get("relaychain").ok_or("missing [relaychain] section")?;
+    let relaychain = RelayChain {
+        binary: optional_string(relay, "relaychain", "binary")?.unwrap_or_else(|| DEFAULT_RELAY_BINARY.to_string()),
+        chain: optional_string(relay, "relaychain", "chain")?.ok_or("relaychain is missing chain")?,
+        validators: nodes(relay, "relaychain", "validators")?,
+    };
+
+    let mut parachains = Vec::new();
+    let sections = match topology.get("parachains") {
+        Some(sections) => sections.as_array().ok_or("parachains must be written as [[parachains]] sections")?.clone(),
+        None => Vec::new(),
+    };
+    for parachain in &sections {
+        let id = parachain.get("id").and_then(Value::as_integer).ok_or("every [[parachains]] needs an integer id")?;
+        let id = u32::try_from(id).map_err(|_| format!("invalid parachain id {}", id))?;
+        let section = format!("parachain {}", id);
+        parachains.push(Parachain {
+            id,
+            binary: optional_string(parachain, &section, "binary")?.unwrap_or_else(|| serve::OMNI_NODE_PATH.to_string()),
+            chain: optional_string(parachain, &section, "chain")?,
+            runtime: optional_string(parachain, &section, "runtime")?,
+            collators: nodes(parachain, &section, "collators")?,
+        });
+    }
+
+    Ok(Topology { name, relaychain, parachains })
+}
+
+// Problems that keep the network from starting, all of them rather than the first one
+pub fn validate(topology: &Topology) -> Vec<String> {
+    let mut problems = Vec::new();
+    let relay = &topology.relaychain;
+
+    check_file(&mut problems, "relaychain binary", &relay.binary);
+    // A bare name like rococo-local is built into the relay binary
+    if relay.chain.ends_with(".json") || relay.chain.contains('/') {
+        check_file(&mut problems, "relaychain chain spec", &relay.chain);
+    }
+
+    let mut para_ids = Vec::new();
+    let mut from_runtime = Vec::new();
+    for parachain in &topology.parachains {
+        let section = format!("parachain {}", parachain.id);
+        if para_ids.contains(&parachain.id) {
+            problems.push(format!("parachain id {} is used more than once", parachain.id));
+        }
+        para_ids.push(parachain.id);
+
+        check_file(&mut problems, &format!("{} binary", section), &parachain.binary);
+        match (&parachain.chain, &parachain.runtime) {
+            (Some(chain), None) => check_file(&mut problems, &format!("{} chain spec", section), chain),
+            (None, Some(runtime)) => {
+                check_file(&mut problems, &format!("{} runtime", section), runtime);
+                from_runtime.push(parachain.id.to_string());
+            }
+            (Some(_), Some(_)) => problems.push(format!("{} sets both chain and runtime, use only one", section)),
+            (None, None) => problems.push(format!("{} needs a chain spec (chain) or a runtime wasm (runtime)", section)),
+        }
+    }
+    if !from_runtime.is_empty() && !Path::new(CHAIN_SPEC_BUILDER_PATH).exists() {
+        problems.push(format!("{} does not exist, it is needed for the chain spec of parachain {}", CHAIN_SPEC_BUILDER_PATH, from_runtime.join(", ")));
+    }
+
+    let nodes = relay.validators.iter().map(|node| (None, node))
+        .chain(topology.parachains.iter().flat_map(|parachain| parachain.collators.iter().map(|node| (Some(parachain.id), node))));
+    let mut names: Vec<String> = Vec::new();
+    let mut ports: HashMap<u16, String> = HashMap::new();
+    for (parachain, node) in nodes {
+        let name = topology.node_name(parachain, &node.name);
+        if let Err(e) = daemon::validate_name(&name) {
+            problems.push(e);
+        }
+        if names.contains(&name) {
+            problems.push(format!("node {} is defined more than once", name));
+        }
+
+        for flag in MANAGED_FLAGS {
+            if serve::has_flag(&node.args, flag) {
+                problems.push(format!("{} sets {}, which dot sets for every node", name, flag));
+            }
+        }
+        for (flag, key) in PORT_FLAGS {
+            if serve::has_flag(&node.args, flag) {
+                problems.push(format!("{} sets {} in its args, use {} instead", name, flag, key));
+            }
+        }
+
+        for (port, key) in [(node.rpc_port, "rpc_port"), (node.port, "port"), (node.prometheus_port, "prometheus_port")] {
+            let port = match port {
+                Some(port) => port,
+                None => continue,
+            };
+            let owner = format!("{} ({})", name, key);
+            match ports.get(&port) {
+                Some(other) => problems.push(format!("port {} is used by both {} and {}", port, other, owner)),
+                None => {
+                    ports.insert(port, owner);
+                }
+            }
+        }
+        names.push(name);
+    }
+    problems
+}
+
+fn check_file(problems: &mut Vec<String>, what: &str, path: &str) {
+    if !Path::new(path).exists() {
+        problems.push(format!("{} {} does not exist", what, path));
+    }
+}
+
+fn optional_string(table: &Value, section: &str, key: &str) -> Result<Option<String>, String> {
+    match table.get(key) {
+        Some(value) => value.as_str().map(|value| Some(value.to_string())).ok_or(format!("{} {} must be a string", section, key)),
+        None => Ok(None),
+    }
+}
+
+fn string_list(table: &Value, section: &str, key: &str) -> Result<Vec<String>, String> {
+    match table.get(key) {
+        Some(Value::Array(values)) => values
+            .iter()
+            .map(|value| value.as_str().map(String::from))
+            .collect::<Option<_>>()
+            .ok_or(format!("{} {} must be a list of strings", section, key)),
+        Some(_) => Err(format!("{} {} must be a list of strings", section, key)),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn env_table(table: &Value, section: &str) -> Result<Vec<(String, String)>, String> {
+    match table.get("env") {
+        Some(Value::Table(env)) => env
+            .iter()
+            .map(|(key, value)| value.as_str().map(|value| (key.clone(), value.to_string())))
+            .collect::<Option<_>>()
+            .ok_or(format!("{} env values must be strings", section)),
+        Some(_) => Err(format!("{} env must be a table of strings", section)),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn optional_port(table: &Value, section: &str, key: &str) -> Result<Option<u16>, String> {
+    match table.get(key) {
+        Some(value) => value
+            .as_integer()
+            .and_then(|port| u16::try_from(port).ok())
+            .filter(|port| *port > 0)
+            .map(Some)
+            .ok_or(format!("{} {} must be a port number", section, key)),
+        None => Ok(None),
+    }
+}
+
+// A number of nodes named after the dev accounts, or a list of node names and/or tables with a
+// name and the node's own args, env and ports. The section's args and env apply to every node
+fn nodes(table: &Value, section: &str, key: &str) -> Result<Vec<NodeSpec>, String> {
+    let args = string_list(table, section, "args")?;
+    let env = env_table(table, section)?;
+    let node = |name: String| NodeSpec { name, args: args.clone(), env: env.clone(), ..Default::default() };
+
+    let nodes: Vec<NodeSpec> = match table.get(key) {
+        Some(Value::Integer(count)) if *count > 0 => (0..*count as usize).map(|index| node(default_node_name(index))).collect(),
+        Some(Value::Array(entries)) => {
+            let mut nodes = Vec::new();
+            for (index, entry) in entries.iter().enumerate() {
+                let context = format!("{} {}[{}]", section, key, index);
+                nodes.push(match entry {
+                    Value::String(name) => node(name.clone()),
+                    Value::Table(_) => {
+                        let name = optional_string(entry, &context, "name")?.ok_or(format!("{} is missing name", context))?;
+                        let mut spec = node(name);
+                        spec.args.extend(string_list(entry, &context, "args")?);
+                        for (key, value) in env_table(entry, &context)? {
+                            spec.env.retain(|(existing, _)| *existing != key);
+                            spec.env.push((key, value));
+                        }
+                        spec.rpc_port = optional_port(entry, &context, "rpc_port")?;
+                        spec.port = optional_port(entry, &context, "port")?;
+                        spec.prometheus_port = optional_port(entry, &context, "prometheus_port")?;
+                        spec
+                    }
+                    _ => return Err(format!("{} must be a node name or a table", context)),
+                });
+            }
+            nodes
+        }
+        Some(_) => return Err(format!("{} {} must be a number of nodes or a list of nodes", section, key)),
+        None => return Err(format!("{} is missing {}", section, key)),
+    };
+    if nodes.is_empty() {
+        return Err(format!("{} needs at least one node in {}", section, key));
+    }
+    Ok(nodes)
+}
+
+fn default_node_name(index: usize) -> String {
+    match DEV_ACCOUNTS.get(index) {
+        Some(account) => account.to_string(),
+        None => format!("node-{}", index),
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOPOLOGY: &str = r#"
+        [relaychain]
+        chain = "rococo-local"
+        validators = 2
+
+        [[parachains]]
+        id = 1000
+        chain = "./chain-specs/chain_spec.json"
+        args = ["--pool-limit", "100"]
+        env = { RUST_LOG = "info" }
+        collators = [
+            "alice",
+            { name = "bob", args = ["--force-authoring"], env = { RUST_LOG = "debug" }, rpc_port = 9988 },
+        ]
+    "#;
+
+    fn names(nodes: &[NodeSpec]) -> Vec<&str> {
+        nodes.iter().map(|node| node.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_topology() {
+        let topology = parse(TOPOLOGY, "local").unwrap();
+        assert_eq!(topology.name, "local");
+        assert_eq!(topology.relaychain.binary, DEFAULT_RELAY_BINARY);
+        assert_eq!(topology.relaychain.chain, "rococo-local");
+        assert_eq!(names(&topology.relaychain.validators), ["alice", "bob"]);
+
+        let parachain = &topology.parachains[0];
+        assert_eq!((parachain.id, parachain.binary.as_str()), (1000, serve::OMNI_NODE_PATH));
+        assert_eq!(parachain.chain.as_deref(), Some("./chain-specs/chain_spec.json"));
+        assert_eq!(parachain.collators[0], NodeSpec {
+            name: "alice".to_string(),
+            args: vec!["--pool-limit".to_string(), "100".to_string()],
+            env: vec![("RUST_LOG".to_string(), "info".to_string())],
+            ..Default::default()
+        });
+        assert_eq!(parachain.collators[1], NodeSpec {
+            name: "bob".to_string(),
+            args: vec!["--pool-limit".to_string(), "100".to_string(), "--force-authoring".to_string()],
+            env: vec![("RUST_LOG".to_string(), "debug".to_string())],
+            rpc_port: Some(9988),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_parse_topology_errors() {
+        assert_eq!(parse("name = \"x\"", "local").unwrap_err(), "missing [relaychain] section");
+        assert_eq!(parse("[relaychain]\nvalidators = 1", "local").unwrap_err(), "relaychain is missing chain");
+        assert_eq!(parse("[relaychain]\nchain = \"rococo-local\"\nvalidators = []", "local").unwrap_err(), "relaychain needs at least one node in validators");
+        let missing_id = "[relaychain]\nchain = \"rococo-local\"\nvalidators = 1\n[[parachains]]\nchain = \"para.json\"\ncollators = 1";
+        assert_eq!(parse(missing_id, "local").unwrap_err(), "every [[parachains]] needs an integer id");
+        let bad_port = "[relaychain]\nchain = \"rococo-local\"\nvalidators = [{ name = \"alice\", port = 70000 }]";
+        assert_eq!(parse(bad_port, "local").unwrap_err(), "relaychain validators[0] port must be a port number");
+        assert!(parse("[relaychain", "local").is_err());
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let topology = parse(r#"
+            [relaychain]
+            binary = "./missing/polkadot"
+            chain = "./missing/relay.json"
+            validators = [{ name = "alice", rpc_port = 9944 }, { name = "alice", port = 9944 }]
+
+            [[parachains]]
+            id = 1000
+            chain = "./missing/para.json"
+            runtime = "./missing/para.wasm"
+            collators = [{ name = "bob", args = ["--rpc-port", "1", "--base-path=/tmp"] }]
+
+            [[parachains]]
+            id = 1000
+            collators = ["bad name"]
+
+            [[parachains]]
+            id = 2000
+            runtime = "./missing/para.wasm"
+            collators = 1
+        "#, "local").unwrap();
+
+        let problems = validate(&topology);
+        for expected in [
+            "relaychain binary ./missing/polkadot does not exist",
+            "relaychain chain spec ./missing/relay.json does not exist",
+            "parachain 1000 sets both chain and runtime, use only one",
+            "parachain id 1000 is used more than once",
+            "parachain 1000 needs a chain spec (chain) or a runtime wasm (runtime)",
+            "node local-alice is defined more than once",
+            "port 9944 is used by both local-alice (rpc_port) and local-alice (port)",
+            "local-1000-bob sets --base-path, which dot sets for every node",
+            "local-1000-bob sets --rpc-port in its args, use rpc_port instead",
+            "Invalid node name \"local-1000-bad name\": use letters, digits, `-` and `_`",
+        ] {
+            assert!(problems.contains(&expected.to_string()), "missing {:?} in {:#?}", expected, problems);
+        }
+        // Only parachain 2000 is generated from its runtime
+        let builder = format!("{} does not exist, it is needed for the chain spec of parachain 2000", CHAIN_SPEC_BUILDER_PATH);
+        assert_eq!(problems.contains(&builder), !Path::new(CHAIN_SPEC_BUILDER_PATH).exists());
+    }
+
+    #[test]
+    fn test_load_json_topology() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("polkadot");
+        fs::write(&binary, "").unwrap();
+        let path = dir.path().join("testnet.json");
+        let topology = serde_json::json!({ "relaychain": { "binary": binary, "chain": "westend-local", "validators": ["alice", "bob"] } });
+        fs::write(&path, topology.to_string()).unwrap();
+
+        let topology = load(&path).unwrap();
+        assert_eq!(topology.name, "testnet");
+        assert_eq!(names(&topology.relaychain.validators), ["alice", "bob"]);
+
+        fs::write(&path, r#"{ "relaychain": { "binary": "./missing", "chain": "westend-local", "validators": 1 } }"#).unwrap();
+        let error = load(&path).unwrap_err().to_string();
+        assert!(error.ends_with(":\n  - relaychain binary ./missing does not exist"), "{}", error);
+    }
+
+    #[test]
+    fn test_default_node_names() {
+        assert_eq!(default_node_name(0), "alice");
+        assert_eq!(default_node_name(5), "ferdie");
+        assert_eq!(default_node_name(6), "node-6");
+    }
+}
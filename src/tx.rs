@@ -0,0 +1,409 @@
+use std::error::Error;
+use std::io::Write;
+use parity_scale_codec::{Compact, Encode};
+use scale_info::TypeDef;
+use serde_json::{json, Map, Value};
+use crate::hashing::{blake2_256, from_hex, to_hex};
+use crate::keyring::Signer;
+use crate::metadata::{self, Metadata};
+use crate::rpc::{self, WsClient};
+use crate::scale;
+use crate::ss58;
+use crate::watch;
+
+// Version 4 extrinsics with the top bit set for signed ones
+const SIGNED_EXTRINSIC_V4: u8 = 0x84;
+const EXTRINSIC_VERSION: u8 = 4;
+
+// Payloads longer than this are signed by their blake2-256 hash
+const MAX_UNHASHED_PAYLOAD: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wait {
+    InBlock,
+    Finalized,
+}
+
+// What a signed extrinsic commits to besides its call
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainState {
+    pub genesis_hash: [u8; 32],
+    pub spec_version: u32,
+    pub transaction_version: u32,
+    pub nonce: u32,
+}
+
+// The block a transaction is in and the events it emitted there
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inclusion {
+    pub number: u64,
+    pub block: String,
+    pub events: Vec<Value>,
+}
+
+// Encodes `Pallet.call` with positional arguments matched to the call's fields in order. Calls may
+// be named as in the metadata or in camelCase, e.g. transferKeepAlive
+pub fn encode_call(metadata: &Metadata, pallet: &str, call: &str, args: &[Value]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let pallet = metadata.pallet(pallet)?;
+    let calls = pallet.calls.ok_or_else(|| format!("Pallet {} has no calls", pallet.name))?;
+    let Some(TypeDef::Variant(variants)) = metadata.types.resolve(calls).map(|ty| &ty.type_def) else {
+        return Err(format!("Pallet {} has no calls", pallet.name).into());
+    };
+    let normalize = |name: &str| name.replace('_', "").to_lowercase();
+    let variant = variants.variants.iter().find(|variant| normalize(&variant.name) == normalize(call))
+        .ok_or_else(|| format!("Pallet {} has no call {}", pallet.name, call))?;
+
+    let fields = &variant.fields;
+    if args.len() != fields.len() {
+        let names = metadata::fields(&metadata.types, fields);
+        return Err(format!(
+            "{}.{} takes {} argument{}{} but {} {} given",
+            pallet.name,
+            variant.name,
+            fields.len(),
+            if fields.len() == 1 { "" } else { "s" },
+            if names.is_empty() { String::new() } else { format!(" ({})", names.join(", ")) },
+            args.len(),
+            if args.len() == 1 { "was" } else { "were" }
+        ).into());
+    }
+    let values = match fields.as_slice() {
+        [] => Value::Null,
+        [field] if field.name.is_none() => args[0].clone(),
+        _ if fields.iter().all(|field| field.name.is_some()) => {
+            Value::Object(fields.iter().zip(args).map(|(field, arg)| (field.name.clone().expect("named field"), arg.clone())).collect::<Map<_, _>>())
+        }
+        _ => Value::Array(args.to_vec()),
+    };
+
+    let mut encoded = vec![pallet.index];
+    scale::encode_value(&metadata.types, calls, &json!({ variant.name.clone(): values }), &mut encoded)
+        .map_err(|e| format!("Invalid arguments for {}.{}: {}", pallet.name, variant.name, e))?;
+    Ok(encoded)
+}
+
+// The data a signed extension adds to the extrinsic and to the signed payload. Transactions are
+// immortal and pay no tip
+fn extension_values(identifier: &str, state: &ChainState) -> (Value, Value) {
+    let genesis_hash = json!(to_hex(&state.genesis_hash));
+    match identifier {
+        "CheckSpecVersion" => (Value::Null, json!(state.spec_version)),
+        "CheckTxVersion" => (Value::Null, json!(state.transaction_version)),
+        "CheckGenesis" => (Value::Null, genesis_hash),
+        "CheckMortality" | "CheckEra" => (json!("Immortal"), genesis_hash),
+        "CheckNonce" => (json!(state.nonce), Value::Null),
+        "ChargeTransactionPayment" => (json!(0), Value::Null),
+        "ChargeAssetTxPayment" => (json!({ "tip": 0, "asset_id": null }), Value::Null),
+        "CheckMetadataHash" => (json!({ "mode": "Disabled" }), Value::Null),
+        // CheckNonZeroSender, CheckWeight and the like carry no data
+        _ => (Value::Null, Value::Null),
+    }
+}
+
+// Builds the signed extrinsic for an encoded call, with the signed extensions the metadata lists
+pub fn sign_extrinsic(metadata: &Metadata, call: &[u8], signer: &Signer, state: &ChainState) -> Result<Vec<u8>, Box<dyn Error>> {
+    let extrinsic = &metadata.extrinsic;
+    if extrinsic.version != EXTRINSIC_VERSION {
+        return Err(format!("Unsupported extrinsic version {}, only {} is supported", extrinsic.version, EXTRINSIC_VERSION).into());
+    }
+    let types = &metadata.types;
+
+    let (mut extra, mut additional) = (Vec::new(), Vec::new());
+    for extension in &extrinsic.signed_extensions {
+        let (extra_value, additional_value) = extension_values(&extension.identifier, state);
+        scale::encode_value(types, extension.extra, &extra_value, &mut extra)
+            .and_then(|_| scale::encode_value(types, extension.additional_signed, &additional_value, &mut additional))
+            .map_err(|e| format!("Unsupported signed extension {}: {}", extension.identifier, e))?;
+    }
+    let mut payload = [call, &extra, &additional].concat();
+    if payload.len() > MAX_UNHASHED_PAYLOAD {
+        payload = blake2_256(&payload).to_vec();
+    }
+    let signature = signer.sign(&payload);
+
+    let address_type = extrinsic.address.ok_or("The metadata does not describe the extrinsic address type")?;
+    let signature_type = extrinsic.signature.ok_or("The metadata does not describe the extrinsic signature type")?;
+    // A MultiSignature names the scheme of the signature
+    let signature = match types.resolve(signature_type).map(|ty| &ty.type_def) {
+        Some(TypeDef::Variant(_)) => json!({ "Sr25519": to_hex(&signature) }),
+        _ => json!(to_hex(&signature)),
+    };
+    let mut body = vec![SIGNED_EXTRINSIC_V4];
+    scale::encode_value(types, address_type, &json!(ss58::encode(ss58::GENERIC_PREFIX, &signer.account())), &mut body)?;
+    scale::encode_value(types, signature_type, &signature, &mut body)?;
+    body.extend(extra);
+    body.extend(call);
+
+    let mut encoded = Compact(body.len() as u32).encode();
+    encoded.extend(body);
+    Ok(encoded)
+}
+
+// Signs `Pallet.call` with the signer's next nonce, submits it and reports its progress and events
+// until it is in a block or finalized
+pub fn send<W: Write>(endpoint: &str, pallet: &str, call: &str, args: &[Value], signer: &Signer, wait: Wait, out: &mut W) -> Result<Inclusion, Box<dyn Error>> {
+    let mut client = WsClient::connect(&rpc::ws_endpoint(endpoint))?;
+    let (_, metadata) = metadata::from_rpc(&client.request("state_getMetadata", json!([]))?)?;
+    let encoded_call = encode_call(&metadata, pallet, call, args)?;
+    submit_call(&mut client, &metadata, &encoded_call, signer, wait, out)
+}
+
+// Like send, for an encoded call on an open connection
+pub fn submit_call<W: Write>(client: &mut WsClient, metadata: &Metadata, encoded_call: &[u8], signer: &Signer, wait: Wait, out: &mut W) -> Result<Inclusion, Box<dyn Error>> {
+    let account = ss58::encode(ss58::GENERIC_PREFIX, &signer.account());
+    let genesis_hash = client.request("chain_getBlockHash", json!([0]))?;
+    let genesis_hash = from_hex(genesis_hash.as_str().ok_or("The node returned no genesis hash")?)?;
+    let version = client.request("state_getRuntimeVersion", json!([]))?;
+    let nonce = client.request("system_accountNextIndex", json!([account]))?;
+    let state = ChainState {
+        genesis_hash: genesis_hash.try_into().map_err(|_| "Invalid genesis hash")?,
+        spec_version: version["specVersion"].as_u64().ok_or("The node returned no spec version")? as u32,
+        transaction_version: version["transactionVersion"].as_u64().ok_or("The node returned no transaction version")? as u32,
+        nonce: nonce.as_u64().ok_or("The node returned no account nonce")? as u32,
+    };
+    let extrinsic = sign_extrinsic(metadata, encoded_call, signer, &state)?;
+    writeln!(out, "Signed by {} with nonce {}", account, state.nonce)?;
+    submit(client, metadata, &extrinsic, wait, out)
+}
+
+fn submit<W: Write>(client: &mut WsClient, metadata: &Metadata, extrinsic: &[u8], wait: Wait, out: &mut W) -> Result<Inclusion, Box<dyn Error>> {
+    let subscription = client.start_subscription("author_submitAndWatchExtrinsic", json!([to_hex(extrinsic)]))?;
+    writeln!(out, "Submitted {}", to_hex(&blake2_256(extrinsic)))?;
+
+    let mut included: Option<Inclusion> = None;
+    loop {
+        let (id, status) = client.next_notification()?;
+        if id != subscription {
+            continue;
+        }
+        let (name, block) = match &status {
+            Value::String(name) => (name.as_str(), None),
+            Value::Object(object) => object.iter().next().map(|(name, block)| (name.as_str(), block.as_str())).unwrap_or_default(),
+            _ => ("", None),
+        };
+        match (name, block) {
+            ("ready", _) | ("broadcast", _) => {}
+            ("future", _) => writeln!(out, "Waiting for transactions with lower nonces")?,
+            ("inBlock", Some(block)) | ("finalized", Some(block)) if included.as_ref().map(|inclusion| inclusion.block.as_str()) != Some(block) => {
+                let inclusion = report_inclusion(client, metadata, extrinsic, block, out)?;
+                if wait == Wait::InBlock {
+                    return Ok(inclusion);
+                }
+                if name == "finalized" {
+                    writeln!(out, "Finalized")?;
+                    return Ok(inclusion);
+                }
+                included = Some(inclusion);
+            }
+            ("finalized", Some(_)) => {
+                writeln!(out, "Finalized")?;
+                return Ok(included.expect("finalized in the block it was reported in"));
+            }
+            ("retracted", Some(block)) => writeln!(out, "Retracted from block {}", block)?,
+            ("usurped", _) => return Err("The transaction was replaced by another one with the same nonce".into()),
+            ("dropped", _) => return Err("The transaction was dropped from the pool".into()),
+            ("invalid", _) => return Err("The transaction is invalid".into()),
+            ("finalityTimeout", _) => return Err("Timed out waiting for the block to be finalized".into()),
+            _ => writeln!(out, "Status {}", status)?,
+        }
+    }
+}
+
+// Prints the block the extrinsic is in with the events it emitted, failing when it did not succeed
+fn report_inclusion<W: Write>(client: &mut WsClient, metadata: &Metadata, extrinsic: &[u8], block: &str, out: &mut W) -> Result<Inclusion, Box<dyn Error>> {
+    let response = client.request("chain_getBlock", json!([block]))?;
+    let number = rpc::parse_hex_number(response["block"]["header"]["number"].as_str().unwrap_or_default())?;
+    writeln!(out, "In block #{} {}", number, block)?;
+
+    let hex = to_hex(extrinsic);
+    let index = response["block"]["extrinsics"].as_array()
+        .and_then(|extrinsics| extrinsics.iter().position(|candidate| candidate.as_str() == Some(hex.as_str())))
+        .ok_or_else(|| format!("Block {} does not contain the transaction", block))?;
+    let events_key = to_hex(&metadata::storage_prefix("System", "Events"));
+    let events = client.request("state_getStorage", json!([events_key, block]))?;
+    let events = events.as_str().map(from_hex).transpose()?;
+
+    let mut failure = None;
+    let mut emitted = Vec::new();
+    for mut record in watch::decode_events(metadata, events.as_deref())? {
+        if record["phase"] != json!({ "ApplyExtrinsic": index }) {
+            continue;
+        }
+        let name = watch::event_name(&record["event"]);
+        let fields = record["event"].as_object().and_then(|pallet| pallet.values().next()).and_then(Value::as_object).and_then(|event| event.values().next());
+        match fields {
+            Some(fields) if !fields.is_null() => writeln!(out, "  {} {}", name, fields)?,
+            _ => writeln!(out, "  {}", name)?,
+        }
+        if name == "System.ExtrinsicFailed" {
+            failure = Some(dispatch_error(metadata, &fields.cloned().unwrap_or_default()["dispatch_error"]));
+        }
+        emitted.push(record["event"].take());
+    }
+    match failure {
+        Some(error) => Err(format!("The transaction failed: {}", error).into()),
+        None => Ok(Inclusion { number, block: block.to_string(), events: emitted }),
+    }
+}
+
+// Module errors are named by the pallet index and the first byte of the error
+pub fn dispatch_error(metadata: &Metadata, error: &Value) -> String {
+    let module = &error["Module"];
+    let code = match &module["error"] {
+        Value::String(bytes) => from_hex(bytes).ok().and_then(|bytes| bytes.first().copied()),
+        code => code.as_u64().map(|code| code as u8),
+    };
+    let pallet = module["index"].as_u64().and_then(|index| metadata.pallets.iter().find(|pallet| pallet.index as u64 == index));
+    if let (Some(pallet), Some(code)) = (pallet, code) {
+        let errors = pallet.error.and_then(|ty| metadata.types.resolve(ty)).map(|ty| &ty.type_def);
+        if let Some(TypeDef::Variant(errors)) = errors {
+            if let Some(variant) = errors.variants.iter().find(|variant| variant.index == code) {
+                return format!("{}.{}", pallet.name, variant.name);
+            }
+        }
+    }
+    match error {
+        Value::String(name) => name.clone(),
+        error => error.to_string(),
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use schnorrkel::{PublicKey, Signature};
+    use std::sync::{Arc, Mutex};
+
+    const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+    fn alice() -> [u8; 32] {
+        ss58::decode(ALICE).unwrap().1
+    }
+
+    #[test]
+    fn test_encode_call() {
+        let metadata = metadata::decode(&test_metadata()).unwrap();
+        let remark = encode_call(&metadata, "System", "remark", &[json!("hi")]).unwrap();
+        assert_eq!(remark, [vec![0], SystemCall::remark { remark: b"hi".to_vec() }.encode()].concat());
+
+        let transfer = encode_call(&metadata, "balances", "transferKeepAlive", &[json!(ALICE), json!(1000)]).unwrap();
+        let expected = BalancesCall::transfer_keep_alive { dest: MultiAddress::Id(AccountId32(alice())), value: 1000 };
+        assert_eq!(transfer, [vec![10], expected.encode()].concat());
+    }
+
+    #[test]
+    fn test_encode_u128_argument() {
+        // 100 tokens of a chain with 18 decimals, as given on the command line
+        let metadata = metadata::decode(&test_metadata()).unwrap();
+        let args = rpc::parse_params(&[ALICE, "100000000000000000000"]);
+        let transfer = encode_call(&metadata, "Balances", "transfer_keep_alive", args.as_array().unwrap()).unwrap();
+        let expected = BalancesCall::transfer_keep_alive { dest: MultiAddress::Id(AccountId32(alice())), value: 100_000_000_000_000_000_000 };
+        assert_eq!(transfer, [vec![10], expected.encode()].concat());
+    }
+
+    #[test]
+    fn test_encode_call_errors() {
+        let metadata = metadata::decode(&test_metadata()).unwrap();
+        let error = |call: &str, args: &[Value]| encode_call(&metadata, "Balances", call, args).unwrap_err().to_string();
+        assert_eq!(error("transfer_keep_alive", &[json!(ALICE)]), "Balances.transfer_keep_alive takes 2 arguments (dest: MultiAddress, value: Compact<u128>) but 1 was given");
+        assert_eq!(error("transfer_all", &[]), "Pallet Balances has no call transfer_all");
+        assert_eq!(error("transfer_keep_alive", &[json!(ALICE), json!("lots")]), "Invalid arguments for Balances.transfer_keep_alive: Expected Compact<u128> but got \"lots\"");
+    }
+
+    #[test]
+    fn test_sign_extrinsic() {
+        let metadata = metadata::decode(&test_metadata()).unwrap();
+        let call = encode_call(&metadata, "System", "remark", &[json!("hi")]).unwrap();
+        let state = ChainState { genesis_hash: [9; 32], spec_version: 1_000, transaction_version: 2, nonce: 5 };
+        let extrinsic = sign_extrinsic(&metadata, &call, &Signer::from_suri("//Alice").unwrap(), &state).unwrap();
+
+        // Era, nonce, tip and metadata hash mode
+        let extra = [0u8, 5 << 2, 0, 0];
+        let body = &extrinsic[2..];
+        assert_eq!(extrinsic[..2], Compact(body.len() as u32).encode()[..]);
+        assert_eq!(body[0], SIGNED_EXTRINSIC_V4);
+        assert_eq!(body[1..34], [&[0u8][..], &alice()].concat()[..]);
+        assert_eq!(body[34], 1, "an Sr25519 MultiSignature");
+        assert_eq!(body[99..], [&extra[..], &call].concat()[..]);
+
+        // Spec and transaction version, genesis hash for CheckGenesis and CheckMortality, no metadata hash
+        let additional = [1_000u32.encode(), 2u32.encode(), vec![9; 32], vec![9; 32], vec![0]].concat();
+        let payload = [&call[..], &extra, &additional].concat();
+        let signature = Signature::from_bytes(&body[35..99]).unwrap();
+        assert!(PublicKey::from_bytes(&alice()).unwrap().verify_simple(b"substrate", &payload, &signature).is_ok());
+    }
+
+    // A stand-in node including whatever is submitted in block #300 with the given events
+    fn node(events: Vec<u8>) -> String {
+        let submitted = Arc::new(Mutex::new(String::new()));
+        let (endpoint, _) = ws_node(move |request| {
+            let reply = |result: Value| json!({ "jsonrpc": "2.0", "id": request["id"], "result": result });
+            let status = |result: Value| json!({ "jsonrpc": "2.0", "method": "author_extrinsicUpdate", "params": { "subscription": "tx", "result": result } });
+            match request["method"].as_str().unwrap() {
+                "state_getMetadata" => vec![reply(json!(to_hex(&test_metadata())))],
+                "chain_getBlockHash" => vec![reply(json!(to_hex(&[9; 32])))],
+                "state_getRuntimeVersion" => vec![reply(json!({ "specVersion": 1000, "transactionVersion": 2 }))],
+                "system_accountNextIndex" => vec![reply(json!(5))],
+                "author_submitAndWatchExtrinsic" => {
+                    *submitted.lock().unwrap() = request["params"][0].as_str().unwrap().to_string();
+                    vec![reply(json!("tx")), status(json!("ready")), status(json!({ "inBlock": "0xb1" })), status(json!({ "finalized": "0xb1" }))]
+                }
+                "chain_getBlock" => vec![reply(json!({ "block": { "header": { "number": "0x12c" }, "extrinsics": ["0x00", *submitted.lock().unwrap()] } }))],
+                "state_getStorage" => vec![reply(json!(to_hex(&events)))],
+                _ => vec![reply(Value::Null)],
+            }
+        });
+        endpoint
+    }
+
+    fn record(index: u32, event: RuntimeEvent) -> EventRecord {
+        EventRecord { phase: Phase::ApplyExtrinsic(index), event, topics: vec![] }
+    }
+
+    #[test]
+    fn test_send() {
+        let events = vec![
+            record(0, RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 })),
+            record(1, RuntimeEvent::Balances(BalancesEvent::Transfer { amount: 1000 })),
+            record(1, RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 2 })),
+            EventRecord { phase: Phase::Finalization, event: RuntimeEvent::System(SystemEvent::CodeUpdated), topics: vec![] },
+        ];
+        let endpoint = node(events.encode());
+
+        let mut out = Vec::new();
+        let signer = Signer::from_suri("//Alice").unwrap();
+        let inclusion = send(&endpoint, "Balances", "transfer_keep_alive", &[json!(ALICE), json!(1000)], &signer, Wait::Finalized, &mut out).unwrap();
+        assert_eq!((inclusion.number, inclusion.block.as_str()), (300, "0xb1"));
+        assert_eq!(inclusion.events, [json!({ "Balances": { "Transfer": { "amount": 1000 } } }), json!({ "System": { "ExtrinsicSuccess": { "weight": 2 } } })]);
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], format!("Signed by {} with nonce 5", ALICE));
+        assert!(lines[1].starts_with("Submitted 0x"));
+        assert_eq!(lines[2..], ["In block #300 0xb1", "  Balances.Transfer {\"amount\":1000}", "  System.ExtrinsicSuccess {\"weight\":2}", "Finalized"]);
+    }
+
+    #[test]
+    fn test_send_failure() {
+        let failed = SystemEvent::ExtrinsicFailed { dispatch_error: DispatchError::Module(ModuleError { index: 10, error: [1, 0, 0, 0] }) };
+        let endpoint = node(vec![record(1, RuntimeEvent::System(failed))].encode());
+
+        let mut out = Vec::new();
+        let signer = Signer::from_suri("//Alice").unwrap();
+        let error = send(&endpoint, "Balances", "transfer_keep_alive", &[json!(ALICE), json!(1000)], &signer, Wait::InBlock, &mut out).unwrap_err();
+        assert_eq!(error.to_string(), "The transaction failed: Balances.InsufficientBalance");
+        assert!(String::from_utf8(out).unwrap().contains("  System.ExtrinsicFailed {\"dispatch_error\":{\"Module\":{\"error\":\"0x01000000\",\"index\":10}}}\n"));
+    }
+
+    #[test]
+    fn test_dispatch_error() {
+        let metadata = metadata::decode(&test_metadata()).unwrap();
+        assert_eq!(dispatch_error(&metadata, &json!("BadOrigin")), "BadOrigin");
+        assert_eq!(dispatch_error(&metadata, &json!({ "Module": { "index": 0, "error": 0 } })), "System.CallFiltered");
+        assert_eq!(dispatch_error(&metadata, &json!({ "Module": { "index": 7, "error": "0x00000000" } })), "{\"Module\":{\"error\":\"0x00000000\",\"index\":7}}");
+    }
+}
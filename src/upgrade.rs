@@ -0,0 +1,322 @@
+use std::error::Error;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use crate::hashing::{blake2_256, from_hex, to_hex};
+use crate::keyring::Signer;
+use crate::metadata::{self, Metadata};
+use crate::rpc::{self, WsClient};
+use crate::runtime;
+use crate::ss58;
+use crate::storage;
+use crate::tx::{self, Inclusion, Wait};
+use crate::watch;
+
+// Upgrades the runtime of a running chain to `code` through its Sudo pallet, with `System.set_code`
+// or, when `authorize` is set, by authorizing the code hash and then applying the upgrade. Waits
+// for the `System.CodeUpdated` event and returns the spec_version the chain then runs
+pub fn upgrade<W: Write>(endpoint: &str, code: &[u8], signer: &Signer, authorize: bool, timeout: Duration, out: &mut W) -> Result<u32, Box<dyn Error>> {
+    let new = runtime::inspect_blob(code)?;
+    let mut client = WsClient::connect(&rpc::ws_endpoint(endpoint))?;
+    let (_, metadata) = metadata::from_rpc(&client.request("state_getMetadata", json!([]))?)?;
+
+    // The runtime rejects these too, but only once the upgrade is dispatched
+    let version = client.request("state_getRuntimeVersion", json!([]))?;
+    let spec_name = version["specName"].as_str().ok_or("The node returned no spec name")?;
+    let spec_version = version["specVersion"].as_u64().ok_or("The node returned no spec version")?;
+    if new.spec_name != spec_name {
+        return Err(format!("The wasm is a {} runtime but the node runs {}", new.spec_name, spec_name).into());
+    }
+    if u64::from(new.spec_version) <= spec_version {
+        return Err(format!("The wasm has spec_version {}, which must be higher than the running {}", new.spec_version, spec_version).into());
+    }
+    check_sudo_key(&mut client, &metadata, signer)?;
+    writeln!(out, "Upgrading {} from spec_version {} to {}", spec_name, spec_version, new.spec_version)?;
+
+    let inclusion = if authorize {
+        let code_hash = to_hex(&blake2_256(code));
+        sudo(&mut client, &metadata, json!({ "System": { "authorize_upgrade": { "code_hash": code_hash } } }), signer, out)?;
+        let apply = tx::encode_call(&metadata, "System", "apply_authorized_upgrade", &[json!(to_hex(code))])?;
+        tx::submit_call(&mut client, &metadata, &apply, signer, Wait::InBlock, out)?
+    } else {
+        sudo(&mut client, &metadata, json!({ "System": { "set_code": { "code": to_hex(code) } } }), signer, out)?
+    };
+
+    let (number, block) = if inclusion.events.iter().any(is_code_updated) {
+        (inclusion.number, inclusion.block)
+    } else {
+        // Parachains enact an upgrade some blocks later, once the relay chain allows it
+        writeln!(out, "Waiting for the chain to enact the upgrade")?;
+        wait_for_code_update(&mut client, &metadata, inclusion.number, Instant::now() + timeout)?
+    };
+    let version = client.request("state_getRuntimeVersion", json!([block]))?;
+    let upgraded = version["specVersion"].as_u64().ok_or("The node returned no spec version")?;
+    if upgraded != u64::from(new.spec_version) {
+        return Err(format!("The chain runs spec_version {} after the upgrade instead of {}", upgraded, new.spec_version).into());
+    }
+    writeln!(out, "Upgraded to spec_version {} in block #{} {}", upgraded, number, block)?;
+    Ok(new.spec_version)
+}
+
+// Sudo only dispatches calls signed by its key
+fn check_sudo_key(client: &mut WsClient, metadata: &Metadata, signer: &Signer) -> Result<(), Box<dyn Error>> {
+    metadata.pallet("Sudo").map_err(|_| "The runtime has no Sudo pallet to upgrade it with")?;
+    let (_, entry) = metadata.storage_entry("Sudo", "Key")?;
+    let key = storage::storage_key(metadata, "Sudo", "Key", &[])?;
+    let sudo_key = storage::decode_storage(metadata, entry, &client.request("state_getStorage", json!([to_hex(&key)]))?)?;
+
+    let account = ss58::encode(ss58::GENERIC_PREFIX, &signer.account());
+    match sudo_key.as_str() {
+        Some(sudo_key) if sudo_key == account => Ok(()),
+        Some(sudo_key) => Err(format!("{} is not the sudo key of the chain, {} is", account, sudo_key).into()),
+        None => Err("The chain has no sudo key".into()),
+    }
+}
+
+// Dispatches a runtime call as root. The sudo transaction succeeds whatever the call does, so the
+// outcome of the call is taken from the Sudo.Sudid event
+fn sudo<W: Write>(client: &mut WsClient, metadata: &Metadata, call: Value, signer: &Signer, out: &mut W) -> Result<Inclusion, Box<dyn Error>> {
+    let encoded = tx::encode_call(metadata, "Sudo", "sudo", &[call])?;
+    let inclusion = tx::submit_call(client, metadata, &encoded, signer, Wait::InBlock, out)?;
+    let result = inclusion.events.iter().find_map(|event| event["Sudo"].get("Sudid")).map(|sudid| &sudid["sudo_result"]);
+    match result.and_then(|result| result.get("Err")) {
+        Some(error) => Err(format!("The upgrade failed: {}", tx::dispatch_error(metadata, error)).into()),
+        None => Ok(inclusion),
+    }
+}
+
+fn is_code_updated(event: &Value) -> bool {
+    watch::event_name(event) == "System.CodeUpdated"
+}
+
+// Checks the events of every block after `after` as new blocks come in, returning the number and
+// hash of the block that updated the code
+fn wait_for_code_update(client: &mut WsClient, metadata: &Metadata, after: u64, deadline: Instant) -> Result<(u64, String), Box<dyn Error>> {
+    let heads = client.start_subscription("chain_subscribeNewHeads", json!([]))?;
+    let events_key = to_hex(&metadata::storage_prefix("System", "Events"));
+    let mut next = after + 1;
+    loop {
+        let (subscription, head) = client.next_notification_until(deadline).map_err(|e| {
+            if Instant::now() >= deadline { "Timed out waiting for the chain to enact the upgrade".into() } else { e }
+        })?;
+        if subscription != heads {
+            continue;
+        }
+        let number = rpc::parse_hex_number(head["number"].as_str().unwrap_or_default())?;
+        // Blocks may be skipped between notifications, and before the subscription started
+        while next <= number {
+            let hash = client.request("chain_getBlockHash", json!([next]))?;
+            let hash = hash.as_str().ok_or_else(|| format!("The node has no block #{}", next))?.to_string();
+            let events = client.request("state_getStorage", json!([events_key, hash]))?;
+            let events = events.as_str().map(from_hex).transpose()?;
+            if watch::decode_events(metadata, events.as_deref())?.iter().any(|record| is_code_updated(&record["event"])) {
+                return Ok((next, hash));
+            }
+            next += 1;
+        }
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{entry, mock_runtime, pallet, runtime_metadata, system_pallet, ws_node, AccountId32, DispatchError, ModuleError, Phase, SystemEvent};
+    use frame_metadata::v14::{PalletCallMetadata, StorageEntryMetadata, StorageEntryModifier, StorageEntryType};
+    use parity_scale_codec::Encode;
+    use scale_info::{meta_type, TypeInfo};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::thread::JoinHandle;
+
+    #[allow(non_camel_case_types)]
+    #[derive(Encode, TypeInfo)]
+    enum SystemCall {
+        #[codec(index = 2)]
+        set_code { code: Vec<u8> },
+        #[codec(index = 9)]
+        authorize_upgrade { code_hash: [u8; 32] },
+        #[codec(index = 11)]
+        apply_authorized_upgrade { code: Vec<u8> },
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum RuntimeCall {
+        #[codec(index = 0)]
+        System(SystemCall),
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Encode, TypeInfo)]
+    enum SudoCall {
+        sudo { call: Box<RuntimeCall> },
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum SudoEvent {
+        Sudid { sudo_result: Result<(), DispatchError> },
+    }
+
+    #[derive(Encode, TypeInfo)]
+    enum RuntimeEvent {
+        #[codec(index = 0)]
+        System(SystemEvent),
+        #[codec(index = 5)]
+        Sudo(SudoEvent),
+    }
+
+    #[derive(Encode, TypeInfo)]
+    struct EventRecord {
+        phase: Phase,
+        event: RuntimeEvent,
+        topics: Vec<[u8; 32]>,
+    }
+
+    // A runtime with the upgrade calls of System and a Sudo pallet holding its `Key`
+    fn sudo_metadata() -> Vec<u8> {
+        let system = system_pallet(meta_type::<Vec<EventRecord>>(), meta_type::<SystemCall>());
+        let key = StorageEntryMetadata { modifier: StorageEntryModifier::Optional, ..entry("Key", StorageEntryType::Plain(meta_type::<AccountId32>()), vec![0]) };
+        let mut sudo = pallet("Sudo", 5, meta_type::<SudoEvent>(), vec![key]);
+        sudo.calls = Some(PalletCallMetadata { ty: meta_type::<SudoCall>() });
+        runtime_metadata(vec![system, sudo], meta_type::<RuntimeEvent>())
+    }
+
+    fn block_hash(number: u64) -> String {
+        format!("0x{:x}", number)
+    }
+
+    fn record(phase: Phase, event: RuntimeEvent) -> EventRecord {
+        EventRecord { phase, event, topics: vec![] }
+    }
+
+    fn sudid(result: Result<(), DispatchError>) -> EventRecord {
+        record(Phase::ApplyExtrinsic(1), RuntimeEvent::Sudo(SudoEvent::Sudid { sudo_result: result }))
+    }
+
+    fn code_updated(phase: Phase) -> EventRecord {
+        record(phase, RuntimeEvent::System(SystemEvent::CodeUpdated))
+    }
+
+    // The extrinsics the stand-in node was given
+    type Submitted = Arc<Mutex<Vec<String>>>;
+
+    // A stand-in asset-hub-westend node on spec_version 1000 with Alice as the sudo key. Whatever
+    // is submitted goes into block #300, and subscribers to new heads are told of block #302.
+    // Blocks have the events given by block number, and run spec_version 1001 from #300 on
+    fn node(events: Vec<(u64, Vec<EventRecord>)>) -> (String, JoinHandle<Vec<String>>, Submitted) {
+        let events: HashMap<String, Vec<u8>> = events.into_iter().map(|(number, records)| (block_hash(number), records.encode())).collect();
+        let sudo_key = to_hex(&metadata::storage_prefix("Sudo", "Key"));
+        let submitted = Arc::new(Mutex::new(Vec::new()));
+        let extrinsics = submitted.clone();
+        let (endpoint, handle) = ws_node(move |request| {
+            let reply = |result: Value| json!({ "jsonrpc": "2.0", "id": request["id"], "result": result });
+            let notify = |subscription: &str, result: Value| json!({ "jsonrpc": "2.0", "method": "notification", "params": { "subscription": subscription, "result": result } });
+            let params = &request["params"];
+            match request["method"].as_str().unwrap() {
+                "state_getMetadata" => vec![reply(json!(to_hex(&sudo_metadata())))],
+                "state_getRuntimeVersion" => {
+                    let spec_version = if params[0].is_null() { 1000 } else { 1001 };
+                    vec![reply(json!({ "specName": "asset-hub-westend", "specVersion": spec_version, "transactionVersion": 2 }))]
+                }
+                "state_getStorage" if params[0] == json!(sudo_key) => vec![reply(json!(to_hex(&AccountId32(ss58::decode(ALICE).unwrap().1).encode())))],
+                "state_getStorage" => vec![reply(events.get(params[1].as_str().unwrap()).map(|events| json!(to_hex(events))).unwrap_or_default())],
+                "chain_getBlockHash" if params[0] == json!(0) => vec![reply(json!(to_hex(&[9; 32])))],
+                "chain_getBlockHash" => vec![reply(json!(block_hash(params[0].as_u64().unwrap())))],
+                "system_accountNextIndex" => vec![reply(json!(5))],
+                "author_submitAndWatchExtrinsic" => {
+                    extrinsics.lock().unwrap().push(params[0].as_str().unwrap().to_string());
+                    vec![reply(json!("tx")), notify("tx", json!({ "inBlock": block_hash(300) }))]
+                }
+                "chain_getBlock" => {
+                    let extrinsic = extrinsics.lock().unwrap().last().cloned().unwrap();
+                    vec![reply(json!({ "block": { "header": { "number": "0x12c" }, "extrinsics": ["0x00", extrinsic] } }))]
+                }
+                "chain_subscribeNewHeads" => vec![reply(json!("heads")), notify("heads", json!({ "number": "0x12e" }))],
+                _ => vec![reply(Value::Null)],
+            }
+        });
+        (endpoint, handle, submitted)
+    }
+
+    const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+    fn alice() -> Signer {
+        Signer::from_suri("//Alice").unwrap()
+    }
+
+    // The encoded call ends the extrinsic
+    fn ends_with_call(extrinsic: &str, call: &[u8]) -> bool {
+        extrinsic.ends_with(&to_hex(call)[2..])
+    }
+
+    #[test]
+    fn test_set_code() {
+        let code = mock_runtime(1001);
+        let (endpoint, _, submitted) = node(vec![(300, vec![sudid(Ok(())), code_updated(Phase::ApplyExtrinsic(1))])]);
+
+        let mut out = Vec::new();
+        assert_eq!(upgrade(&endpoint, &code, &alice(), false, Duration::from_secs(5), &mut out).unwrap(), 1001);
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "Upgrading asset-hub-westend from spec_version 1000 to 1001");
+        assert_eq!(lines[3], "In block #300 0x12c");
+        assert!(lines[4].starts_with("  Sudo.Sudid {\"sudo_result\":{\"Ok\""));
+        assert_eq!(lines[5..], ["  System.CodeUpdated", "Upgraded to spec_version 1001 in block #300 0x12c"]);
+
+        let set_code = SudoCall::sudo { call: Box::new(RuntimeCall::System(SystemCall::set_code { code })) };
+        assert!(ends_with_call(&submitted.lock().unwrap()[0], &[vec![5], set_code.encode()].concat()));
+    }
+
+    #[test]
+    fn test_authorized_upgrade_enacted_later() {
+        let code = mock_runtime(1001);
+        let (endpoint, node, submitted) = node(vec![(300, vec![sudid(Ok(()))]), (302, vec![code_updated(Phase::Finalization)])]);
+
+        let mut out = Vec::new();
+        assert_eq!(upgrade(&endpoint, &code, &alice(), true, Duration::from_secs(5), &mut out).unwrap(), 1001);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.ends_with("Waiting for the chain to enact the upgrade\nUpgraded to spec_version 1001 in block #302 0x12e\n"));
+
+        let submitted = submitted.lock().unwrap().clone();
+        let authorize = SudoCall::sudo { call: Box::new(RuntimeCall::System(SystemCall::authorize_upgrade { code_hash: blake2_256(&code) })) };
+        assert!(ends_with_call(&submitted[0], &[vec![5], authorize.encode()].concat()));
+        let apply = SystemCall::apply_authorized_upgrade { code };
+        assert!(ends_with_call(&submitted[1], &[vec![0], apply.encode()].concat()));
+
+        // Blocks #301 and #302 are checked once the new head #302 comes in
+        let methods = node.join().unwrap();
+        let checked: Vec<&String> = methods.iter().skip_while(|method| *method != "chain_subscribeNewHeads").filter(|method| *method == "chain_getBlockHash").collect();
+        assert_eq!(checked.len(), 2);
+    }
+
+    #[test]
+    fn test_upgrade_failures() {
+        let (endpoint, _, _) = node(vec![(300, vec![sudid(Err(DispatchError::Module(ModuleError { index: 0, error: [0; 4] })))])]);
+        let error = upgrade(&endpoint, &mock_runtime(1001), &alice(), false, Duration::from_secs(5), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), "The upgrade failed: System.CallFiltered");
+
+        let (endpoint, _, _) = node(vec![(300, vec![sudid(Ok(()))])]);
+        let error = upgrade(&endpoint, &mock_runtime(1001), &alice(), false, Duration::from_millis(200), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), "Timed out waiting for the chain to enact the upgrade");
+    }
+
+    #[test]
+    fn test_upgrade_checks() {
+        let (endpoint, _, submitted) = node(vec![]);
+        let error = upgrade(&endpoint, &mock_runtime(1000), &alice(), false, Duration::from_secs(5), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), "The wasm has spec_version 1000, which must be higher than the running 1000");
+
+        let (endpoint, _, _) = node(vec![]);
+        let bob = Signer::from_suri("//Bob").unwrap();
+        let error = upgrade(&endpoint, &mock_runtime(1001), &bob, false, Duration::from_secs(5), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), format!("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty is not the sudo key of the chain, {} is", ALICE));
+
+        // The wasm is checked before connecting
+        let error = upgrade("ws://127.0.0.1:1", b"not wasm", &alice(), false, Duration::from_secs(5), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), "Not a wasm module");
+        assert!(submitted.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+use std::error::Error;
+use std::io::Write;
+use parity_scale_codec::{Compact, Encode};
+use serde_json::{json, Value};
+use frame_metadata::v14::StorageEntryType;
+use crate::hashing::{blake2_256, from_hex, to_hex};
+use crate::metadata::{self, Metadata};
+use crate::rpc::{self, WsClient};
+use crate::scale;
+
+// The node reports headers without their hash, so it is computed from the SCALE encoded header
+pub fn header_hash(header: &Value) -> Result<String, Box<dyn Error>> {
+    let field = |name: &str| -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(from_hex(header[name].as_str().ok_or_else(|| format!("Header has no {}", name))?)?)
+    };
+    let number = rpc::parse_hex_number(header["number"].as_str().ok_or("Header has no number")?)?;
+    let logs = header["digest"]["logs"].as_array().ok_or("Header has no digest")?;
+
+    let mut encoded = field("parentHash")?;
+    encoded.extend(Compact(number).encode());
+    encoded.extend(field("stateRoot")?);
+    encoded.extend(field("extrinsicsRoot")?);
+    encoded.extend(Compact(logs.len() as u32).encode());
+    for log in logs {
+        // Every digest item is already given SCALE encoded
+        encoded.extend(from_hex(log.as_str().ok_or("Invalid digest item")?)?);
+    }
+    Ok(to_hex(&blake2_256(&encoded)))
+}
+
+// The event records of a block from its encoded System.Events, each with its phase, event and topics
+pub fn decode_events(metadata: &Metadata, events: Option<&[u8]>) -> Result<Vec<Value>, Box<dyn Error>> {
+    let (_, entry) = metadata.storage_entry("System", "Events")?;
+    let type_id = match &entry.ty {
+        StorageEntryType::Plain(ty) => ty.id,
+        StorageEntryType::Map { .. } => return Err("System.Events is not a plain storage value".into()),
+    };
+    let records = match events {
+        Some(mut events) => scale::decode_value(&metadata.types, type_id, &mut events)?,
+        None => json!([]),
+    };
+    match records {
+        Value::Array(records) => Ok(records),
+        _ => Err("System.Events is not a list".into()),
+    }
+}
+
+// Counts the events of a block by `Pallet.Event`, in the order they first occur
+pub fn events_summary(metadata: &Metadata, events: Option<&[u8]>) -> Result<Vec<(String, usize)>, Box<dyn Error>> {
+    let mut summary: Vec<(String, usize)> = Vec::new();
+    for record in decode_events(metadata, events)? {
+        let name = event_name(&record["event"]);
+        match summary.iter_mut().find(|(known, _)| *known == name) {
+            Some((_, count)) => *count += 1,
+            None => summary.push((name, 1)),
+        }
+    }
+    Ok(summary)
+}
+
+// Events decode as {"Pallet": "Event"} or {"Pallet": {"Event": fields}}
+pub fn event_name(event: &Value) -> String {
+    let variant = |value: &Value| match value {
+        Value::String(name) => Some(name.clone()),
+        Value::Object(object) => object.keys().next().cloned(),
+        _ => None,
+    };
+    match event.as_object().and_then(|object| object.iter().next()) {
+        Some((pallet, inner)) => match variant(inner) {
+            Some(name) => format!("{}.{}", pallet, name),
+            None => pallet.clone(),
+        },
+        None => "unknown".to_string(),
+    }
+}
+
+pub fn format_summary(summary: &[(String, usize)]) -> String {
+    let total: usize = summary.iter().map(|(_, count)| count).sum();
+    if total == 0 {
+        return "no events".to_string();
+    }
+    let names: Vec<String> = summary
+        .iter()
+        .map(|(name, count)| if *count == 1 { name.clone() } else { format!("{} ({})", name, count) })
+        .collect();
+    format!("{} event{}: {}", total, if total == 1 { "" } else { "s" }, names.join(", "))
+}
+
+// Prints every new best and finalized block until `count` new blocks were seen. Metadata is
+// reloaded when the runtime is upgraded, so events keep decoding
+pub fn watch<W: Write>(endpoint: &str, count: Option<u64>, out: &mut W) -> Result<(), Box<dyn Error>> {
+    let mut client = WsClient::connect(&rpc::ws_endpoint(endpoint))?;
+    let (_, mut metadata) = metadata::from_rpc(&client.request("state_getMetadata", json!([]))?)?;
+    let runtime = client.start_subscription("state_subscribeRuntimeVersion", json!([]))?;
+    let best = client.start_subscription("chain_subscribeNewHeads", json!([]))?;
+    let finalized = client.start_subscription("chain_subscribeFinalizedHeads", json!([]))?;
+    let events_key = to_hex(&metadata::storage_prefix("System", "Events"));
+
+    let mut spec_version = None;
+    let mut seen = 0;
+    loop {
+        let (subscription, item) = client.next_notification()?;
+        if subscription == runtime {
+            let version = item["specVersion"].as_u64();
+            if spec_version.is_some() && version != spec_version {
+                metadata = metadata::from_rpc(&client.request("state_getMetadata", json!([]))?)?.1;
+                writeln!(out, "runtime   upgraded to {} version {}", item["specName"].as_str().unwrap_or("runtime"), version.unwrap_or_default())?;
+            }
+            spec_version = version;
+        } else if subscription == finalized {
+            let number = rpc::parse_hex_number(item["number"].as_str().unwrap_or_default())?;
+            writeln!(out, "finalized #{:<8} {}", number, header_hash(&item)?)?;
+        } else if subscription == best {
+            let number = rpc::parse_hex_number(item["number"].as_str().unwrap_or_default())?;
+            let hash = header_hash(&item)?;
+            let block = client.request("chain_getBlock", json!([hash]))?;
+            let extrinsics = block["block"]["extrinsics"].as_array().map(Vec::len).unwrap_or_default();
+            let events = client.request("state_getStorage", json!([events_key, hash]))?;
+            let events = match events.as_str().map(from_hex).transpose()? {
+                Some(bytes) => events_summary(&metadata, Some(&bytes)),
+                None => events_summary(&metadata, None),
+            };
+            let events = events.map(|summary| format_summary(&summary)).unwrap_or_else(|e| format!("events unavailable: {}", e));
+            writeln!(out, "best      #{:<8} {}  {} extrinsic{}, {}", number, hash, extrinsics, if extrinsics == 1 { "" } else { "s" }, events)?;
+
+            seen += 1;
+            if count.is_some_and(|count| seen >= count) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+
+/// =================================================================================================
+/// Test Module
+/// =================================================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{test_metadata, ws_node, BalancesEvent, EventRecord, Phase, RuntimeEvent, SystemEvent};
+
+    fn header(number: &str) -> Value {
+        json!({
+            "parentHash": to_hex(&[1; 32]),
+            "number": number,
+            "stateRoot": to_hex(&[2; 32]),
+            "extrinsicsRoot": to_hex(&[3; 32]),
+            "digest": { "logs": ["0x0661757261200000000000000000", "0x0561757261"] },
+        })
+    }
+
+    fn events() -> Vec<u8> {
+        let record = |event| EventRecord { phase: Phase::ApplyExtrinsic(0), event, topics: vec![] };
+        vec![
+            record(RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 1 })),
+            record(RuntimeEvent::Balances(BalancesEvent::Transfer { amount: 5 })),
+            record(RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { weight: 2 })),
+            EventRecord { phase: Phase::Finalization, event: RuntimeEvent::System(SystemEvent::CodeUpdated), topics: vec![[7; 32]] },
+        ]
+        .encode()
+    }
+
+    #[test]
+    fn test_header_hash() {
+        assert_eq!(header_hash(&header("0x12c")).unwrap(), "0x582cb555923d37ec78f6b4e056f03d6deda396301ba209f31516e05920e0c7a5");
+        assert_eq!(header_hash(&json!({ "number": "0x1" })).unwrap_err().to_string(), "Header has no digest");
+    }
+
+    #[test]
+    fn test_events_summary() {
+        let metadata = metadata::decode(&test_metadata()).unwrap();
+        let summary = events_summary(&metadata, Some(&events())).unwrap();
+        assert_eq!(format_summary(&summary), "4 events: System.ExtrinsicSuccess (2), Balances.Transfer, System.CodeUpdated");
+        assert_eq!(format_summary(&events_summary(&metadata, None).unwrap()), "no events");
+        assert!(events_summary(&metadata, Some(&[4, 9])).is_err());
+    }
+
+    #[test]
+    fn test_watch() {
+        let events = to_hex(&events());
+        let (endpoint, _node) = ws_node(move |request| {
+            let reply = |result: Value| json!({ "jsonrpc": "2.0", "id": request["id"], "result": result });
+            let notify = |subscription: &str, result: Value| json!({ "jsonrpc": "2.0", "method": "notification", "params": { "subscription": subscription, "result": result } });
+            match request["method"].as_str().unwrap() {
+                "state_getMetadata" => vec![reply(json!(to_hex(&test_metadata())))],
+                "state_subscribeRuntimeVersion" => vec![reply(json!("runtime")), notify("runtime", json!({ "specName": "dev", "specVersion": 1 }))],
+                "chain_subscribeNewHeads" => vec![reply(json!("best"))],
+                "chain_subscribeFinalizedHeads" => vec![
+                    reply(json!("finalized")),
+                    notify("finalized", header("0x12b")),
+                    notify("runtime", json!({ "specName": "dev", "specVersion": 2 })),
+                    notify("best", header("0x12c")),
+                ],
+                "chain_getBlock" => vec![reply(json!({ "block": { "extrinsics": ["0x01", "0x02"] } }))],
+                "state_getStorage" => vec![reply(json!(events))],
+                _ => vec![reply(Value::Null)],
+            }
+        });
+
+        let mut out = Vec::new();
+        watch(&endpoint.replace("ws://", "http://"), Some(1), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[0].starts_with("finalized #299      0x"));
+        assert_eq!(lines[1], "runtime   upgraded to dev version 2");
+        assert_eq!(lines[2], "best      #300      0x582cb555923d37ec78f6b4e056f03d6deda396301ba209f31516e05920e0c7a5  2 extrinsics, 4 events: System.ExtrinsicSuccess (2), Balances.Transfer, System.CodeUpdated");
+        assert_eq!(lines.len(), 3);
+    }
+}